@@ -1,10 +1,21 @@
+use crate::app::NotificationLevel;
+use crate::ssh::connection::Disconnect;
+use crate::ssh::discovery::RemoteListener;
+use crate::ssh::health::TunnelTest;
+use crate::ssh::port_owner::PortOwner;
+use crate::ssh::tunnel::Tunnel;
+use crate::ui::input::EditKey;
+use crate::ui::keymap::KeyCombo;
 use uuid::Uuid;
 
 /// All possible actions in the application (TEA pattern).
 pub enum Action {
     Tick,
     Render,
+    /// Show a message in the status bar.
+    Notify(String, NotificationLevel),
     Quit,
+    Shutdown,
     NavigateUp,
     NavigateDown,
     Select,
@@ -14,26 +25,160 @@ pub enum Action {
     SearchBackspace,
     EndSearch,
     ShowHelp,
+    ShowHostMenu,
+    ToggleZoom,
+    /// Fold disabled tunnels into a single summary row, or show them again.
+    ToggleCollapseDisabled,
+    /// Mask hostnames, addresses and users on screen, or show them again.
+    ToggleSafeMode,
 
     // Connection actions
     Connect(usize),
+    /// Ask for a user/port override, then connect to a session-only variant.
+    ConnectWithOverrides(usize),
+    QuickConnect(usize),
+    /// Send a Wake-on-LAN packet to the host, then connect once it answers.
+    WakeHost(usize),
+    WakeFailed(String),
+    /// A woken host answered on its ssh port.
+    HostAwake(String),
+    CycleHostSort,
+    HostCanonicalized(usize, Option<String>),
+    /// The `[[prerequisites]]` of the host with this alias hold, or why not.
+    PrerequisitesChecked(String, Result<(), String>),
+    ToggleAgentForwarding,
+    CycleX11Forwarding,
     ConnectionEstablished,
+    /// The host's control socket is served by a master another alias started.
+    SocketCollision(usize, String),
     ConnectionFailed(String),
+    /// Background sweep result: host name, reachable, resolved address.
+    /// Host name, reachable, resolved address, handshake time in ms.
+    HostReachability(String, bool, Option<String>, Option<u64>),
+    ReachabilitySweepDone,
+    /// Number of other ssh sessions sharing the current master.
+    SharedSessions(usize),
     Disconnect,
-    Disconnected,
+    /// The master is gone, or was given up on.
+    Disconnected(Disconnect),
+    /// Process id of the connected master.
+    MasterPid(u32),
 
     // Tunnel actions
     ShowAddTunnelModal,
+    QuickAddFromClipboard,
     ModalInput(char),
     ModalBackspace,
+    /// Cursor movement or deletion in the focused text field.
+    ModalEdit(EditKey),
     ModalNextField,
+    ModalPreviousField,
     ModalSubmit,
-    TunnelFailed(String),
+    /// Who listens on the local port the add-tunnel modal found taken.
+    PortInUse(u16, Option<PortOwner>),
+    /// Outcome of stopping that process; the modal is submitted again on success.
+    PortFreed(Result<(), String>),
+    TunnelFailed(Uuid, String),
+    /// Enable every tunnel, or disable them all if all are on.
+    ToggleAllTunnels,
+    /// Try enabling a failed tunnel again.
+    RetryTunnel(usize),
+    /// Enter or leave visual mode in the tunnels panel.
+    ToggleVisualMode,
+    /// Mark or unmark a tunnel in visual mode.
+    MarkTunnel(usize),
+    ToggleMarkedTunnels,
+    DeleteMarkedTunnels,
+    /// Batch toggle finished: enabling?, tunnels attempted, failure messages.
+    /// Each failed tunnel got its own `TunnelFailed` first.
+    TunnelBatchDone(bool, usize, Vec<String>),
+    /// Probe results for enabled tunnels: alive, and open time in ms.
+    TunnelLiveness(Vec<(Uuid, bool, Option<u64>)>),
+    /// Established client connections on each -L tunnel's local port.
+    TunnelClients(Vec<(Uuid, usize)>),
+    /// Ask which connection, tunnel or process holds a local port.
+    ShowPortLookup,
+    /// Who holds the looked-up port, as a sentence.
+    PortLookupResult(String),
+    /// Compare the forwards the master actually listens on with the tunnel list.
+    AuditForwards,
+    /// Ports the master listens on; `None` if they couldn't be listed.
+    ForwardAudit(Option<Vec<u16>>),
+    /// Take the selected orphaned forward into the tunnel list.
+    AdoptForward,
+    /// The master confirmed an adopted forward; list it as enabled.
+    ForwardAdopted(Box<Tunnel>),
+    /// Cancel the selected orphaned forward on the master.
+    CancelForward,
+    /// List the ports listening on the remote host, to forward one.
+    DiscoverRemotePorts,
+    /// The remote host's listeners, or why they couldn't be listed.
+    RemotePorts(Result<Vec<RemoteListener>, String>),
+    /// Forward the selected remote port on a suggested local port.
+    ForwardDiscoveredPort,
+    /// Open the add modal pre-filled for the selected remote port.
+    EditDiscoveredPort,
+    /// What answers on a tunnel, to label it with.
+    TunnelIdentified(Uuid, String),
+    /// A problem with an enabled tunnel that doesn't stop it working.
+    TunnelWarning(Uuid, String),
     ToggleTunnel(usize),
+    /// Bring a tunnel to this state, e.g. on schedule; does nothing if it is
+    /// there already or a change is in flight.
+    SetTunnelEnabled(Uuid, bool),
     TunnelToggled(Uuid, bool),
+    /// An in-flight change to this tunnel finished and left it as it was.
+    TunnelSettled(Uuid),
     DeleteTunnel(usize),
+    /// Edit the remote host:port of the tunnel at this index in place.
+    EditTunnel(usize),
+    /// Open the add modal pre-filled from a tunnel, on the next free port.
+    DuplicateTunnel(usize),
+    EditSessionNote,
+    EditTunnelNote(usize),
+    /// Set how long the tunnel may sit idle before it is disabled.
+    EditIdleTimeout(usize),
+    /// Set the most bytes per second the tunnel may carry each way.
+    EditBandwidthLimit(usize),
+    /// Keep the tunnel out of history, or save it again.
+    ToggleEphemeral(usize),
+    /// Connect through the tunnel now and report what answered.
+    TestTunnel(usize),
+    TunnelTested(Uuid, TunnelTest),
+    /// Open the tunnel's local end in the browser.
+    OpenTunnelUrl(usize),
+    /// Switch the tunnel between opening as http and https.
+    ToggleTunnelHttps(usize),
+    /// Set the daily window the tunnel is kept up in.
+    EditSchedule(usize),
+    /// Put the tunnel at this index in a group, or take it out of one.
+    EditTunnelGroup(usize),
+    /// Enable or disable every tunnel in the group of the tunnel at this index.
+    ToggleGroup(usize),
+    /// Fold the group of the tunnel at this index to one row, or unfold it.
+    FoldGroup(usize),
     TunnelDeleted(Uuid),
+    /// Switch the tunnel list to the next sort order.
+    CycleTunnelSort,
 
     // Persistence
     RestoreTunnels,
+    ShowRestoreModal,
+    ShowSettings,
+    /// Write the settings screen's draft to config.toml and apply it.
+    SaveSettings,
+    /// Copy and show the plain ssh command for the connection and its
+    /// enabled tunnels.
+    ShowSshCommand,
+    /// Open the key binding editor.
+    ShowKeymap,
+    /// Wait for the new key of the command selected in the key editor.
+    StartRebind,
+    BindKey(KeyCombo),
+    /// Put the selected command back on its default key.
+    ResetKey,
+    /// Write the key editor's bindings to `[keys]` in config.toml.
+    SaveKeymap,
+    RestoreToggleItem,
+    RestoreToggleAll,
 }
@@ -1,14 +1,34 @@
 use ratatui::widgets::ListState;
-use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::sync::mpsc;
+use uuid::Uuid;
 
 use crate::action::Action;
-use crate::ssh::config::SshHost;
-use crate::ssh::connection::ConnectionManager;
-use crate::ssh::tunnel::Tunnel;
-use crate::state::history::History;
-use crate::state::persistence::AppConfig;
-use crate::ui::add_modal::AddModalState;
+use crate::notify::{Event, EventKind, Notifier};
+use crate::ssh::config::{SshHost, X11Forwarding};
+use crate::ssh::connection::{ConnectionManager, Disconnect};
+use crate::ssh::discovery::RemoteListener;
+use crate::ssh::health::{MuxStats, TunnelTest};
+use crate::ssh::preflight::SshUnavailable;
+use crate::ssh::tunnel::{Tunnel, TunnelDirection, TunnelSort, TunnelStatus};
+use crate::state::history::{History, HostEventKind, SavedTunnel};
+use crate::state::persistence::{AppConfig, HostSort, ServicePreset};
+use crate::state::reachability::ReachabilityCache;
+use crate::ui::add_modal::{AddModalState, ModalField};
+use crate::ui::forward_audit::ForwardAuditState;
+use crate::ui::host_details::HostDetailsState;
+use crate::ui::host_menu::{HostMenuItem, HostMenuState};
+use crate::ui::keymap::{Keymap, KeymapEditorState};
+use crate::ui::port_conflict::{self, ConflictChoice, PortConflictState};
+use crate::ui::port_discovery::PortDiscoveryState;
+use crate::ui::prompt::{PromptKind, PromptState};
+use crate::ui::restore_modal::RestoreModalState;
+use crate::ui::safe_mode::Masker;
+use crate::ui::settings::{Setting, SettingsState};
+use crate::ui::socket_collision::{CollisionChoice, SocketCollisionState};
+use crate::ui::ssh_command::SshCommandState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Panel {
@@ -21,6 +41,8 @@ pub enum ConnectionStatus {
     Disconnected,
     Connecting,
     Connected(String),
+    /// `-O exit` sent to this host's master, cleanup still running.
+    Disconnecting(String),
     Error(String),
 }
 
@@ -33,24 +55,84 @@ pub struct App {
     pub search_mode: bool,
     pub filtered_host_indices: Vec<usize>,
     pub show_help: bool,
+    pub zoomed: bool,
+    /// Show only enabled tunnels, with the disabled ones counted in one row.
+    pub collapse_disabled: bool,
+    /// Hostnames, addresses and users are masked for screen sharing.
+    pub safe_mode: bool,
+    pub host_sort: HostSort,
+    /// `hosts` is due a sort that waits for `host_indices_free`.
+    hosts_unsorted: bool,
+    pub host_menu: Option<HostMenuState>,
+    pub host_details: Option<HostDetailsState>,
+    /// Restore saved tunnels once the pending connection is established.
+    pub restore_on_connect: bool,
+    /// Host whose prerequisites just passed; the next connect to it skips them.
+    prerequisites_met: Option<String>,
     pub connection: Option<ConnectionManager>,
     pub connection_status: ConnectionStatus,
+    /// Other ssh sessions multiplexed over the current master.
+    pub shared_sessions: usize,
+    /// Why the current connection is up, shown in the status bar (`n`).
+    pub session_note: Option<String>,
+    /// Set after a first `x` on a shared master; the next one disconnects.
+    pub disconnect_armed: bool,
     pub action_tx: mpsc::UnboundedSender<Action>,
+    /// Set at startup when no usable OpenSSH client was found.
+    pub ssh_unavailable: Option<SshUnavailable>,
     pub socket_dir: PathBuf,
+    /// ssh config files the host list was read from.
+    pub ssh_config_paths: Vec<PathBuf>,
     pub tick_count: u32,
+    /// Events were logged since history was last written.
+    history_dirty: bool,
 
     // Tunnel state
     pub tunnels: Vec<Tunnel>,
     pub tunnel_list_state: ListState,
+    /// Selection and scroll of the shown rows, when group headers make them
+    /// differ from `tunnels`.
+    pub tunnel_rows_state: ListState,
+    /// Sort order of `tunnels`, saved per host.
+    pub tunnel_sort: TunnelSort,
+    /// Tunnel groups folded to one row, saved per host.
+    pub collapsed_groups: HashSet<String>,
+    /// Tunnels marked in visual mode (`v`); `None` outside it.
+    pub tunnel_marks: Option<HashSet<Uuid>>,
     pub add_modal: Option<AddModalState>,
+    pub prompt: Option<PromptState>,
+    pub settings: Option<SettingsState>,
+    /// Keys moved away from their defaults in `[keys]`.
+    pub keymap: Keymap,
+    pub key_editor: Option<KeymapEditorState>,
+    pub restore_modal: Option<RestoreModalState>,
+    /// Forwards found on the master that the tunnel list doesn't account for.
+    pub forward_audit: Option<ForwardAuditState>,
+    pub port_discovery: Option<PortDiscoveryState>,
+    /// What the last port discovery found on this connection, offered as
+    /// remote hosts in the add modal.
+    pub remote_listeners: Vec<RemoteListener>,
+    pub socket_collision: Option<SocketCollisionState>,
+    /// Ways past a taken local port, over the add-tunnel modal.
+    pub port_conflict: Option<PortConflictState>,
+    /// The equivalent ssh command, just copied to the clipboard.
+    pub ssh_command: Option<SshCommandState>,
+    /// Restored tunnels whose local port is taken, waiting for a new one.
+    pub remap_queue: Vec<Uuid>,
+    /// Tunnels waiting for the host's `max_forwards` to leave room, oldest first.
+    pub forward_queue: Vec<Uuid>,
 
     // Persistence
     pub config: AppConfig,
     pub history: History,
+    pub reachability: ReachabilityCache,
+    pub sweep_running: bool,
 
     // Notifications
     pub notification: Option<Notification>,
     pub notification_ticks: u32,
+    /// Delivers connection events to the status bar and configured sinks.
+    pub notifier: Notifier,
 }
 
 #[derive(Debug, Clone)]
@@ -70,7 +152,35 @@ impl App {
     pub fn new(action_tx: mpsc::UnboundedSender<Action>) -> Self {
         let config = AppConfig::load();
         let history = History::load();
-        let socket_dir = config.general.socket_dir.clone();
+        let socket_dir = crate::ssh::connection::resolve_socket_dir(&config.general.socket_dir);
+        let ssh_config_paths = config.general.ssh_configs();
+        let collapse_disabled = config.ui.collapse_disabled_tunnels;
+        let notifier = Notifier::new(&config.notifications, action_tx.clone());
+        let (keymap, key_errors) = Keymap::from_config(&config.keys);
+        let history_errors = history
+            .load_error
+            .iter()
+            .map(|e| format!("history.json couldn't be read ({e}); it won't be saved until fixed"));
+        let config_errors = config
+            .load_error
+            .iter()
+            .map(|e| format!("config.toml couldn't be read ({e}); running on defaults"));
+        let mut startup_errors = key_errors
+            .into_iter()
+            .chain(config_errors)
+            .chain(config.warnings.iter().cloned())
+            .chain(history_errors)
+            .chain(history.warnings.iter().cloned());
+        let notification = match startup_errors.next() {
+            Some(message) => Some(Notification {
+                message,
+                level: NotificationLevel::Error,
+            }),
+            None => socket_dir.fallback_reason.map(|message| Notification {
+                message,
+                level: NotificationLevel::Info,
+            }),
+        };
 
         Self {
             running: true,
@@ -81,42 +191,136 @@ impl App {
             search_mode: false,
             filtered_host_indices: Vec::new(),
             show_help: false,
+            zoomed: false,
+            collapse_disabled,
+            safe_mode: config.ui.safe_mode,
+            host_sort: config.ui.host_sort,
+            hosts_unsorted: false,
+            host_menu: None,
+            host_details: None,
+            restore_on_connect: false,
+            prerequisites_met: None,
             connection: None,
             connection_status: ConnectionStatus::Disconnected,
+            shared_sessions: 0,
+            session_note: None,
+            disconnect_armed: false,
             action_tx,
-            socket_dir,
+            ssh_unavailable: None,
+            socket_dir: socket_dir.path,
+            ssh_config_paths,
             tick_count: 0,
+            history_dirty: false,
             tunnels: Vec::new(),
             tunnel_list_state: ListState::default(),
+            tunnel_rows_state: ListState::default(),
+            tunnel_sort: TunnelSort::default(),
+            collapsed_groups: HashSet::new(),
+            tunnel_marks: None,
             add_modal: None,
+            prompt: None,
+            settings: None,
+            keymap,
+            key_editor: None,
+            restore_modal: None,
+            forward_audit: None,
+            port_discovery: None,
+            remote_listeners: Vec::new(),
+            socket_collision: None,
+            port_conflict: None,
+            ssh_command: None,
+            remap_queue: Vec::new(),
+            forward_queue: Vec::new(),
             config,
             history,
-            notification: None,
+            reachability: ReachabilityCache::load(),
+            sweep_running: false,
+            notification,
             notification_ticks: 0,
+            notifier,
         }
     }
 
-    pub fn load_hosts(&mut self, ssh_config_path: &Path) {
-        match crate::ssh::config::parse_ssh_config(ssh_config_path) {
-            Ok(hosts) => {
-                self.hosts = hosts;
-                self.rebuild_filtered_indices();
-                if !self.filtered_host_indices.is_empty() {
-                    self.host_list_state.select(Some(0));
-                }
+    pub fn load_hosts(&mut self, ssh_config_paths: &[PathBuf]) {
+        self.ssh_config_paths = ssh_config_paths.to_vec();
+        let (mut hosts, errors) = crate::ssh::config::parse_ssh_configs(ssh_config_paths);
+        if let Some(error) = errors.first() {
+            self.notify(error.clone(), NotificationLevel::Error);
+        }
+        let mut bad_proxy = None;
+        for host in &mut hosts {
+            host.known_hosts_file = self.config.general.known_hosts_file.clone();
+            let host_config = self.config.hosts.get(&host.name);
+            if let Some(host_config) = host_config {
+                host_config.apply(host);
             }
-            Err(_) => {
-                self.hosts = Vec::new();
-                self.filtered_host_indices = Vec::new();
+            // stm's proxy only fills in for hosts without their own proxying
+            let proxy =
+                host_config
+                    .and_then(|c| c.proxy.as_ref())
+                    .or(self.config.general.proxy.as_ref());
+            if let Some(url) = proxy {
+                if host.proxy_command.is_none() && host.proxy_jump.is_empty() {
+                    host.proxy_command = crate::ssh::proxy::proxy_command(url);
+                    if host.proxy_command.is_none() {
+                        bad_proxy = Some(url.clone());
+                    }
+                }
             }
         }
+        if let Some(url) = bad_proxy {
+            self.notify(
+                format!("Ignoring proxy '{url}': expected http:// or socks5://host:port"),
+                NotificationLevel::Error,
+            );
+        }
+        self.hosts = hosts;
+        self.rebuild_filtered_indices();
+        if !self.filtered_host_indices.is_empty() {
+            self.host_list_state.select(Some(0));
+        }
     }
 
     pub fn update(&mut self, action: Action) {
         match action {
             Action::Quit => {
-                if self.add_modal.is_some() {
+                if self.port_conflict.is_some() {
+                    self.port_conflict = None;
+                } else if self.add_modal.is_some() {
                     self.add_modal = None;
+                } else if self.prompt.is_some() {
+                    // Skipping a remap moves on to the next conflicting tunnel
+                    self.prompt = None;
+                    self.open_next_remap_prompt();
+                } else if self.socket_collision.is_some() {
+                    self.socket_collision = None;
+                } else if self.restore_modal.is_some() {
+                    self.restore_modal = None;
+                } else if self.forward_audit.is_some() {
+                    self.forward_audit = None;
+                } else if self.port_discovery.is_some() {
+                    self.port_discovery = None;
+                } else if self.ssh_command.is_some() {
+                    self.ssh_command = None;
+                } else if let Some(ref mut settings) = self.settings {
+                    // Esc while typing only abandons the edit
+                    if settings.editing.take().is_none() {
+                        self.settings = None;
+                    }
+                    if let Some(ref mut settings) = self.settings {
+                        settings.error_message = None;
+                    }
+                } else if let Some(ref mut editor) = self.key_editor {
+                    // Esc while waiting for a key only cancels the rebind
+                    if editor.capturing {
+                        editor.capturing = false;
+                    } else {
+                        self.key_editor = None;
+                    }
+                } else if self.host_menu.is_some() {
+                    self.host_menu = None;
+                } else if self.host_details.is_some() {
+                    self.host_details = None;
                 } else if self.search_mode {
                     self.search_mode = false;
                     self.search_query.clear();
@@ -127,51 +331,60 @@ impl App {
                     self.running = false;
                 }
             }
+            Action::Shutdown => {
+                self.running = false;
+            }
             Action::Tick => {
                 self.tick_count += 1;
+                if self.hosts_unsorted {
+                    self.sort_hosts_when_free();
+                }
+                self.sync_history();
+                self.flush_history();
                 // Auto-dismiss notifications after ~4 seconds (16 ticks)
                 if self.notification.is_some() {
                     self.notification_ticks += 1;
                     if self.notification_ticks >= 16 {
                         self.notification = None;
+                        self.disconnect_armed = false;
                     }
                 }
+                if self.tick_count.is_multiple_of(4) {
+                    self.sample_traffic();
+                }
+                if self.tick_count.is_multiple_of(8) {
+                    self.count_tunnel_clients();
+                }
                 if self.tick_count.is_multiple_of(40) {
-                    if let ConnectionStatus::Connected(_) = &self.connection_status {
-                        let tx = self.action_tx.clone();
-                        if let Some(ref conn) = self.connection {
-                            let socket = conn.socket_path().clone();
-                            let target = conn.host().display_target();
-                            tokio::spawn(async move {
-                                let check_result = tokio::process::Command::new("ssh")
-                                    .args(["-S", &socket.to_string_lossy(), "-O", "check", &target])
-                                    .stdin(std::process::Stdio::null())
-                                    .stdout(std::process::Stdio::null())
-                                    .stderr(std::process::Stdio::null())
-                                    .output()
-                                    .await;
-
-                                match check_result {
-                                    Ok(output) if !output.status.success() => {
-                                        let _ = tx.send(Action::ConnectionFailed(
-                                            "Connection lost".to_string(),
-                                        ));
-                                    }
-                                    Err(e) => {
-                                        let _ = tx.send(Action::ConnectionFailed(e.to_string()));
-                                    }
-                                    _ => {}
-                                }
-                            });
-                        }
-                    }
+                    self.disable_idle_tunnels();
+                    self.apply_schedules();
+                    self.schedule_health_checks();
+                    self.probe_tunnels();
+                    self.start_reachability_sweep();
                 }
             }
             Action::Render => {}
+            Action::Notify(message, level) => self.notify(message, level),
             Action::NavigateUp => self.navigate(-1),
             Action::NavigateDown => self.navigate(1),
             Action::Select => {
-                if self.active_panel == Panel::Hosts {
+                if let Some(menu) = self.host_menu.take() {
+                    self.run_host_menu_item(menu.host_idx, menu.item());
+                } else if let Some(conflict) = self.port_conflict.take() {
+                    self.resolve_port_conflict(conflict);
+                } else if let Some(collision) = self.socket_collision.take() {
+                    self.resolve_socket_collision(collision);
+                } else if let Some(modal) = self.restore_modal.take() {
+                    let chosen = modal
+                        .chosen()
+                        .into_iter()
+                        .map(|st| SavedTunnel {
+                            enabled: true,
+                            ..st
+                        })
+                        .collect();
+                    self.restore_saved(chosen);
+                } else if self.active_panel == Panel::Hosts {
                     if let Some(selected) = self.host_list_state.selected() {
                         if let Some(&real_idx) = self.filtered_host_indices.get(selected) {
                             let _ = self.action_tx.send(Action::Connect(real_idx));
@@ -219,18 +432,127 @@ impl App {
             Action::ShowHelp => {
                 self.show_help = !self.show_help;
             }
+            Action::ShowHostMenu => {
+                if self.active_panel == Panel::Hosts {
+                    if let Some(selected) = self.host_list_state.selected() {
+                        if let Some(&real_idx) = self.filtered_host_indices.get(selected) {
+                            self.host_menu = Some(HostMenuState::new(real_idx));
+                        }
+                    }
+                }
+            }
+            Action::ToggleZoom => {
+                self.zoomed = !self.zoomed;
+            }
+            Action::ToggleCollapseDisabled => {
+                self.collapse_disabled = !self.collapse_disabled;
+                self.fix_tunnel_selection();
+            }
+            Action::ToggleSafeMode => {
+                self.safe_mode = !self.safe_mode;
+                let state = if self.safe_mode { "on" } else { "off" };
+                self.notify(format!("Safe mode {state}"), NotificationLevel::Info);
+            }
 
             // Connection actions
+            Action::ConnectWithOverrides(idx) => {
+                if let Some(host) = self.hosts.get(idx).filter(|h| !h.is_pattern()) {
+                    let mut prompt = PromptState::new(
+                        PromptKind::ConnectOverride(idx),
+                        format!("Connect to {} as", host.name),
+                        "user:port",
+                    );
+                    prompt.value.set(format!(
+                        "{}:{}",
+                        host.user.as_deref().unwrap_or_default(),
+                        host.effective_port()
+                    ));
+                    self.prompt = Some(prompt);
+                }
+            }
             Action::Connect(idx) => {
+                if self.ssh_unavailable.is_some() {
+                    return;
+                }
+                // The old master may still hold the socket the new one wants
+                if let ConnectionStatus::Disconnecting(ref name) = self.connection_status {
+                    let message = format!("Still disconnecting from {name}");
+                    self.notify(message, NotificationLevel::Info);
+                    return;
+                }
+                // Pattern hosts need a concrete hostname first
+                if let Some(host) = self.hosts.get(idx).filter(|h| h.is_pattern()) {
+                    self.prompt = Some(PromptState::new(
+                        PromptKind::InstantiatePattern(idx),
+                        format!("Connect to {}", host.name),
+                        "Hostname:",
+                    ));
+                    return;
+                }
+                // A VPN that isn't up fails here at once, not as an ssh timeout
+                let checked =
+                    self.hosts.get(idx).map(|h| &h.name) == self.prerequisites_met.as_ref();
+                if !checked {
+                    if let Some(host) = self.hosts.get(idx) {
+                        let prerequisites: Vec<_> = self
+                            .config
+                            .prerequisites
+                            .iter()
+                            .filter(|p| p.applies_to(&host.name))
+                            .cloned()
+                            .collect();
+                        if !prerequisites.is_empty() {
+                            let alias = host.name.clone();
+                            let message = format!("Checking prerequisites for {alias}…");
+                            self.notify(message, NotificationLevel::Info);
+                            self.connection_status = ConnectionStatus::Connecting;
+                            let tx = self.action_tx.clone();
+                            tokio::spawn(async move {
+                                let result = crate::ssh::prerequisite::check(&prerequisites).await;
+                                let _ = tx.send(Action::PrerequisitesChecked(alias, result));
+                            });
+                            return;
+                        }
+                    }
+                }
+                // Canonicalize first so the socket name and target use the real hostname
+                if let Some(host) = self.hosts.get(idx).filter(|h| h.needs_canonicalization()) {
+                    let alias = host.name.clone();
+                    let config_path = host
+                        .source
+                        .clone()
+                        .or_else(|| self.ssh_config_paths.first().cloned())
+                        .unwrap_or_default();
+                    let tx = self.action_tx.clone();
+                    self.connection_status = ConnectionStatus::Connecting;
+                    tokio::spawn(async move {
+                        let resolved = crate::ssh::config::resolve_hostname(&alias, &config_path)
+                            .await
+                            .ok();
+                        let _ = tx.send(Action::HostCanonicalized(idx, resolved));
+                    });
+                    return;
+                }
                 if let Some(host) = self.hosts.get(idx).cloned() {
-                    if let Some(mut conn) = self.connection.take() {
-                        tokio::spawn(async move {
-                            let _ = conn.disconnect().await;
-                        });
+                    self.prerequisites_met = None;
+                    if host.forces_session() {
+                        self.notify(
+                            format!(
+                                "{}: ignoring RemoteCommand/RequestTTY for the tunnel master",
+                                host.name
+                            ),
+                            NotificationLevel::Info,
+                        );
                     }
+                    let previous = self.connection.take();
 
-                    // Clear tunnels from previous connection
+                    // Clear tunnels and session count from previous connection
                     self.tunnels.clear();
+                    self.tunnel_marks = None;
+                    self.forward_queue.clear();
+                    self.remote_listeners.clear();
+                    self.shared_sessions = 0;
+                    self.session_note = None;
                     self.tunnel_list_state.select(None);
                     self.connection_status = ConnectionStatus::Connecting;
 
@@ -238,7 +560,25 @@ impl App {
                     let tx = self.action_tx.clone();
 
                     tokio::spawn(async move {
+                        // Relays of the previous host's tunnels would be left
+                        // listening in front of forwards that are gone
+                        crate::ssh::tunnel::stop_relays().await;
+                        // Close the previous master first: it may use the same socket
+                        if let Some(mut conn) = previous {
+                            let _ = conn.disconnect().await;
+                        }
                         let mut mgr = ConnectionManager::new(host, &socket_dir);
+                        // A master already on this socket: ours from an earlier run
+                        // (keep_masters_on_exit) is reused, another alias's needs a choice
+                        if let Some(owner) = mgr.live_owner().await {
+                            let action = if owner == mgr.host().name {
+                                Action::ConnectionEstablished
+                            } else {
+                                Action::SocketCollision(idx, owner)
+                            };
+                            let _ = tx.send(action);
+                            return;
+                        }
                         match mgr.connect().await {
                             Ok(()) => {
                                 let _ = tx.send(Action::ConnectionEstablished);
@@ -256,216 +596,2774 @@ impl App {
                     }
                 }
             }
+            Action::QuickConnect(position) => {
+                // `position` is the 0-based row in the (filtered) host list
+                if let Some(&real_idx) = self.filtered_host_indices.get(position) {
+                    self.host_list_state.select(Some(position));
+                    let _ = self.action_tx.send(Action::Connect(real_idx));
+                }
+            }
+            Action::PrerequisitesChecked(name, result) => match result {
+                Ok(()) => {
+                    // Looked up by name: the list may have been re-sorted meanwhile
+                    if let Some(idx) = self.hosts.iter().position(|h| h.name == name) {
+                        self.prerequisites_met = Some(name);
+                        let _ = self.action_tx.send(Action::Connect(idx));
+                    } else {
+                        // Gone from a reloaded config
+                        self.restore_connection_status();
+                    }
+                }
+                Err(message) => {
+                    self.restore_connection_status();
+                    let message = format!("Not connecting to {name}: {message}");
+                    self.notify(message, NotificationLevel::Error);
+                }
+            },
+            Action::HostCanonicalized(idx, resolved) => {
+                if let Some(host) = self.hosts.get_mut(idx) {
+                    // Fall back to the configured hostname if ssh -G failed
+                    let hostname =
+                        resolved.unwrap_or_else(|| host.effective_hostname().to_string());
+                    host.canonical_hostname = Some(hostname);
+                    let _ = self.action_tx.send(Action::Connect(idx));
+                }
+            }
+            Action::ToggleAgentForwarding => {
+                if let Some(host) = self.selected_host_mut() {
+                    host.forward_agent = !host.forward_agent;
+                    let msg = format!(
+                        "Agent forwarding {} for {} (applies on next connect)",
+                        if host.forward_agent { "on" } else { "off" },
+                        host.name
+                    );
+                    self.notify(msg, NotificationLevel::Info);
+                }
+            }
+            Action::CycleX11Forwarding => {
+                if let Some(host) = self.selected_host_mut() {
+                    host.forward_x11 = host.forward_x11.next();
+                    let mode = match host.forward_x11 {
+                        X11Forwarding::Off => "off",
+                        X11Forwarding::Untrusted => "untrusted (-X)",
+                        X11Forwarding::Trusted => "trusted (-Y)",
+                    };
+                    let msg = format!(
+                        "X11 forwarding {mode} for {} (applies on next connect)",
+                        host.name
+                    );
+                    self.notify(msg, NotificationLevel::Info);
+                }
+            }
             Action::ConnectionEstablished => {
-                if let Some(ref conn) = self.connection {
-                    let name = conn.host().name.clone();
+                if let Some(host) = self.connection.as_ref().map(|c| c.host().clone()) {
+                    let name = host.name.clone();
                     self.connection_status = ConnectionStatus::Connected(name.clone());
+                    // A master kept from an earlier run keeps its note
+                    self.session_note = self.connection.as_ref().and_then(|c| c.read_note());
                     self.history.record_connection(&name);
                     let _ = self.history.save();
+                    if self.host_indices_free() {
+                        self.bump_host_recency(&name);
+                    }
 
-                    // Load previously saved tunnels (disabled by default)
-                    let saved = self.history.get_saved_tunnels(&name);
+                    // Load previously saved tunnels; with auto_restore, re-enable the
+                    // ones that were on when the host was last used.
+                    let saved = self.history.saved_tunnels_by_usage(&name);
+                    let auto_restore = self.config.general.auto_restore;
+                    let mut to_enable = Vec::new();
                     for st in saved {
-                        let tunnel = Tunnel::new(st.local_port, st.remote_host, st.remote_port);
+                        let tunnel = Tunnel::from(&st);
+                        if auto_restore && st.enabled {
+                            to_enable.push(tunnel.id);
+                        }
                         self.tunnels.push(tunnel);
                     }
+                    // Set up the forwards plain ssh would open from the config right
+                    // away, so a bad one shows its error now rather than on first use
+                    let mut config_forwards = Vec::new();
+                    for fwd in host.config_forwards() {
+                        let tunnel =
+                            Tunnel::new(fwd.local_port, fwd.remote_host.clone(), fwd.remote_port);
+                        config_forwards.extend(self.add_config_tunnel(tunnel));
+                    }
+                    // Tunnels declared in config.toml for this host, same treatment
+                    let vars = self.config.template_vars(&host);
+                    let mut template_errors = Vec::new();
+                    let resolved: Vec<_> = self
+                        .config
+                        .tunnels
+                        .iter()
+                        .filter(|t| t.applies_to(&name))
+                        .map(|t| t.resolve(&vars))
+                        .collect();
+                    for resolved in resolved {
+                        let (local_port, remote_host, remote_port, label) = match resolved {
+                            Ok(resolved) => resolved,
+                            Err(e) => {
+                                template_errors.push(e);
+                                continue;
+                            }
+                        };
+                        let mut tunnel = Tunnel::new(local_port, remote_host, remote_port);
+                        tunnel.label = label;
+                        config_forwards.extend(self.add_config_tunnel(tunnel));
+                    }
+                    // Sort before enabling: ToggleTunnel refers to list positions
+                    self.tunnel_sort = self.history.tunnel_sort(&name);
+                    self.tunnel_sort.sort(&mut self.tunnels);
+                    self.collapsed_groups =
+                        self.history.collapsed_groups(&name).into_iter().collect();
+                    let mut remapped = Vec::new();
+                    for id in to_enable {
+                        if let Some(idx) = self.tunnels.iter().position(|t| t.id == id) {
+                            let port = self.tunnels[idx].local_port;
+                            if self.enable_restored(idx) && self.tunnels[idx].local_port != port {
+                                remapped.push((port, self.tunnels[idx].local_port));
+                            }
+                        }
+                    }
+                    for id in config_forwards {
+                        self.enable_config_forward(id);
+                    }
+                    self.open_next_remap_prompt();
                     if !self.tunnels.is_empty() {
                         self.tunnel_list_state.select(Some(0));
                     }
+                    if self.config.ui.focus_tunnels_on_connect {
+                        self.active_panel = Panel::Tunnels;
+                    }
+                    if self.restore_on_connect {
+                        self.restore_on_connect = false;
+                        let _ = self.action_tx.send(Action::RestoreTunnels);
+                    }
 
-                    self.notify(format!("Connected to {name}"), NotificationLevel::Success);
+                    self.emit(EventKind::Connected, &name, format!("Connected to {name}"));
+                    // Kept to kill the master should it stop answering on disconnect
+                    if let Some(probe) = self
+                        .connection
+                        .as_ref()
+                        .filter(|c| c.is_owned())
+                        .map(|c| c.probe())
+                    {
+                        let tx = self.action_tx.clone();
+                        tokio::spawn(async move {
+                            let pid =
+                                crate::ssh::health::master_pid(&probe.socket_path, &probe.target)
+                                    .await;
+                            if let Some(pid) = pid {
+                                let _ = tx.send(Action::MasterPid(pid));
+                            }
+                        });
+                    }
+                    // Queued behind the connected event so they aren't overwritten
+                    if !remapped.is_empty() {
+                        let _ = self.action_tx.send(Action::Notify(
+                            format!("Ports taken, remapped: {}", describe_remaps(&remapped)),
+                            NotificationLevel::Info,
+                        ));
+                    }
+                    if let Some(e) = template_errors.first() {
+                        let _ = self.action_tx.send(Action::Notify(
+                            format!("[[tunnels]]: {e}"),
+                            NotificationLevel::Error,
+                        ));
+                    }
                 }
             }
+            Action::SocketCollision(idx, owner) => {
+                self.connection = None;
+                self.connection_status = ConnectionStatus::Disconnected;
+                self.socket_collision = Some(SocketCollisionState::new(idx, owner));
+            }
+            Action::WakeHost(idx) => self.wake_host(idx),
+            Action::HostAwake(name) => {
+                // Looked up by name: the list may have been re-sorted meanwhile
+                if let Some(idx) = self.hosts.iter().position(|h| h.name == name) {
+                    let _ = self.action_tx.send(Action::Connect(idx));
+                }
+            }
+            Action::WakeFailed(msg) => {
+                self.notify(format!("Wake-on-LAN: {msg}"), NotificationLevel::Error);
+            }
             Action::ConnectionFailed(msg) => {
-                self.notify(
-                    format!("Connection failed: {msg}"),
-                    NotificationLevel::Error,
-                );
+                let host = self
+                    .connection
+                    .as_ref()
+                    .map(|c| c.host().name.clone())
+                    .unwrap_or_default();
+                let kind = match self.connection_status {
+                    ConnectionStatus::Connected(_) => EventKind::ConnectionLost,
+                    _ => EventKind::ConnectionFailed,
+                };
+                self.emit(kind, &host, format!("Connection failed: {msg}"));
+                tokio::spawn(crate::ssh::tunnel::stop_relays());
                 self.connection_status = ConnectionStatus::Error(msg);
-                self.connection = None;
+                if let Some(conn) = self.connection.take() {
+                    tokio::spawn(async move { conn.forget().await });
+                }
+                self.shared_sessions = 0;
+                self.session_note = None;
                 self.tunnels.clear();
+                self.tunnel_marks = None;
+                self.forward_queue.clear();
+                self.remote_listeners.clear();
+                self.restore_on_connect = false;
+            }
+            Action::HostReachability(name, reachable, address, rtt_ms) => {
+                self.reachability.record(&name, reachable, address, rtt_ms);
+            }
+            Action::ReachabilitySweepDone => {
+                self.sweep_running = false;
+                let _ = self.reachability.save();
+                if self.host_sort == HostSort::Latency {
+                    self.sort_hosts_when_free();
+                }
+            }
+            Action::CycleHostSort => {
+                self.host_sort = match self.host_sort {
+                    HostSort::Recent => HostSort::Latency,
+                    HostSort::Latency => HostSort::Recent,
+                };
+                self.sort_hosts_when_free();
+                let label = match self.host_sort {
+                    HostSort::Recent => "recent use",
+                    HostSort::Latency => "latency (experimental)",
+                };
+                self.notify(format!("Hosts sorted by {label}"), NotificationLevel::Info);
+            }
+            Action::SharedSessions(count) => {
+                if self.connection.is_none() {
+                    return;
+                }
+                let was_near = self.mux_stats().is_some_and(|s| s.near_limit());
+                self.shared_sessions = count;
+                if let (false, Some(stats), Some(name)) =
+                    (was_near, self.mux_stats(), self.connected_host_name())
+                {
+                    if stats.near_limit() {
+                        let message = format!(
+                            "{count} sessions share {name}'s master against a limit of {} (max_sessions), so new ones may be refused",
+                            stats.max_sessions
+                        );
+                        self.notify(message, NotificationLevel::Error);
+                    }
+                }
             }
             Action::Disconnect => {
-                // Save tunnels before disconnecting
-                if let Some(ref conn) = self.connection {
-                    let name = conn.host().name.clone();
-                    self.history.save_tunnels(&name, &self.tunnels);
-                    let _ = self.history.save();
+                // Ask for a second press before dropping sessions riding on the master
+                if self.shared_sessions > 0 && !self.disconnect_armed && self.connection.is_some() {
+                    self.disconnect_armed = true;
+                    self.notify(
+                        format!(
+                            "Master is shared by {} other session(s); press x again to disconnect",
+                            self.shared_sessions
+                        ),
+                        NotificationLevel::Error,
+                    );
+                    return;
                 }
+                self.disconnect_armed = false;
+                self.shared_sessions = 0;
+                self.session_note = None;
+                // Save tunnels before disconnecting
+                self.persist_tunnels();
                 if let Some(mut conn) = self.connection.take() {
+                    // Tunnels stay listed until the master is really gone
+                    self.connection_status =
+                        ConnectionStatus::Disconnecting(conn.host().name.clone());
+                    self.tunnel_marks = None;
+                    self.forward_queue.clear();
                     let tx = self.action_tx.clone();
                     tokio::spawn(async move {
-                        let _ = conn.disconnect().await;
-                        let _ = tx.send(Action::Disconnected);
+                        crate::ssh::tunnel::stop_relays().await;
+                        let outcome = conn.disconnect().await;
+                        let _ = tx.send(Action::Disconnected(outcome));
                     });
-                    self.connection_status = ConnectionStatus::Disconnected;
-                    self.tunnels.clear();
-                    self.tunnel_list_state.select(None);
                 }
             }
-            Action::Disconnected => {
-                self.connection = None;
+            Action::Disconnected(outcome) => {
+                // A connection started since then has taken over
+                let ConnectionStatus::Disconnecting(ref name) = self.connection_status else {
+                    return;
+                };
+                let name = name.clone();
                 self.connection_status = ConnectionStatus::Disconnected;
                 self.tunnels.clear();
+                self.tunnel_marks = None;
+                self.forward_queue.clear();
+                self.remote_listeners.clear();
                 self.tunnel_list_state.select(None);
+                let timeout = crate::ssh::connection::DISCONNECT_TIMEOUT.as_secs();
+                let message = match outcome {
+                    Disconnect::Exited => format!("Disconnected from {name}"),
+                    Disconnect::Killed => format!(
+                        "Disconnected from {name}: the master didn't exit within {timeout}s and was killed"
+                    ),
+                    Disconnect::Unresponsive => format!(
+                        "Disconnected from {name}: the master didn't exit within {timeout}s and may still be running"
+                    ),
+                };
+                self.emit(EventKind::Disconnected, &name, message);
+            }
+            Action::MasterPid(pid) => {
+                if let Some(conn) = self.connection.as_mut() {
+                    conn.set_master_pid(pid);
+                }
             }
 
             // Modal actions
             Action::ShowAddTunnelModal => {
-                if matches!(self.connection_status, ConnectionStatus::Connected(_)) {
-                    self.add_modal = Some(AddModalState::new());
+                if let ConnectionStatus::Connected(ref name) = self.connection_status {
+                    let mut modal = AddModalState::new();
+                    if let Some(host) = self
+                        .config
+                        .hosts
+                        .get(name)
+                        .and_then(|h| h.default_remote_host.clone())
+                    {
+                        modal.remote_host.set(host);
+                    }
+                    modal.host_suggestions = self.remote_host_suggestions();
+                    modal.presets = self.service_presets();
+                    modal.active_field = ModalField::Preset;
+                    self.add_modal = Some(modal);
                 } else {
                     self.notify("Connect to a host first (Enter)", NotificationLevel::Info);
                 }
             }
+            Action::QuickAddFromClipboard => {
+                if !matches!(self.connection_status, ConnectionStatus::Connected(_)) {
+                    self.notify("Connect to a host first (Enter)", NotificationLevel::Info);
+                    return;
+                }
+                match crate::clipboard::paste() {
+                    Ok(text) => match crate::ssh::tunnel::parse_endpoint(&text) {
+                        Some((host, port)) => {
+                            let local_port = self
+                                .suggest_local_port(port)
+                                .or_else(|| self.unprivileged_local_port(port))
+                                .unwrap_or(port);
+                            let mut modal = AddModalState::with_endpoint(host, port, local_port);
+                            modal.host_suggestions = self.remote_host_suggestions();
+                            self.add_modal = Some(modal);
+                        }
+                        None => self.notify(
+                            "No host:port or URL found in clipboard",
+                            NotificationLevel::Info,
+                        ),
+                    },
+                    Err(e) => self.notify(e.to_string(), NotificationLevel::Error),
+                }
+            }
             Action::ModalInput(c) => {
-                if let Some(ref mut modal) = self.add_modal {
+                if let Some(ref mut prompt) = self.prompt {
+                    prompt.input(c);
+                } else if let Some(ref mut settings) = self.settings {
+                    settings.input(c);
+                } else if let Some(ref mut modal) = self.add_modal {
                     modal.input(c);
                 }
             }
             Action::ModalBackspace => {
-                if let Some(ref mut modal) = self.add_modal {
+                if let Some(ref mut prompt) = self.prompt {
+                    prompt.backspace();
+                } else if let Some(ref mut settings) = self.settings {
+                    settings.backspace();
+                } else if let Some(ref mut modal) = self.add_modal {
                     modal.backspace();
                 }
             }
+            Action::ModalEdit(key) => {
+                if let Some(ref mut prompt) = self.prompt {
+                    prompt.edit(key);
+                } else if let Some(ref mut settings) = self.settings {
+                    settings.edit(key);
+                } else if let Some(ref mut modal) = self.add_modal {
+                    modal.edit(key);
+                }
+            }
             Action::ModalNextField => {
+                // Leaving the remote port with no local port typed: suggest one
+                let suggestion = self
+                    .add_modal
+                    .as_ref()
+                    .filter(|m| {
+                        m.active_field == ModalField::RemotePort
+                            && m.direction == TunnelDirection::Local
+                            && m.local_port.is_empty()
+                    })
+                    .and_then(|m| m.remote_port.text().parse().ok())
+                    .and_then(|port| self.suggest_local_port(port));
                 if let Some(ref mut modal) = self.add_modal {
+                    if let Some(port) = suggestion {
+                        modal.local_port.set(port.to_string());
+                    }
                     modal.next_field();
                 }
             }
-            Action::ModalSubmit => {
+            Action::ModalPreviousField => {
                 if let Some(ref mut modal) = self.add_modal {
-                    if let Some((local_port, remote_host, remote_port)) = modal.validate() {
-                        let tunnel = Tunnel::new(local_port, remote_host, remote_port);
-                        self.tunnels.push(tunnel);
-                        let tunnel_idx = self.tunnels.len() - 1;
+                    modal.previous_field();
+                }
+            }
+            Action::ModalSubmit => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.submit_prompt(prompt);
+                } else if let Some(ref mut settings) = self.settings {
+                    settings.activate();
+                } else if let Some(ref mut modal) = self.add_modal {
+                    let pick_port = modal.local_port.is_empty();
+                    if let Some(ports) = modal.validate() {
+                        let mut ids = Vec::new();
+                        for (local_port, remote_host, remote_port) in ports.iter().cloned() {
+                            let mut tunnel = Tunnel::new(local_port, remote_host, remote_port);
+                            tunnel.direction = modal.direction;
+                            tunnel.local_socket = modal.local_socket();
+                            tunnel.bind_address = modal.bind_address();
+                            tunnel.label = modal.label();
+                            tunnel.ephemeral = modal.ephemeral;
+                            ids.push(tunnel.id);
+                            self.tunnels.push(tunnel);
+                        }
+                        self.tunnel_sort.sort(&mut self.tunnels);
+                        let indices: Vec<usize> = ids
+                            .iter()
+                            .filter_map(|id| self.tunnels.iter().position(|t| t.id == *id))
+                            .collect();
                         self.add_modal = None;
 
-                        // Auto-enable the tunnel
-                        let _ = self.action_tx.send(Action::ToggleTunnel(tunnel_idx));
+                        // Auto-enable the tunnels
+                        for &idx in &indices {
+                            let _ = self.action_tx.send(Action::ToggleTunnel(idx));
+                        }
 
-                        // Select the new tunnel
-                        self.tunnel_list_state.select(Some(tunnel_idx));
+                        // Select the (first) new tunnel
+                        self.tunnel_list_state.select(indices.iter().min().copied());
                         self.active_panel = Panel::Tunnels;
+                        if let [(first, _, _), .., (last, _, _)] = ports[..] {
+                            self.notify(
+                                format!("Adding {} tunnels, ports {first}-{last}", ports.len()),
+                                NotificationLevel::Info,
+                            );
+                        } else if let [(local_port, _, _)] = ports[..] {
+                            if pick_port && local_port != 0 {
+                                self.notify(
+                                    format!("Picked free local port {local_port}"),
+                                    NotificationLevel::Info,
+                                );
+                            }
+                        }
+                    } else if let Some(port) = modal.busy_port {
+                        let tx = self.action_tx.clone();
+                        tokio::spawn(async move {
+                            let owner = crate::ssh::port_owner::find(port).await;
+                            let _ = tx.send(Action::PortInUse(port, owner));
+                        });
+                    }
+                    self.persist_tunnels();
+                }
+            }
+            Action::PortInUse(port, owner) => {
+                let Some(ref mut modal) = self.add_modal else {
+                    return;
+                };
+                if modal.busy_port != Some(port) {
+                    return;
+                }
+                if let Some(ref owner) = owner {
+                    modal.error_message = Some(format!(
+                        "Port {port} is already in use by pid {} `{}`",
+                        owner.pid,
+                        owner.short_command(24)
+                    ));
+                }
+                let free_port = if modal.is_range() {
+                    None
+                } else {
+                    let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+                    next_free_port(port, &taken)
+                };
+                let choices = port_conflict::choices(owner.as_ref(), &self.socket_dir, free_port);
+                if !choices.is_empty() {
+                    self.port_conflict = Some(PortConflictState::new(port, owner, choices));
+                }
+            }
+            Action::PortFreed(result) => {
+                let Some(ref mut modal) = self.add_modal else {
+                    return;
+                };
+                match result {
+                    Ok(()) => {
+                        modal.error_message = None;
+                        let _ = self.action_tx.send(Action::ModalSubmit);
                     }
+                    Err(message) => modal.error_message = Some(message),
                 }
             }
             // Tunnel actions
-            Action::TunnelFailed(msg) => {
-                self.notify(format!("Tunnel error: {msg}"), NotificationLevel::Error);
+            Action::TunnelFailed(id, msg) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.error = Some(msg.clone());
+                    tunnel.pending = false;
+                }
+                self.start_queued_forwards();
+                let host = self.connected_host_name().unwrap_or_default().to_string();
+                self.emit(
+                    EventKind::TunnelFailed,
+                    &host,
+                    format!("Tunnel error: {msg}"),
+                );
             }
             Action::ToggleTunnel(idx) => {
-                if let (Some(tunnel), Some(ref conn)) =
-                    (self.tunnels.get(idx).cloned(), &self.connection)
+                // Exposed -R forwards need a typed confirmation before going up
+                if let Some(tunnel) = self.tunnels.get(idx).filter(|t| !t.enabled) {
+                    if let Some(exposure) = tunnel.exposure() {
+                        let mut prompt = PromptState::new(
+                            PromptKind::ConfirmExposure(tunnel.id),
+                            format!("Enable {}?", tunnel.describe()),
+                            "Type yes:",
+                        );
+                        prompt.hint = Some(exposure);
+                        self.prompt = Some(prompt);
+                        return;
+                    }
+                }
+                self.toggle_tunnel(idx);
+            }
+            Action::SetTunnelEnabled(id, enable) => {
+                let Some(idx) = self.tunnels.iter().position(|t| t.id == id) else {
+                    return;
+                };
+                let tunnel = &self.tunnels[idx];
+                if tunnel.enabled == enable || tunnel.pending || self.forward_queue.contains(&id) {
+                    return;
+                }
+                if let (true, Some(exposure)) = (enable, tunnel.exposure()) {
+                    // Don't take over a prompt the user is typing in
+                    if self.prompt.is_some() {
+                        let message = format!(
+                            "{} is due on schedule, enable it by hand",
+                            tunnel.describe()
+                        );
+                        self.notify(message, NotificationLevel::Info);
+                        return;
+                    }
+                    let mut prompt = PromptState::new(
+                        PromptKind::ConfirmExposure(id),
+                        format!("Enable {}?", tunnel.describe()),
+                        "Type yes:",
+                    );
+                    prompt.hint = Some(exposure);
+                    self.prompt = Some(prompt);
+                    return;
+                }
+                self.toggle_tunnel(idx);
+            }
+            Action::ToggleAllTunnels => {
+                let ids = self.tunnels.iter().map(|t| t.id).collect();
+                self.toggle_tunnel_batch(ids);
+            }
+            Action::ToggleVisualMode => {
+                if self.tunnel_marks.take().is_none() && !self.tunnels.is_empty() {
+                    self.active_panel = Panel::Tunnels;
+                    self.tunnel_marks = Some(HashSet::new());
+                }
+            }
+            Action::MarkTunnel(idx) => {
+                if let (Some(marks), Some(tunnel)) = (&mut self.tunnel_marks, self.tunnels.get(idx))
                 {
-                    let socket_path = conn.socket_path().clone();
-                    let ssh_target = conn.host().display_target();
-                    let tx = self.action_tx.clone();
-                    let tunnel_id = tunnel.id;
-                    let currently_enabled = tunnel.enabled;
-
-                    tokio::spawn(async move {
-                        let result = if currently_enabled {
-                            crate::ssh::tunnel::remove_tunnel(&socket_path, &ssh_target, &tunnel)
-                                .await
-                        } else {
-                            crate::ssh::tunnel::add_tunnel(&socket_path, &ssh_target, &tunnel).await
-                        };
-
-                        match result {
-                            Ok(()) => {
-                                let _ =
-                                    tx.send(Action::TunnelToggled(tunnel_id, !currently_enabled));
-                            }
-                            Err(e) => {
-                                let _ = tx.send(Action::TunnelFailed(e.to_string()));
-                            }
-                        }
-                    });
+                    if !marks.remove(&tunnel.id) {
+                        marks.insert(tunnel.id);
+                    }
+                    self.navigate(1);
                 }
             }
-            Action::TunnelToggled(id, enabled) => {
-                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
-                    tunnel.enabled = enabled;
+            Action::ToggleMarkedTunnels => {
+                if let Some(marks) = self.tunnel_marks.take() {
+                    self.toggle_tunnel_batch(marks.into_iter().collect());
                 }
             }
-            Action::DeleteTunnel(idx) => {
-                if let Some(tunnel) = self.tunnels.get(idx).cloned() {
-                    if tunnel.enabled {
-                        // Cancel the tunnel first, then remove
-                        if let Some(ref conn) = self.connection {
-                            let socket_path = conn.socket_path().clone();
-                            let ssh_target = conn.host().display_target();
-                            let tx = self.action_tx.clone();
-                            let tunnel_id = tunnel.id;
-
-                            tokio::spawn(async move {
-                                let _ = crate::ssh::tunnel::remove_tunnel(
-                                    &socket_path,
-                                    &ssh_target,
-                                    &tunnel,
-                                )
-                                .await;
-                                let _ = tx.send(Action::TunnelDeleted(tunnel_id));
-                            });
+            Action::DeleteMarkedTunnels => {
+                if let Some(marks) = self.tunnel_marks.take() {
+                    for id in marks {
+                        if let Some(idx) = self.tunnels.iter().position(|t| t.id == id) {
+                            self.delete_tunnel(idx);
                         }
-                    } else {
-                        self.tunnels.retain(|t| t.id != tunnel.id);
-                        self.fix_tunnel_selection();
                     }
                 }
             }
-            Action::TunnelDeleted(id) => {
-                self.tunnels.retain(|t| t.id != id);
-                self.fix_tunnel_selection();
+            Action::TunnelBatchDone(enable, total, failures) => {
+                let verb = if enable { "Enabled" } else { "Disabled" };
+                let done = total - failures.len();
+                match failures.first() {
+                    None => self.notify(
+                        format!("{verb} {done} tunnel(s)"),
+                        NotificationLevel::Success,
+                    ),
+                    Some(first) => self.notify(
+                        format!(
+                            "{verb} {done}/{total} tunnel(s); {} failed: {first}",
+                            failures.len()
+                        ),
+                        NotificationLevel::Error,
+                    ),
+                }
             }
-
-            // Persistence
-            Action::RestoreTunnels => {
-                if let ConnectionStatus::Connected(ref name) = self.connection_status {
-                    let saved = self.history.get_saved_tunnels(name);
-                    for st in saved {
-                        let tunnel = Tunnel::new(st.local_port, st.remote_host, st.remote_port);
-                        self.tunnels.push(tunnel);
-                        let idx = self.tunnels.len() - 1;
-                        let _ = self.action_tx.send(Action::ToggleTunnel(idx));
-                    }
-                    if !self.tunnels.is_empty() {
-                        self.tunnel_list_state.select(Some(0));
-                        self.active_panel = Panel::Tunnels;
+            Action::EditSessionNote => {
+                if !matches!(self.connection_status, ConnectionStatus::Connected(_)) {
+                    self.notify("Connect to a host first (Enter)", NotificationLevel::Info);
+                    return;
+                }
+                let mut prompt = PromptState::new(
+                    PromptKind::SessionNote,
+                    "Session note (empty clears)",
+                    "Note:",
+                );
+                prompt
+                    .value
+                    .set(self.session_note.clone().unwrap_or_default());
+                self.prompt = Some(prompt);
+            }
+            Action::EditTunnelNote(idx) => {
+                if self.refuse_config_edit(idx) {
+                    return;
+                }
+                if let Some(tunnel) = self.tunnels.get(idx) {
+                    let mut prompt = PromptState::new(
+                        PromptKind::TunnelNote(tunnel.id),
+                        format!("Note for {} (empty clears)", tunnel.describe()),
+                        "Note:",
+                    );
+                    prompt.value.set(tunnel.note.clone().unwrap_or_default());
+                    self.prompt = Some(prompt);
+                }
+            }
+            Action::EditIdleTimeout(idx) => {
+                if self.refuse_config_edit(idx) {
+                    return;
+                }
+                if let Some(tunnel) = self.tunnels.get(idx) {
+                    let mut prompt = PromptState::new(
+                        PromptKind::IdleTimeout(tunnel.id),
+                        format!("Idle timeout for {} (empty clears)", tunnel.describe()),
+                        "Minutes:",
+                    );
+                    prompt.hint = Some("Disabled after this long without clients".to_string());
+                    if let Some(mins) = tunnel.idle_timeout_mins {
+                        prompt.value.set(mins.to_string());
                     }
+                    self.prompt = Some(prompt);
                 }
             }
-        }
-    }
-
-    fn navigate(&mut self, delta: i32) {
-        match self.active_panel {
-            Panel::Hosts => {
-                let max = self.filtered_host_indices.len();
-                if max == 0 {
+            Action::EditBandwidthLimit(idx) => {
+                if self.refuse_config_edit(idx) {
                     return;
                 }
-                let current = self.host_list_state.selected().unwrap_or(0);
-                let next = if delta > 0 {
-                    (current + 1).min(max - 1)
-                } else {
-                    current.saturating_sub(1)
+                let Some(tunnel) = self.tunnels.get(idx) else {
+                    return;
                 };
-                self.host_list_state.select(Some(next));
+                if !tunnel.binds_locally() {
+                    self.notify(
+                        "Bandwidth limits need a -L tunnel on a TCP port",
+                        NotificationLevel::Info,
+                    );
+                    return;
+                }
+                let mut prompt = PromptState::new(
+                    PromptKind::BandwidthLimit(tunnel.id),
+                    format!("Bandwidth limit for {} (empty clears)", tunnel.describe()),
+                    "Per second:",
+                );
+                prompt.hint = Some("Each way, e.g. 512K or 2M; stm relays the tunnel".to_string());
+                if let Some(rate) = tunnel.bandwidth_limit {
+                    prompt.value.set(crate::ssh::tunnel::format_rate(rate));
+                }
+                self.prompt = Some(prompt);
             }
-            Panel::Tunnels => {
-                let max = self.tunnels.len();
-                if max == 0 {
+            Action::ToggleEphemeral(idx) => {
+                if self.refuse_config_edit(idx) {
                     return;
                 }
-                let current = self.tunnel_list_state.selected().unwrap_or(0);
+                if let Some(tunnel) = self.tunnels.get_mut(idx) {
+                    tunnel.ephemeral = !tunnel.ephemeral;
+                    let message = if tunnel.ephemeral {
+                        format!("{} is for this connection only", tunnel.describe())
+                    } else {
+                        format!("{} will be saved to history", tunnel.describe())
+                    };
+                    self.persist_tunnels();
+                    self.notify(message, NotificationLevel::Info);
+                }
+            }
+            Action::TestTunnel(idx) => {
+                let Some(tunnel) = self.tunnels.get(idx) else {
+                    return;
+                };
+                if tunnel.pending {
+                    self.notify_pending(idx);
+                } else if !tunnel.enabled {
+                    let message = format!("Enable {} to test it", tunnel.describe());
+                    self.notify(message, NotificationLevel::Info);
+                } else if tunnel.direction == TunnelDirection::Remote {
+                    // Connecting here would only reach the local service, not the forward
+                    let message = format!(
+                        "{} listens on the remote host; only -L tunnels can be tested from here",
+                        tunnel.describe()
+                    );
+                    self.notify(message, NotificationLevel::Info);
+                } else {
+                    let tunnel = tunnel.clone();
+                    let tx = self.action_tx.clone();
+                    self.notify(
+                        format!("Testing {}…", tunnel.describe()),
+                        NotificationLevel::Info,
+                    );
+                    tokio::spawn(async move {
+                        let result = crate::ssh::health::test_tunnel(&tunnel).await;
+                        let _ = tx.send(Action::TunnelTested(tunnel.id, result));
+                    });
+                }
+            }
+            Action::TunnelTested(id, result) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id && t.enabled) {
+                    let level = match result {
+                        TunnelTest::Passed { .. } => NotificationLevel::Success,
+                        TunnelTest::Failed(_) => NotificationLevel::Error,
+                    };
+                    let message = format!("{}: {result}", tunnel.describe());
+                    tunnel.test = Some(result);
+                    self.notify(message, level);
+                }
+            }
+            Action::OpenTunnelUrl(idx) => {
+                let Some(tunnel) = self.tunnels.get(idx) else {
+                    return;
+                };
+                match tunnel.url() {
+                    Some(url) => match crate::browser::open(&url) {
+                        Ok(()) => self.notify(format!("Opening {url}"), NotificationLevel::Info),
+                        Err(e) => self.notify(e.to_string(), NotificationLevel::Error),
+                    },
+                    None => self.notify(
+                        "Only -L tunnels on a local port open in the browser",
+                        NotificationLevel::Info,
+                    ),
+                }
+            }
+            Action::ToggleTunnelHttps(idx) => {
+                if self.refuse_config_edit(idx) {
+                    return;
+                }
+                if let Some(tunnel) = self.tunnels.get_mut(idx) {
+                    tunnel.https = !tunnel.https;
+                    let scheme = if tunnel.https { "https" } else { "http" };
+                    let message = format!("{} opens as {scheme}", tunnel.describe());
+                    self.persist_tunnels();
+                    self.notify(message, NotificationLevel::Info);
+                }
+            }
+            Action::EditSchedule(idx) => {
+                if self.refuse_config_edit(idx) {
+                    return;
+                }
+                if let Some(tunnel) = self.tunnels.get(idx) {
+                    let mut prompt = PromptState::new(
+                        PromptKind::Schedule(tunnel.id),
+                        format!("Schedule for {} (empty clears)", tunnel.describe()),
+                        "Window:",
+                    );
+                    prompt.hint =
+                        Some("e.g. 09:00-18:00 weekdays, 22:00-06:00 fri-sun".to_string());
+                    if let Some(schedule) = tunnel.schedule {
+                        prompt.value.set(schedule.to_string());
+                    }
+                    self.prompt = Some(prompt);
+                }
+            }
+            Action::EditTunnelGroup(idx) => {
+                if self.refuse_config_edit(idx) {
+                    return;
+                }
+                if let Some(tunnel) = self.tunnels.get(idx) {
+                    let mut prompt = PromptState::new(
+                        PromptKind::TunnelGroup(tunnel.id),
+                        format!("Group for {} (empty clears)", tunnel.describe()),
+                        "Group:",
+                    );
+                    let groups = self.tunnel_groups();
+                    if !groups.is_empty() {
+                        prompt.hint = Some(format!("Existing: {}", groups.join(", ")));
+                    }
+                    prompt.value.set(tunnel.group.clone().unwrap_or_default());
+                    self.prompt = Some(prompt);
+                }
+            }
+            Action::ToggleGroup(idx) => {
+                let Some(group) = self.tunnels.get(idx).and_then(|t| t.group.clone()) else {
+                    return;
+                };
+                let ids = self
+                    .tunnels
+                    .iter()
+                    .filter(|t| t.group.as_ref() == Some(&group))
+                    .map(|t| t.id)
+                    .collect();
+                self.toggle_tunnel_batch(ids);
+            }
+            Action::FoldGroup(idx) => {
+                let Some(group) = self.tunnels.get(idx).and_then(|t| t.group.clone()) else {
+                    return;
+                };
+                if !self.collapsed_groups.remove(&group) {
+                    self.collapsed_groups.insert(group.clone());
+                }
+                // Land on the group's row, which is its first tunnel
+                let first = self
+                    .tunnels
+                    .iter()
+                    .position(|t| t.group.as_ref() == Some(&group));
+                self.tunnel_list_state.select(first);
+                self.fix_tunnel_selection();
+                if let ConnectionStatus::Connected(ref name) = self.connection_status {
+                    let mut groups: Vec<String> = self.collapsed_groups.iter().cloned().collect();
+                    groups.sort();
+                    self.history.set_collapsed_groups(name, groups);
+                    let _ = self.history.save();
+                }
+            }
+            Action::DuplicateTunnel(idx) => {
+                if let Some(tunnel) = self.tunnels.get(idx) {
+                    let taken: Vec<u16> = self
+                        .tunnels
+                        .iter()
+                        .filter(|t| t.direction == tunnel.direction)
+                        .map(|t| match t.direction {
+                            TunnelDirection::Local => t.local_port,
+                            TunnelDirection::Remote => t.remote_port,
+                        })
+                        .collect();
+                    let mut modal = AddModalState::duplicate(tunnel, &taken);
+                    modal.host_suggestions = self.remote_host_suggestions();
+                    self.add_modal = Some(modal);
+                }
+            }
+            Action::EditTunnel(idx) => {
+                if self.refuse_config_edit(idx) {
+                    return;
+                }
+                if let Some(tunnel) = self.tunnels.get(idx) {
+                    if tunnel.pending {
+                        self.notify_pending(idx);
+                        return;
+                    }
+                    if !tunnel.binds_locally() {
+                        self.notify(
+                            "Only -L tunnels can be edited in place",
+                            NotificationLevel::Info,
+                        );
+                        return;
+                    }
+                    let mut prompt = PromptState::new(
+                        PromptKind::EditTunnelRemote(tunnel.id),
+                        format!("Edit {}", tunnel.describe()),
+                        "Remote:",
+                    );
+                    prompt.value.set(if tunnel.targets_socket() {
+                        tunnel.remote_host.clone()
+                    } else {
+                        format!(
+                            "{}:{}",
+                            crate::ssh::tunnel::bracket_ipv6(&tunnel.remote_host),
+                            tunnel.remote_port
+                        )
+                    });
+                    self.prompt = Some(prompt);
+                }
+            }
+            Action::TunnelToggled(id, enabled) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.enabled = enabled;
+                    tunnel.saved_enabled = None;
+                    tunnel.pending = false;
+                    tunnel.alive = None;
+                    tunnel.open_ms = None;
+                    tunnel.traffic = None;
+                    tunnel.throughput = (0, 0);
+                    tunnel.clients = None;
+                    tunnel.warning = None;
+                    tunnel.test = None;
+                    tunnel.up_since = enabled.then(chrono::Utc::now);
+                    if enabled {
+                        tunnel.enabled_since = tunnel.up_since;
+                        tunnel.last_active = tunnel.enabled_since;
+                        tunnel.error = None;
+                    }
+                }
+                if enabled {
+                    self.check_gateway_ports(id);
+                    self.identify_tunnel(id);
+                    self.warn_forward_quota();
+                } else {
+                    self.start_queued_forwards();
+                }
+                self.persist_tunnels();
+                if enabled {
+                    if let (ConnectionStatus::Connected(name), Some(tunnel)) = (
+                        &self.connection_status,
+                        self.tunnels.iter().find(|t| t.id == id && !t.ephemeral),
+                    ) {
+                        self.history.record_tunnel_enabled(name, tunnel);
+                        self.history.record_event(
+                            name,
+                            HostEventKind::TunnelEnabled,
+                            format!("Enabled {}", tunnel.describe()),
+                        );
+                        let _ = self.history.save();
+                    }
+                }
+                if self.tunnel_sort == TunnelSort::Status {
+                    self.sort_tunnels();
+                }
+                self.fix_tunnel_selection();
+            }
+            Action::TunnelSettled(id) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.pending = false;
+                }
+            }
+            Action::TunnelLiveness(results) => {
+                for (id, alive, open_ms) in results {
+                    if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id && t.enabled)
+                    {
+                        tunnel.alive = Some(alive);
+                        tunnel.open_ms = open_ms;
+                    }
+                }
+            }
+            Action::TunnelClients(counts) => {
+                for (id, clients) in counts {
+                    if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id && t.enabled)
+                    {
+                        tunnel.clients = Some(clients);
+                        if clients > 0 {
+                            tunnel.last_active = Some(chrono::Utc::now());
+                        }
+                    }
+                }
+            }
+            Action::ShowPortLookup => {
+                self.prompt = Some(PromptState::new(
+                    PromptKind::PortLookup,
+                    "What's on local port…?",
+                    "Local port:",
+                ));
+            }
+            Action::PortLookupResult(answer) => match self.prompt {
+                Some(ref mut prompt) if prompt.kind == PromptKind::PortLookup => {
+                    prompt.hint = Some(answer);
+                }
+                _ => self.notify(answer, NotificationLevel::Info),
+            },
+            Action::AuditForwards => self.audit_forwards(),
+            Action::ForwardAudit(None) => self.notify(
+                "Couldn't list the master's forwards (needs lsof or ss)",
+                NotificationLevel::Error,
+            ),
+            Action::ForwardAudit(Some(listening)) => self.show_forward_audit(&listening),
+            Action::AdoptForward => {
+                let Some(orphan) = self.forward_audit.as_mut().and_then(|a| a.take_selected())
+                else {
+                    return;
+                };
+                match orphan.tunnel {
+                    Some(tunnel) => self.adopt_forward(tunnel),
+                    None => {
+                        self.prompt = Some(PromptState::new(
+                            PromptKind::AdoptForward(orphan.port),
+                            format!("Adopt the forward on port {}", orphan.port),
+                            "Destination host:port:",
+                        ));
+                    }
+                }
+                self.close_empty_forward_audit();
+            }
+            Action::ForwardAdopted(tunnel) => {
+                if self.connection.is_none() {
+                    return;
+                }
+                let mut tunnel = *tunnel;
+                tunnel.pending = true;
+                let id = tunnel.id;
+                match self.tunnels.iter_mut().find(|t| t.id == id) {
+                    Some(existing) => *existing = tunnel,
+                    None => {
+                        // A saved tunnel may be in the list under its own id
+                        self.tunnels.retain(|t| {
+                            t.enabled || !t.binds_locally() || t.local_port != tunnel.local_port
+                        });
+                        self.tunnels.push(tunnel);
+                        self.fix_tunnel_selection();
+                    }
+                }
+                let _ = self.action_tx.send(Action::TunnelToggled(id, true));
+            }
+            Action::CancelForward => {
+                let Some(orphan) = self.forward_audit.as_mut().and_then(|a| a.take_selected())
+                else {
+                    return;
+                };
+                match orphan.tunnel {
+                    Some(tunnel) => self.cancel_forward(tunnel),
+                    None => {
+                        let mut prompt = PromptState::new(
+                            PromptKind::CancelForward(orphan.port),
+                            format!("Cancel the forward on port {}", orphan.port),
+                            "Destination host:port:",
+                        );
+                        prompt.hint =
+                            Some("ssh only cancels a forward given its full spec".to_string());
+                        self.prompt = Some(prompt);
+                    }
+                }
+                self.close_empty_forward_audit();
+            }
+            Action::DiscoverRemotePorts => self.discover_remote_ports(),
+            Action::RemotePorts(Err(e)) => self.notify(e, NotificationLevel::Error),
+            Action::RemotePorts(Ok(listeners)) => {
+                if listeners.is_empty() {
+                    self.notify(
+                        "Nothing listens on the remote host",
+                        NotificationLevel::Info,
+                    );
+                } else {
+                    self.remote_listeners = listeners.clone();
+                    self.port_discovery = Some(PortDiscoveryState::new(listeners, &self.tunnels));
+                }
+            }
+            Action::ForwardDiscoveredPort => {
+                let Some(discovery) = self.port_discovery.as_ref() else {
+                    return;
+                };
+                let Some(listener) = discovery.selected().cloned() else {
+                    return;
+                };
+                if discovery.is_forwarded() {
+                    self.notify(
+                        format!("Port {} is already forwarded", listener.port),
+                        NotificationLevel::Info,
+                    );
+                    return;
+                }
+                let Some(local_port) = self.local_port_for(listener.port) else {
+                    self.notify("No free local port", NotificationLevel::Error);
+                    return;
+                };
+                let mut tunnel = Tunnel::new(local_port, listener.forward_host(), listener.port);
+                tunnel.label = listener.process.clone();
+                let id = tunnel.id;
+                self.tunnels.push(tunnel);
+                self.sort_tunnels();
+                if let Some(idx) = self.tunnels.iter().position(|t| t.id == id) {
+                    let _ = self.action_tx.send(Action::ToggleTunnel(idx));
+                }
+                self.persist_tunnels();
+                if let Some(ref mut discovery) = self.port_discovery {
+                    discovery.mark_forwarded();
+                }
+                self.notify(
+                    format!("Forwarding remote port {} on {local_port}", listener.port),
+                    NotificationLevel::Info,
+                );
+            }
+            Action::EditDiscoveredPort => {
+                let Some(listener) = self
+                    .port_discovery
+                    .take()
+                    .and_then(|d| d.selected().cloned())
+                else {
+                    return;
+                };
+                let local_port = self.local_port_for(listener.port).unwrap_or(listener.port);
+                let mut modal = AddModalState::with_endpoint(
+                    listener.forward_host(),
+                    listener.port,
+                    local_port,
+                );
+                modal.label.set(listener.process.unwrap_or_default());
+                modal.host_suggestions = self.remote_host_suggestions();
+                self.add_modal = Some(modal);
+            }
+            Action::TunnelIdentified(id, label) => {
+                // A label typed in the meantime wins
+                if let Some(tunnel) = self
+                    .tunnels
+                    .iter_mut()
+                    .find(|t| t.id == id && t.label.is_none())
+                {
+                    tunnel.label = Some(label);
+                    self.persist_tunnels();
+                    if self.tunnel_sort == TunnelSort::Label {
+                        self.sort_tunnels();
+                    }
+                }
+            }
+            Action::TunnelWarning(id, warning) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id && t.enabled) {
+                    tunnel.warning = Some(warning.clone());
+                    self.notify(warning, NotificationLevel::Error);
+                }
+            }
+            Action::CycleTunnelSort => {
+                self.tunnel_sort = self.tunnel_sort.next();
+                if let ConnectionStatus::Connected(ref name) = self.connection_status {
+                    self.history.set_tunnel_sort(name, self.tunnel_sort);
+                    let _ = self.history.save();
+                }
+                self.sort_tunnels();
+                self.notify(
+                    format!("Tunnels sorted by {}", self.tunnel_sort.label()),
+                    NotificationLevel::Info,
+                );
+            }
+            Action::DeleteTunnel(idx) => self.delete_tunnel(idx),
+            Action::RetryTunnel(idx) => match self.tunnels.get(idx).map(Tunnel::status) {
+                Some(TunnelStatus::Failed(_)) => {
+                    let _ = self.action_tx.send(Action::ToggleTunnel(idx));
+                }
+                Some(_) => self.notify(
+                    "Only failed tunnels can be retried",
+                    NotificationLevel::Info,
+                ),
+                None => {}
+            },
+            Action::TunnelDeleted(id) => {
+                self.tunnels.retain(|t| t.id != id);
+                self.start_queued_forwards();
+                self.fix_tunnel_selection();
+                self.persist_tunnels();
+            }
+
+            // Persistence
+            Action::RestoreTunnels => {
+                if let ConnectionStatus::Connected(ref name) = self.connection_status {
+                    // Bring the tunnel list back to its saved on/off configuration
+                    let saved = self.history.saved_tunnels_by_usage(name);
+                    self.restore_saved(saved);
+                }
+            }
+            Action::ShowSettings => {
+                self.settings = Some(SettingsState::new(&self.config));
+            }
+            Action::SaveSettings => {
+                let Some(ref mut settings) = self.settings else {
+                    return;
+                };
+                let changed = Setting::changed(&self.config, &settings.draft);
+                self.config = settings.draft.clone();
+                settings.dirty = false;
+                match self.config.save_settings(&changed) {
+                    Ok(()) => self.notify(
+                        "Saved config.toml (paths apply after a restart)",
+                        NotificationLevel::Success,
+                    ),
+                    Err(e) => self.notify(
+                        format!("Failed to save config.toml: {e}"),
+                        NotificationLevel::Error,
+                    ),
+                }
+            }
+            Action::ShowSshCommand => {
+                let Some(host) = self.connection.as_ref().map(|c| c.host()) else {
+                    self.notify("Connect to a host first (Enter)", NotificationLevel::Info);
+                    return;
+                };
+                let forwards: Vec<(&str, String)> = self
+                    .tunnels
+                    .iter()
+                    .filter(|t| t.enabled)
+                    .map(|t| (t.direction.flag(), t.forward_spec()))
+                    .collect();
+                let command = host.forward_command(&forwards);
+                let copy_error = crate::clipboard::copy(&command)
+                    .err()
+                    .map(|e| e.to_string());
+                self.ssh_command = Some(SshCommandState {
+                    command,
+                    copy_error,
+                });
+            }
+            Action::ShowKeymap => {
+                self.key_editor = Some(KeymapEditorState::new(&self.keymap));
+            }
+            Action::StartRebind => {
+                if let Some(ref mut editor) = self.key_editor {
+                    editor.capturing = true;
+                    editor.error_message = None;
+                }
+            }
+            Action::BindKey(key) => {
+                if let Some(ref mut editor) = self.key_editor {
+                    editor.capture(key);
+                }
+            }
+            Action::ResetKey => {
+                if let Some(ref mut editor) = self.key_editor {
+                    editor.reset();
+                }
+            }
+            Action::SaveKeymap => {
+                let Some(ref mut editor) = self.key_editor else {
+                    return;
+                };
+                self.keymap = editor.draft.clone();
+                self.config.keys = self.keymap.to_config();
+                editor.dirty = false;
+                match AppConfig::save_keys(&self.config.keys) {
+                    Ok(()) => {
+                        self.notify("Saved [keys] to config.toml", NotificationLevel::Success)
+                    }
+                    Err(e) => self.notify(
+                        format!("Failed to save config.toml: {e}"),
+                        NotificationLevel::Error,
+                    ),
+                }
+            }
+            Action::ShowRestoreModal => {
+                let ConnectionStatus::Connected(ref name) = self.connection_status else {
+                    self.notify("Connect to a host first (Enter)", NotificationLevel::Info);
+                    return;
+                };
+                let saved = self.history.saved_tunnels_by_usage(name);
+                if saved.is_empty() {
+                    self.notify(
+                        format!("No saved tunnels for {name}"),
+                        NotificationLevel::Info,
+                    );
+                } else {
+                    self.restore_modal = Some(RestoreModalState::new(saved));
+                }
+            }
+            Action::RestoreToggleItem => {
+                if let Some(ref mut modal) = self.restore_modal {
+                    modal.toggle();
+                }
+            }
+            Action::RestoreToggleAll => {
+                if let Some(ref mut modal) = self.restore_modal {
+                    modal.toggle_all();
+                }
+            }
+        }
+    }
+
+    /// Add saved tunnels missing from the list (e.g. deleted this session) and
+    /// enable the ones saved as enabled.
+    fn restore_saved(&mut self, saved: Vec<SavedTunnel>) {
+        let mut to_enable = Vec::new();
+        for st in saved {
+            let id = match self.tunnels.iter().find(|t| st.matches(t)) {
+                Some(tunnel) => tunnel.id,
+                None => {
+                    let tunnel = Tunnel::from(&st);
+                    let id = tunnel.id;
+                    self.tunnels.push(tunnel);
+                    id
+                }
+            };
+            if st.enabled {
+                to_enable.push(id);
+            }
+        }
+        self.tunnel_sort.sort(&mut self.tunnels);
+        let mut restored = 0;
+        let mut remapped = Vec::new();
+        for id in to_enable {
+            let Some(idx) = self.tunnels.iter().position(|t| t.id == id) else {
+                continue;
+            };
+            let port = self.tunnels[idx].local_port;
+            if !self.tunnels[idx].enabled && self.enable_restored(idx) {
+                restored += 1;
+                if self.tunnels[idx].local_port != port {
+                    remapped.push((port, self.tunnels[idx].local_port));
+                }
+            }
+        }
+        self.open_next_remap_prompt();
+        if !self.tunnels.is_empty() {
+            self.tunnel_list_state.select(Some(0));
+            self.active_panel = Panel::Tunnels;
+        }
+        let mut message = format!("Restoring {restored} tunnel(s)");
+        if !remapped.is_empty() {
+            message.push_str(&format!(
+                "; taken, remapped: {}",
+                describe_remaps(&remapped)
+            ));
+        }
+        self.notify(message, NotificationLevel::Info);
+    }
+
+    /// Enable a restored tunnel, remapping its local port if it is taken.
+    /// Returns false if it was queued to ask the user for a new port.
+    fn enable_restored(&mut self, idx: usize) -> bool {
+        let tunnel = &self.tunnels[idx];
+        if tunnel.binds_locally() && !tunnel.local_port_available() && !self.auto_remap(idx) {
+            self.remap_queue.push(self.tunnels[idx].id);
+            return false;
+        }
+        let _ = self.action_tx.send(Action::ToggleTunnel(idx));
+        true
+    }
+
+    /// Multiplexing on the connected master; see `health::MuxStats`.
+    pub fn mux_stats(&self) -> Option<MuxStats> {
+        let name = self.connected_host_name()?;
+        let enabled = || self.tunnels.iter().filter(|t| t.enabled);
+        Some(MuxStats {
+            sessions: self.shared_sessions,
+            forwarded: enabled().filter_map(|t| t.clients).sum(),
+            forwards: enabled().count(),
+            max_sessions: self
+                .config
+                .hosts
+                .get(name)
+                .and_then(|c| c.max_sessions)
+                .unwrap_or(crate::ssh::health::DEFAULT_MAX_SESSIONS),
+        })
+    }
+
+    /// How many more forwards the connected host's `max_forwards` allows;
+    /// `None` without a limit. Forwards being set up count as active.
+    fn forward_slots(&self) -> Option<usize> {
+        let name = self.connected_host_name()?;
+        let limit = self
+            .config
+            .hosts
+            .get(name)
+            .and_then(|c| c.max_forwards)
+            .or(self.config.general.max_forwards)?;
+        let active = self
+            .tunnels
+            .iter()
+            .filter(|t| t.enabled || t.pending)
+            .count();
+        Some(limit.saturating_sub(active))
+    }
+
+    /// Warn once the connected host is one forward away from its limit.
+    fn warn_forward_quota(&mut self) {
+        let Some(slots) = self.forward_slots() else {
+            return;
+        };
+        if slots <= 1 {
+            let active = self
+                .tunnels
+                .iter()
+                .filter(|t| t.enabled || t.pending)
+                .count();
+            self.notify(
+                format!("{active}/{} forwards in use on this host", active + slots),
+                NotificationLevel::Info,
+            );
+        }
+    }
+
+    /// Start queued forwards while the host's limit leaves room for them.
+    fn start_queued_forwards(&mut self) {
+        while self.forward_slots() != Some(0) && !self.forward_queue.is_empty() {
+            let id = self.forward_queue.remove(0);
+            if let Some(idx) = self.tunnels.iter().position(|t| t.id == id && !t.enabled) {
+                self.toggle_tunnel(idx);
+            }
+        }
+    }
+
+    fn toggle_tunnel(&mut self, idx: usize) {
+        let Some(tunnel) = self.tunnels.get(idx) else {
+            return;
+        };
+        // Locked while a request is in flight, so add and remove can't race
+        if tunnel.pending {
+            self.notify_pending(idx);
+            return;
+        }
+        // Toggling a queued tunnel takes it out of the queue
+        if let Some(pos) = self.forward_queue.iter().position(|&id| id == tunnel.id) {
+            self.forward_queue.remove(pos);
+            self.notify("Removed from the forward queue", NotificationLevel::Info);
+            return;
+        }
+        if !tunnel.enabled && self.forward_slots() == Some(0) {
+            let message = format!(
+                "Forward limit for this host reached, {} queued until one is disabled",
+                tunnel.describe()
+            );
+            self.forward_queue.push(tunnel.id);
+            self.notify(message, NotificationLevel::Info);
+            return;
+        }
+
+        let tunnel = self.tunnels.get_mut(idx);
+        if let (Some(tunnel), Some(ref conn)) = (tunnel, &self.connection) {
+            tunnel.pending = true;
+            let tunnel = tunnel.clone();
+            let socket_path = conn.socket_path().clone();
+            let ssh_target = conn.host().display_target();
+            let tx = self.action_tx.clone();
+            let tunnel_id = tunnel.id;
+            let currently_enabled = tunnel.enabled;
+            let relay = self.config.general.traffic_stats;
+
+            tokio::spawn(async move {
+                let result = if currently_enabled {
+                    crate::ssh::tunnel::remove_tunnel(&socket_path, &ssh_target, &tunnel).await
+                } else {
+                    crate::ssh::tunnel::open_tunnel(&socket_path, &ssh_target, &tunnel, relay).await
+                };
+
+                match result {
+                    Ok(()) => {
+                        let _ = tx.send(Action::TunnelToggled(tunnel_id, !currently_enabled));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Action::TunnelFailed(tunnel_id, e.to_string()));
+                    }
+                }
+            });
+        }
+    }
+
+    fn delete_tunnel(&mut self, idx: usize) {
+        let Some(tunnel) = self.tunnels.get(idx).cloned() else {
+            return;
+        };
+        // Deleting mid-request would leave the forward behind on the master
+        if tunnel.pending {
+            self.notify_pending(idx);
+            return;
+        }
+        if tunnel.enabled {
+            // Cancel the tunnel first, then remove
+            if let Some(ref conn) = self.connection {
+                self.tunnels[idx].pending = true;
+                let socket_path = conn.socket_path().clone();
+                let ssh_target = conn.host().display_target();
+                let tx = self.action_tx.clone();
+                let tunnel_id = tunnel.id;
+
+                tokio::spawn(async move {
+                    let _ =
+                        crate::ssh::tunnel::remove_tunnel(&socket_path, &ssh_target, &tunnel).await;
+                    let _ = tx.send(Action::TunnelDeleted(tunnel_id));
+                });
+            }
+        } else {
+            self.tunnels.retain(|t| t.id != tunnel.id);
+            self.fix_tunnel_selection();
+            self.persist_tunnels();
+        }
+    }
+
+    /// Enable the given tunnels, or disable them if they are all on already.
+    fn toggle_tunnel_batch(&mut self, ids: Vec<Uuid>) {
+        let enable = self
+            .tunnels
+            .iter()
+            .any(|t| ids.contains(&t.id) && !t.enabled);
+        self.toggle_tunnels(ids, enable);
+    }
+
+    /// Enable or disable several tunnels concurrently, then report the
+    /// outcome in one notification.
+    fn toggle_tunnels(&mut self, ids: Vec<Uuid>, enable: bool) {
+        let mut slots = if enable { self.forward_slots() } else { None };
+        let Some(ref conn) = self.connection else {
+            return;
+        };
+        // Exposed -R forwards are never enabled in bulk: they need their own confirmation
+        let mut tunnels = Vec::new();
+        for tunnel in self.tunnels.iter_mut() {
+            if !ids.contains(&tunnel.id) || tunnel.enabled == enable || tunnel.pending {
+                continue;
+            }
+            if enable && tunnel.exposure().is_some() {
+                tunnel.error = Some("Exposed -R forward, enable it on its own".to_string());
+                continue;
+            }
+            match slots {
+                Some(0) => {
+                    if !self.forward_queue.contains(&tunnel.id) {
+                        self.forward_queue.push(tunnel.id);
+                    }
+                    continue;
+                }
+                Some(ref mut n) => *n -= 1,
+                None => {}
+            }
+            tunnel.pending = true;
+            tunnels.push(tunnel.clone());
+        }
+        if tunnels.is_empty() {
+            return;
+        }
+        let socket_path = conn.socket_path().clone();
+        let ssh_target = conn.host().display_target();
+        let relay = self.config.general.traffic_stats;
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let results = futures::future::join_all(tunnels.iter().map(|tunnel| {
+                let (socket_path, ssh_target) = (&socket_path, &ssh_target);
+                async move {
+                    let result = if enable {
+                        crate::ssh::tunnel::open_tunnel(socket_path, ssh_target, tunnel, relay)
+                            .await
+                    } else {
+                        crate::ssh::tunnel::remove_tunnel(socket_path, ssh_target, tunnel).await
+                    };
+                    (tunnel.id, result)
+                }
+            }))
+            .await;
+
+            let total = results.len();
+            let mut failures = Vec::new();
+            for (id, result) in results {
+                match result {
+                    Ok(()) => {
+                        let _ = tx.send(Action::TunnelToggled(id, enable));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Action::TunnelFailed(id, e.to_string()));
+                        failures.push(e.to_string());
+                    }
+                }
+            }
+            let _ = tx.send(Action::TunnelBatchDone(enable, total, failures));
+        });
+    }
+
+    /// For a -R tunnel bound beyond loopback, check what the server actually
+    /// listens on: without `GatewayPorts` sshd silently binds loopback only.
+    fn check_gateway_ports(&self, id: Uuid) {
+        let (Some(tunnel), Some(conn)) = (
+            self.tunnels.iter().find(|t| t.id == id),
+            self.connection.as_ref(),
+        ) else {
+            return;
+        };
+        if !tunnel.wants_gateway_ports() {
+            return;
+        }
+        let socket_path = conn.socket_path().clone();
+        let ssh_target = conn.host().display_target();
+        let port = tunnel.remote_port;
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let warning = match crate::ssh::tunnel::remote_listen_addresses(
+                &socket_path,
+                &ssh_target,
+                port,
+            )
+            .await
+            {
+                Ok(addrs) if crate::ssh::tunnel::loopback_only(&addrs) => format!(
+                    "Port {port} is loopback-only on the server: GatewayPorts is off in its sshd_config"
+                ),
+                Ok(_) => return,
+                Err(e) => format!("Couldn't check GatewayPorts for port {port}: {e}"),
+            };
+            let _ = tx.send(Action::TunnelWarning(id, warning));
+        });
+    }
+
+    /// With `auto_label`, name an unlabelled tunnel after the service that
+    /// answers on it.
+    fn identify_tunnel(&self, id: Uuid) {
+        if !self.config.general.auto_label {
+            return;
+        }
+        let Some(tunnel) = self
+            .tunnels
+            .iter()
+            .find(|t| t.id == id && t.label.is_none())
+            .cloned()
+        else {
+            return;
+        };
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            if let Some(label) = crate::ssh::banner::identify(&tunnel).await {
+                let _ = tx.send(Action::TunnelIdentified(id, label));
+            }
+        });
+    }
+
+    /// Send a magic packet for the host's `mac_address`, directly or through
+    /// the `wol_relay` host's master, then connect when the ssh port answers.
+    fn wake_host(&mut self, idx: usize) {
+        let Some(host) = self.hosts.get(idx).cloned() else {
+            return;
+        };
+        let host_config = self.config.hosts.get(&host.name);
+        let Some(mac_text) = host_config.and_then(|c| c.mac_address.clone()) else {
+            self.notify(
+                format!("No mac_address for {} in config.toml", host.name),
+                NotificationLevel::Info,
+            );
+            return;
+        };
+        let Some(mac) = crate::ssh::wol::parse_mac(&mac_text) else {
+            self.notify(
+                format!("Invalid mac_address '{mac_text}' for {}", host.name),
+                NotificationLevel::Error,
+            );
+            return;
+        };
+        let relay = match host_config.and_then(|c| c.wol_relay.as_deref()) {
+            Some(alias) => match self.hosts.iter().find(|h| h.name == alias) {
+                Some(relay) => Some(ConnectionManager::new(relay.clone(), &self.socket_dir)),
+                None => {
+                    self.notify(
+                        format!("Unknown wol_relay host '{alias}'"),
+                        NotificationLevel::Error,
+                    );
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        // Hosts behind a jump host or proxy can't be probed directly
+        let wait = host.proxy_jump.is_empty() && host.proxy_command.is_none();
+        let message = if wait {
+            format!("Waking {}; connecting once it answers", host.name)
+        } else {
+            format!("Sending wake packet to {}", host.name)
+        };
+        self.notify(message, NotificationLevel::Info);
+
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let sent = match relay {
+                Some(relay) => {
+                    if !relay.check().await.unwrap_or(false) {
+                        let _ = tx.send(Action::WakeFailed(format!(
+                            "relay {} has no running master",
+                            relay.host().name
+                        )));
+                        return;
+                    }
+                    let target = relay.host().display_target();
+                    crate::ssh::wol::send_via(relay.socket_path(), &target, &mac_text).await
+                }
+                None => crate::ssh::wol::send_local(mac).await,
+            };
+            if let Err(e) = sent {
+                let _ = tx.send(Action::WakeFailed(e.to_string()));
+                return;
+            }
+            if !wait {
+                return;
+            }
+            let deadline = tokio::time::Instant::now() + crate::ssh::wol::WAKE_TIMEOUT;
+            while tokio::time::Instant::now() < deadline {
+                let (up, _, _) = crate::ssh::health::probe_reachable(
+                    host.effective_hostname(),
+                    host.effective_port(),
+                )
+                .await;
+                if up {
+                    let _ = tx.send(Action::HostAwake(host.name));
+                    return;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+            }
+            let _ = tx.send(Action::WakeFailed(format!(
+                "{} did not come up within {}s",
+                host.name,
+                crate::ssh::wol::WAKE_TIMEOUT.as_secs()
+            )));
+        });
+    }
+
+    /// Enable a forward declared in the ssh config, marking it failed if its
+    /// local port is taken (it is the config's port, so it isn't remapped).
+    /// Add a tunnel declared in config unless the list already has its
+    /// forward; returns its id if added.
+    fn add_config_tunnel(&mut self, mut tunnel: Tunnel) -> Option<Uuid> {
+        if self.tunnels.iter().any(|t| t.matches(&tunnel)) {
+            return None;
+        }
+        tunnel.from_config = true;
+        let id = tunnel.id;
+        self.tunnels.push(tunnel);
+        Some(id)
+    }
+
+    fn enable_config_forward(&mut self, id: Uuid) {
+        let Some(idx) = self.tunnels.iter().position(|t| t.id == id) else {
+            return;
+        };
+        let port = self.tunnels[idx].local_port;
+        if crate::ssh::tunnel::is_port_available(port) {
+            let _ = self.action_tx.send(Action::ToggleTunnel(idx));
+        } else {
+            self.tunnels[idx].error = Some(format!("Port {port} is already in use"));
+        }
+    }
+
+    /// The configured service presets, each with the local port it would
+    /// get now: its own, the suggested one, or its remote port, moved up to
+    /// the next free one.
+    fn service_presets(&self) -> Vec<ServicePreset> {
+        let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+        let mut presets = self.config.service_presets();
+        for preset in &mut presets {
+            preset.local_port = match preset.local_port {
+                Some(port) => crate::ssh::tunnel::find_free_port(port..=u16::MAX, &taken),
+                None => self.local_port_for(preset.remote_port),
+            };
+        }
+        presets
+    }
+
+    /// Local port to forward `remote_port` on: the suggested one for a known
+    /// service, or the same port, moved up to the next free one.
+    fn local_port_for(&self, remote_port: u16) -> Option<u16> {
+        self.suggest_local_port(remote_port).or_else(|| {
+            let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+            crate::ssh::tunnel::find_free_port(remote_port..=u16::MAX, &taken)
+        })
+    }
+
+    /// The configured local port for a remote service, or the next free one
+    /// above it if it is taken.
+    fn suggest_local_port(&self, remote_port: u16) -> Option<u16> {
+        let port = self.config.port_suggestions.suggest(remote_port)?;
+        let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+        crate::ssh::tunnel::find_free_port(port..=u16::MAX, &taken)
+    }
+
+    /// `remote_port` itself or the next free port above it, or one from the
+    /// OS when it is privileged, like a URL's 443.
+    fn unprivileged_local_port(&self, remote_port: u16) -> Option<u16> {
+        if remote_port < 1024 {
+            return crate::ssh::tunnel::os_free_port(None);
+        }
+        let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+        crate::ssh::tunnel::find_free_port(remote_port..=u16::MAX, &taken)
+    }
+
+    /// Move a tunnel whose local port is taken into `general.auto_remap_range`,
+    /// or with `general.auto_remap` to the nearest free port above it.
+    /// Returns false if neither is configured or no port is free.
+    fn auto_remap(&mut self, idx: usize) -> bool {
+        let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+        let port = match self.config.general.auto_remap_range {
+            Some([first, last]) => crate::ssh::tunnel::find_free_port(first..=last, &taken),
+            None if self.config.general.auto_remap => {
+                next_free_port(self.tunnels[idx].local_port, &taken)
+            }
+            None => None,
+        };
+        let Some(port) = port else {
+            return false;
+        };
+        self.remap_tunnel(idx, port);
+        true
+    }
+
+    /// Change a tunnel's local port and persist the new mapping.
+    fn remap_tunnel(&mut self, idx: usize, local_port: u16) {
+        // Update the saved entry in place: the tunnel list may still be loading
+        if let ConnectionStatus::Connected(ref name) = self.connection_status {
+            self.history
+                .remap_saved_tunnel(name, &self.tunnels[idx], local_port);
+            let _ = self.history.save();
+        }
+        self.tunnels[idx].local_port = local_port;
+    }
+
+    /// Tell the user the tunnel at `idx` is locked until its request resolves.
+    /// Show the connection that is still up, if any, after a connect was
+    /// abandoned.
+    fn restore_connection_status(&mut self) {
+        self.connection_status = match self.connection {
+            Some(ref conn) => ConnectionStatus::Connected(conn.host().name.clone()),
+            None => ConnectionStatus::Disconnected,
+        };
+    }
+
+    /// Tunnels declared in config are rebuilt from it on every connect, so
+    /// edits to them would be lost; refuse them with a message.
+    fn refuse_config_edit(&mut self, idx: usize) -> bool {
+        let Some(tunnel) = self.tunnels.get(idx).filter(|t| t.from_config) else {
+            return false;
+        };
+        let message = format!(
+            "{} is declared in config and isn't saved, so it can't be edited here",
+            tunnel.describe()
+        );
+        self.notify(message, NotificationLevel::Info);
+        true
+    }
+
+    fn notify_pending(&mut self, idx: usize) {
+        let message = format!(
+            "{} is still switching, try again when it settles",
+            self.tunnels[idx].describe()
+        );
+        self.notify(message, NotificationLevel::Info);
+    }
+
+    /// Change a tunnel's destination, re-forwarding it if it is enabled.
+    fn retarget_tunnel(&mut self, idx: usize, remote_host: String, remote_port: u16) {
+        let old = self.tunnels[idx].clone();
+        if old.remote_host == remote_host && old.remote_port == remote_port {
+            return;
+        }
+        if old.pending {
+            self.notify_pending(idx);
+            return;
+        }
+        if let ConnectionStatus::Connected(ref name) = self.connection_status {
+            self.history
+                .retarget_saved_tunnel(name, &old, &remote_host, remote_port);
+        }
+        let tunnel = &mut self.tunnels[idx];
+        tunnel.remote_host = remote_host;
+        tunnel.remote_port = remote_port;
+        let new = tunnel.clone();
+        if self.tunnel_sort == TunnelSort::Remote {
+            self.sort_tunnels();
+        }
+
+        if let (true, Some(conn)) = (old.enabled, &self.connection) {
+            // Locked like a toggle until the forward is back up
+            self.tunnels[idx].pending = true;
+            let relay = self.config.general.traffic_stats;
+            let socket_path = conn.socket_path().clone();
+            let ssh_target = conn.host().display_target();
+            let tx = self.action_tx.clone();
+            tokio::spawn(async move {
+                let _ = crate::ssh::tunnel::remove_tunnel(&socket_path, &ssh_target, &old).await;
+                if let Err(e) =
+                    crate::ssh::tunnel::open_tunnel(&socket_path, &ssh_target, &new, relay).await
+                {
+                    let _ = tx.send(Action::TunnelFailed(new.id, e.to_string()));
+                    let _ = tx.send(Action::TunnelToggled(new.id, false));
+                } else {
+                    let _ = tx.send(Action::TunnelSettled(new.id));
+                }
+            });
+        }
+        self.persist_tunnels();
+    }
+
+    /// Ask for a new local port for the next queued conflicting tunnel.
+    fn open_next_remap_prompt(&mut self) {
+        if self.prompt.is_some() {
+            return;
+        }
+        while !self.remap_queue.is_empty() {
+            let id = self.remap_queue.remove(0);
+            let Some(tunnel) = self.tunnels.iter().find(|t| t.id == id) else {
+                continue;
+            };
+            let taken: Vec<u16> = self.tunnels.iter().map(|t| t.local_port).collect();
+            let mut prompt = PromptState::new(
+                PromptKind::RemapLocalPort(id),
+                format!("Port {} is in use", tunnel.local_port),
+                "Local port:",
+            );
+            // Suggest the next free port above the original one
+            if let Some(port) = next_free_port(tunnel.local_port, &taken) {
+                prompt.value.set(port.to_string());
+            }
+            self.prompt = Some(prompt);
+            return;
+        }
+    }
+
+    /// Act on a submitted prompt; invalid input re-opens it with an error.
+    fn submit_prompt(&mut self, mut prompt: PromptState) {
+        let value = prompt.value.text().trim().to_string();
+        match prompt.kind {
+            PromptKind::InstantiatePattern(idx) => {
+                let Some(pattern) = self.hosts.get(idx) else {
+                    return;
+                };
+                if !crate::ssh::config::host_pattern_matches(&pattern.name, &value) {
+                    prompt.error_message =
+                        Some(format!("'{value}' does not match {}", pattern.name));
+                    self.prompt = Some(prompt);
+                    return;
+                }
+                // Reuse an earlier instantiation of the same hostname
+                let new_idx = match self.hosts.iter().position(|h| h.name == value) {
+                    Some(existing) => existing,
+                    None => {
+                        let host = pattern.instantiate(&value);
+                        self.hosts.push(host);
+                        self.rebuild_filtered_indices();
+                        self.hosts.len() - 1
+                    }
+                };
+                let _ = self.action_tx.send(Action::Connect(new_idx));
+            }
+            PromptKind::ConnectOverride(idx) => {
+                let Some(host) = self.hosts.get(idx) else {
+                    return;
+                };
+                let (user, port) = value.trim().split_once(':').unwrap_or((value.trim(), ""));
+                let port = match port {
+                    "" => None,
+                    p => match p.parse::<u16>() {
+                        Ok(p) if p > 0 => Some(p),
+                        _ => {
+                            prompt.error_message = Some(format!("Invalid port '{p}'"));
+                            self.prompt = Some(prompt);
+                            return;
+                        }
+                    },
+                };
+                // Only what differs from the config makes a variant
+                let user = Some(user.to_string())
+                    .filter(|u| !u.is_empty() && host.user.as_deref() != Some(u.as_str()));
+                let port = port.filter(|&p| p != host.effective_port());
+                if user.is_none() && port.is_none() {
+                    let _ = self.action_tx.send(Action::Connect(idx));
+                    return;
+                }
+                let variant = host.variant(user, port);
+                let variant_idx = match self.hosts.iter().position(|h| h.name == variant.name) {
+                    Some(existing) => existing,
+                    None => {
+                        self.hosts.push(variant);
+                        self.rebuild_filtered_indices();
+                        self.hosts.len() - 1
+                    }
+                };
+                let _ = self.action_tx.send(Action::Connect(variant_idx));
+            }
+            PromptKind::SessionNote => {
+                let note = Some(value).filter(|v| !v.is_empty());
+                let Some(ref conn) = self.connection else {
+                    return;
+                };
+                if let Err(e) = conn.write_note(note.as_deref()) {
+                    self.notify(
+                        format!("Failed to save note: {e}"),
+                        NotificationLevel::Error,
+                    );
+                }
+                self.session_note = note;
+            }
+            PromptKind::ConfirmExposure(id) => {
+                if !value.eq_ignore_ascii_case("yes") {
+                    prompt.error_message = Some("Type yes to enable, Esc to cancel".to_string());
+                    self.prompt = Some(prompt);
+                    return;
+                }
+                if let Some(idx) = self.tunnels.iter().position(|t| t.id == id) {
+                    self.toggle_tunnel(idx);
+                }
+            }
+            PromptKind::PortLookup => {
+                let Some(port) = value.parse::<u16>().ok().filter(|p| *p > 0) else {
+                    prompt.error_message = Some("Enter a port number".to_string());
+                    self.prompt = Some(prompt);
+                    return;
+                };
+                prompt.hint = Some(self.lookup_port(port));
+                self.prompt = Some(prompt);
+            }
+            PromptKind::AdoptForward(port) | PromptKind::CancelForward(port) => {
+                let Some((remote_host, remote_port)) = crate::ssh::tunnel::parse_endpoint(&value)
+                else {
+                    prompt.error_message = Some("Expected host:port".to_string());
+                    self.prompt = Some(prompt);
+                    return;
+                };
+                let tunnel = Tunnel::new(port, remote_host, remote_port);
+                if matches!(prompt.kind, PromptKind::AdoptForward(_)) {
+                    self.adopt_forward(tunnel);
+                } else {
+                    self.cancel_forward(tunnel);
+                }
+            }
+            PromptKind::IdleTimeout(id) => {
+                let mins = match value.parse::<u32>() {
+                    _ if value.is_empty() => None,
+                    Ok(0) => None,
+                    Ok(mins) => Some(mins),
+                    Err(_) => {
+                        prompt.error_message = Some("Expected a number of minutes".to_string());
+                        self.prompt = Some(prompt);
+                        return;
+                    }
+                };
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.idle_timeout_mins = mins;
+                    self.persist_tunnels();
+                }
+            }
+            PromptKind::BandwidthLimit(id) => {
+                let rate = match crate::ssh::tunnel::parse_rate(&value) {
+                    _ if value.is_empty() => None,
+                    Some(rate) => Some(rate),
+                    None => {
+                        prompt.error_message = Some("Expected a rate like 512K or 2M".to_string());
+                        self.prompt = Some(prompt);
+                        return;
+                    }
+                };
+                let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) else {
+                    return;
+                };
+                tunnel.bandwidth_limit = rate;
+                // A tunnel up without a relay can only be limited from its next enable
+                let applied = crate::ssh::tunnel::set_relay_limit(id, rate);
+                if tunnel.enabled && !applied && rate.is_some() {
+                    let name = tunnel.describe();
+                    self.notify(
+                        format!("Limit applies once {name} is enabled again"),
+                        NotificationLevel::Info,
+                    );
+                }
+                self.persist_tunnels();
+            }
+            PromptKind::Schedule(id) => {
+                let schedule = match crate::scheduler::Schedule::parse(&value) {
+                    _ if value.is_empty() => None,
+                    Ok(schedule) => Some(schedule),
+                    Err(e) => {
+                        prompt.error_message = Some(e);
+                        self.prompt = Some(prompt);
+                        return;
+                    }
+                };
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.schedule = schedule;
+                    // Brought in line with the new window on the next check
+                    tunnel.in_window = None;
+                    self.persist_tunnels();
+                }
+            }
+            PromptKind::TunnelGroup(id) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.group = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+                    self.persist_tunnels();
+                    self.sort_tunnels();
+                    self.fix_tunnel_selection();
+                }
+            }
+            PromptKind::TunnelNote(id) => {
+                if let Some(tunnel) = self.tunnels.iter_mut().find(|t| t.id == id) {
+                    tunnel.note = Some(value).filter(|v| !v.is_empty());
+                    self.persist_tunnels();
+                }
+            }
+            PromptKind::EditTunnelRemote(id) => {
+                let Some(idx) = self.tunnels.iter().position(|t| t.id == id) else {
+                    return;
+                };
+                let endpoint = if crate::ssh::tunnel::is_socket_path(value.trim()) {
+                    Some((value.trim().to_string(), 0))
+                } else {
+                    crate::ssh::tunnel::parse_endpoint(&value)
+                };
+                let Some((remote_host, remote_port)) = endpoint else {
+                    prompt.error_message = Some("Expected host:port or /socket/path".to_string());
+                    self.prompt = Some(prompt);
+                    return;
+                };
+                self.retarget_tunnel(idx, remote_host, remote_port);
+            }
+            PromptKind::RemapLocalPort(id) => {
+                let Some(idx) = self.tunnels.iter().position(|t| t.id == id) else {
+                    self.open_next_remap_prompt();
+                    return;
+                };
+                let error = match value.parse::<u16>() {
+                    Ok(port) if port > 0 => {
+                        if self
+                            .tunnels
+                            .iter()
+                            .any(|t| t.id != id && t.local_port == port)
+                        {
+                            Some(format!("Port {port} is used by another tunnel"))
+                        } else if !crate::ssh::tunnel::is_port_available(port) {
+                            Some(format!("Port {port} is already in use"))
+                        } else {
+                            self.remap_tunnel(idx, port);
+                            let _ = self.action_tx.send(Action::ToggleTunnel(idx));
+                            None
+                        }
+                    }
+                    _ => Some("Enter a port between 1 and 65535".to_string()),
+                };
+                match error {
+                    Some(message) => {
+                        prompt.error_message = Some(message);
+                        self.prompt = Some(prompt);
+                    }
+                    None => self.open_next_remap_prompt(),
+                }
+            }
+        }
+    }
+
+    fn resolve_port_conflict(&mut self, conflict: PortConflictState) {
+        let Some(ref mut modal) = self.add_modal else {
+            return;
+        };
+        match (conflict.choice(), conflict.owner.clone()) {
+            // Stopping a process takes a second Enter
+            (Some(ConflictChoice::Kill), Some(_)) if !conflict.confirming_kill => {
+                self.port_conflict = Some(PortConflictState {
+                    confirming_kill: true,
+                    ..conflict
+                });
+            }
+            (Some(ConflictChoice::Kill), Some(owner)) => {
+                modal.error_message = Some(format!("Stopping pid {}…", owner.pid));
+                let port = conflict.port;
+                let tx = self.action_tx.clone();
+                tokio::spawn(async move {
+                    let result = crate::ssh::port_owner::terminate(&owner, port).await;
+                    let _ = tx.send(Action::PortFreed(result));
+                });
+            }
+            (Some(ConflictChoice::OtherPort(port)), _) => {
+                modal.local_port.set(port.to_string());
+                let _ = self.action_tx.send(Action::ModalSubmit);
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_socket_collision(&mut self, collision: SocketCollisionState) {
+        let idx = collision.host_idx;
+        let Some(host) = self.hosts.get_mut(idx) else {
+            return;
+        };
+        match collision.choice() {
+            CollisionChoice::Reuse => {
+                // Attach to the running master; tunnels are added through its
+                // socket, but it stays the other alias's to stop
+                self.connection = Some(ConnectionManager::borrowed(host.clone(), &self.socket_dir));
+                self.connection_status = ConnectionStatus::Connecting;
+                let _ = self.action_tx.send(Action::ConnectionEstablished);
+            }
+            CollisionChoice::SeparateSocket => {
+                host.separate_socket = true;
+                let _ = self.action_tx.send(Action::Connect(idx));
+            }
+        }
+    }
+
+    /// Probe hosts whose cached reachability is missing or stale, in the
+    /// background. Proxied hosts (ProxyJump/ProxyCommand) and patterns are skipped.
+    pub fn start_reachability_sweep(&mut self) {
+        if !self.config.general.reachability_sweep || self.sweep_running {
+            return;
+        }
+        let ttl = self.config.general.reachability_ttl_secs;
+        let targets: Vec<(String, String, u16)> = self
+            .hosts
+            .iter()
+            .filter(|h| h.proxy_jump.is_empty() && h.proxy_command.is_none() && !h.is_pattern())
+            .filter(|h| self.reachability.needs_refresh(&h.name, ttl))
+            .map(|h| {
+                (
+                    h.name.clone(),
+                    h.effective_hostname().to_string(),
+                    h.effective_port(),
+                )
+            })
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        self.sweep_running = true;
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let sweep_tx = tx.clone();
+            crate::ssh::health::run_bounded(
+                targets,
+                crate::ssh::health::MAX_CONCURRENT_CHECKS,
+                crate::ssh::health::CHECK_JITTER,
+                move |(name, hostname, port)| {
+                    let tx = sweep_tx.clone();
+                    async move {
+                        let (reachable, address, rtt) =
+                            crate::ssh::health::probe_reachable(&hostname, port).await;
+                        let rtt_ms = rtt.map(|d| d.as_millis() as u64);
+                        let _ = tx.send(Action::HostReachability(name, reachable, address, rtt_ms));
+                    }
+                },
+            )
+            .await;
+            let _ = tx.send(Action::ReachabilitySweepDone);
+        });
+    }
+
+    /// Probe every open master through the batched health-check scheduler.
+    fn schedule_health_checks(&mut self) {
+        let probes: Vec<_> = match (&self.connection_status, &self.connection) {
+            (ConnectionStatus::Connected(_), Some(conn)) => vec![conn.probe()],
+            _ => Vec::new(),
+        };
+        if probes.is_empty() {
+            return;
+        }
+
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            for (probe, result) in crate::ssh::health::check_all(probes).await {
+                match result {
+                    Ok(false) => {
+                        let _ = tx.send(Action::ConnectionFailed("Connection lost".to_string()));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Action::ConnectionFailed(e.to_string()));
+                    }
+                    Ok(true) => {
+                        let shared =
+                            crate::ssh::health::count_mux_clients(&probe.socket_path).await;
+                        let _ = tx.send(Action::SharedSessions(shared.unwrap_or(0)));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Read relayed tunnels' byte counts; called about once a second, so the
+    /// difference from the last sample is the throughput.
+    fn sample_traffic(&mut self) {
+        for tunnel in self.tunnels.iter_mut().filter(|t| t.enabled) {
+            let traffic = crate::ssh::tunnel::relay_traffic(tunnel.id);
+            tunnel.throughput = match (traffic, tunnel.traffic) {
+                (Some(now), Some(before)) => (
+                    now.sent.saturating_sub(before.sent),
+                    now.received.saturating_sub(before.received),
+                ),
+                _ => (0, 0),
+            };
+            if let Some(traffic) = traffic {
+                tunnel.clients = Some(traffic.connections);
+                if traffic.connections > 0 || tunnel.throughput != (0, 0) {
+                    tunnel.last_active = Some(chrono::Utc::now());
+                }
+            }
+            tunnel.traffic = traffic;
+        }
+    }
+
+    /// Remote hosts of this connection's tunnels and saved tunnels, most
+    /// used first, then those of discovered listeners, for completion in the
+    /// add modal.
+    fn remote_host_suggestions(&self) -> Vec<String> {
+        let saved = match self.connection_status {
+            ConnectionStatus::Connected(ref name) => self.history.saved_tunnels_by_usage(name),
+            _ => Vec::new(),
+        };
+        let mut hosts: Vec<String> = Vec::new();
+        let candidates = saved
+            .iter()
+            .map(|t| t.remote_host.clone())
+            .chain(self.tunnels.iter().map(|t| t.remote_host.clone()))
+            .chain(self.remote_listeners.iter().map(|l| l.forward_host()));
+        for host in candidates {
+            if !crate::ssh::tunnel::is_socket_path(&host) && !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+        hosts
+    }
+
+    /// Enable or disable scheduled tunnels as their windows open and close.
+    /// Only the first check and window edges act, so a tunnel toggled by
+    /// hand stays that way until the next edge.
+    fn apply_schedules(&mut self) {
+        if !matches!(self.connection_status, ConnectionStatus::Connected(_)) {
+            return;
+        }
+        let now = chrono::Local::now().naive_local();
+        let mut changed = Vec::new();
+        for tunnel in &mut self.tunnels {
+            let Some(schedule) = tunnel.schedule else {
+                continue;
+            };
+            let active = schedule.is_active(now);
+            if tunnel.in_window.replace(active) != Some(active)
+                && tunnel.enabled != active
+                && !tunnel.pending
+            {
+                changed.push((tunnel.id, active));
+            }
+        }
+        for &(id, active) in &changed {
+            // Through the action so exposed -R tunnels still ask first
+            let _ = self.action_tx.send(Action::SetTunnelEnabled(id, active));
+        }
+        if let [(id, active)] = changed[..] {
+            let Some(tunnel) = self.tunnels.iter().find(|t| t.id == id) else {
+                return;
+            };
+            let verb = if active { "Enabling" } else { "Disabling" };
+            let message = format!("{verb} {} on schedule", tunnel.describe());
+            self.notify(message, NotificationLevel::Info);
+        } else if !changed.is_empty() {
+            self.notify(
+                format!("Toggling {} tunnels on schedule", changed.len()),
+                NotificationLevel::Info,
+            );
+        }
+    }
+
+    /// Disable tunnels that sat idle past their `idle_timeout_mins`.
+    fn disable_idle_tunnels(&mut self) {
+        let now = chrono::Utc::now();
+        let idle: Vec<usize> = (0..self.tunnels.len())
+            .filter(|&i| self.tunnels[i].idle_expired(now))
+            .collect();
+        for &idx in &idle {
+            self.toggle_tunnel(idx);
+        }
+        if let [idx] = idle[..] {
+            let tunnel = &self.tunnels[idx];
+            let message = format!(
+                "Disabled {}: idle for {} min",
+                tunnel.describe(),
+                tunnel.idle_timeout_mins.unwrap_or_default()
+            );
+            self.notify(message, NotificationLevel::Info);
+        } else if !idle.is_empty() {
+            self.notify(
+                format!("Disabled {} idle tunnels", idle.len()),
+                NotificationLevel::Info,
+            );
+        }
+    }
+
+    /// Count clients on the local ports of enabled -L tunnels. Relayed
+    /// tunnels count their own connections in `sample_traffic`.
+    fn count_tunnel_clients(&self) {
+        let (ids, ports): (Vec<Uuid>, Vec<u16>) = self
+            .tunnels
+            .iter()
+            .filter(|t| t.enabled && t.binds_locally() && t.traffic.is_none())
+            .map(|t| (t.id, t.local_port))
+            .unzip();
+        if ids.is_empty() {
+            return;
+        }
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            if let Some(counts) = crate::ssh::health::count_port_clients(&ports).await {
+                let _ = tx.send(Action::TunnelClients(ids.into_iter().zip(counts).collect()));
+            }
+        });
+    }
+
+    /// List the ports the connected master listens on, for `ForwardAudit`.
+    fn audit_forwards(&mut self) {
+        let Some(probe) = self.connection.as_ref().map(|c| c.probe()) else {
+            self.notify("Not connected", NotificationLevel::Error);
+            return;
+        };
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let ports =
+                match crate::ssh::health::master_pid(&probe.socket_path, &probe.target).await {
+                    Some(pid) => crate::ssh::port_owner::listening_ports(pid).await,
+                    None => None,
+                };
+            let _ = tx.send(Action::ForwardAudit(ports));
+        });
+    }
+
+    /// List the remote host's listening ports, for `RemotePorts`.
+    fn discover_remote_ports(&mut self) {
+        let Some(probe) = self.connection.as_ref().map(|c| c.probe()) else {
+            self.notify("Not connected", NotificationLevel::Error);
+            return;
+        };
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let listeners =
+                crate::ssh::discovery::remote_listeners(&probe.socket_path, &probe.target)
+                    .await
+                    .map_err(|e| e.to_string());
+            let _ = tx.send(Action::RemotePorts(listeners));
+        });
+    }
+
+    /// Diff the master's listening ports against the enabled -L tunnels.
+    /// Tunnels whose forward is gone are marked failed; forwards no tunnel
+    /// accounts for are listed to adopt or cancel.
+    fn show_forward_audit(&mut self, listening: &[u16]) {
+        let Some(name) = self.connected_host_name().map(str::to_string) else {
+            return;
+        };
+        let mut expected = Vec::new();
+        let mut missing = 0;
+        for tunnel in self
+            .tunnels
+            .iter_mut()
+            .filter(|t| t.enabled && !t.pending && t.binds_locally())
+        {
+            let port =
+                crate::ssh::tunnel::relay_forward_port(tunnel.id).unwrap_or(tunnel.local_port);
+            if listening.contains(&port) {
+                expected.push(port);
+            } else {
+                // A relay left listening would take the port from a re-enable
+                crate::ssh::tunnel::stop_relay(tunnel.id);
+                tunnel.enabled = false;
+                tunnel.saved_enabled = None;
+                tunnel.up_since = None;
+                tunnel.traffic = None;
+                tunnel.error = Some("Forward not found on the master".to_string());
+                missing += 1;
+            }
+        }
+        if missing > 0 {
+            self.persist_tunnels();
+        }
+
+        // Disabled tunnels in the list first, then ones saved for the host
+        let known: Vec<Tunnel> = self
+            .tunnels
+            .iter()
+            .filter(|t| !t.enabled)
+            .cloned()
+            .chain(
+                self.history
+                    .saved_tunnels_by_usage(&name)
+                    .iter()
+                    .map(Tunnel::from),
+            )
+            .collect();
+        let audit = ForwardAuditState::new(listening, &expected, &known);
+        let orphans = audit.orphans.len();
+        if orphans > 0 {
+            self.forward_audit = Some(audit);
+        }
+        match (orphans, missing) {
+            (0, 0) => self.notify(
+                format!("The master's {} forward(s) match the list", expected.len()),
+                NotificationLevel::Success,
+            ),
+            (_, 0) => {}
+            _ => self.notify(
+                format!("{missing} enabled tunnel(s) had no forward on the master, marked [ERR]"),
+                NotificationLevel::Error,
+            ),
+        }
+    }
+
+    fn close_empty_forward_audit(&mut self) {
+        if self
+            .forward_audit
+            .as_ref()
+            .is_some_and(|a| a.orphans.is_empty())
+        {
+            self.forward_audit = None;
+        }
+    }
+
+    /// Put an orphaned forward in the tunnel list once the master confirms
+    /// it. Re-requesting the same forward succeeds without side effects when
+    /// it matches the one the master has, and fails if `tunnel`'s
+    /// destination is a wrong guess; the list is left as it was then.
+    fn adopt_forward(&mut self, mut tunnel: Tunnel) {
+        let Some(conn) = self.connection.as_ref() else {
+            return;
+        };
+        let socket_path = conn.socket_path().clone();
+        let ssh_target = conn.host().display_target();
+        tunnel.error = None;
+        self.notify(
+            format!("Adopting {}…", tunnel.describe()),
+            NotificationLevel::Info,
+        );
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let action =
+                match crate::ssh::tunnel::add_tunnel(&socket_path, &ssh_target, &tunnel).await {
+                    Ok(()) => Action::ForwardAdopted(Box::new(tunnel)),
+                    Err(e) => Action::Notify(
+                        format!(
+                            "The forward on port {} goes elsewhere: {e}",
+                            tunnel.local_port
+                        ),
+                        NotificationLevel::Error,
+                    ),
+                };
+            let _ = tx.send(action);
+        });
+    }
+
+    /// Cancel an orphaned forward on the master.
+    fn cancel_forward(&mut self, tunnel: Tunnel) {
+        let Some(conn) = self.connection.as_ref() else {
+            return;
+        };
+        let socket_path = conn.socket_path().clone();
+        let ssh_target = conn.host().display_target();
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let (message, level) =
+                match crate::ssh::tunnel::remove_tunnel(&socket_path, &ssh_target, &tunnel).await {
+                    Ok(()) => (
+                        format!("Cancelled {}", tunnel.describe()),
+                        NotificationLevel::Success,
+                    ),
+                    Err(e) => (e.to_string(), NotificationLevel::Error),
+                };
+            let _ = tx.send(Action::Notify(message, level));
+        });
+    }
+
+    /// Say who holds local `port`. Tunnels of this connection are answered
+    /// right away; otherwise the listening process is looked up in the
+    /// background and reported with `PortLookupResult`.
+    fn lookup_port(&self, port: u16) -> String {
+        if let (Some(tunnel), Some(host)) = (
+            self.tunnels
+                .iter()
+                .find(|t| t.binds_locally() && t.local_port == port && (t.enabled || t.pending)),
+            self.connected_host_name(),
+        ) {
+            let name = tunnel.label.clone().unwrap_or_else(|| tunnel.describe());
+            return format!("Port {port}: tunnel {name} on {host} (this connection)");
+        }
+
+        let mut saved_on: Vec<String> = self
+            .history
+            .hosts
+            .iter()
+            .filter(|(_, h)| {
+                h.tunnels
+                    .iter()
+                    .any(|t| t.direction == TunnelDirection::Local && t.local_port == port)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        saved_on.sort();
+        let socket_dir = self.socket_dir.clone();
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let mut answer = match crate::ssh::port_owner::find(port).await {
+                Some(owner) => match owner.control_socket() {
+                    Some(socket) if socket.starts_with(&socket_dir) => {
+                        let alias = crate::ssh::connection::socket_owner(&socket)
+                            .unwrap_or_else(|| socket.display().to_string());
+                        format!(
+                            "Port {port}: stm master for {alias} (pid {}), not this session",
+                            owner.pid
+                        )
+                    }
+                    _ => format!(
+                        "Port {port}: foreign, pid {} `{}`",
+                        owner.pid,
+                        owner.short_command(40)
+                    ),
+                },
+                None if crate::ssh::tunnel::is_port_available(port) => {
+                    format!("Port {port} is free")
+                }
+                None => format!("Port {port}: in use by a process stm can't see"),
+            };
+            if !saved_on.is_empty() {
+                answer.push_str(&format!(" · saved on {}", saved_on.join(", ")));
+            }
+            let _ = tx.send(Action::PortLookupResult(answer));
+        });
+        format!("Looking up port {port}…")
+    }
+
+    /// Probe every enabled tunnel in the background; see `health::probe_tunnel`.
+    fn probe_tunnels(&self) {
+        if !self.config.general.probe_tunnels {
+            return;
+        }
+        let tunnels: Vec<Tunnel> = self
+            .tunnels
+            .iter()
+            .filter(|t| t.enabled && !t.pending)
+            .cloned()
+            .collect();
+        if tunnels.is_empty() {
+            return;
+        }
+        let Some(ref conn) = self.connection else {
+            return;
+        };
+        let socket_path = conn.socket_path().clone();
+        let ssh_target = conn.host().display_target();
+        let tx = self.action_tx.clone();
+        tokio::spawn(async move {
+            let results = crate::ssh::health::run_bounded(
+                tunnels,
+                crate::ssh::health::MAX_CONCURRENT_CHECKS,
+                Duration::ZERO,
+                |tunnel| {
+                    let (socket_path, ssh_target) = (socket_path.clone(), ssh_target.clone());
+                    async move {
+                        let alive = crate::ssh::health::probe_tunnel(&tunnel).await;
+                        let open_time = if alive {
+                            crate::ssh::health::tunnel_open_time(&socket_path, &ssh_target, &tunnel)
+                                .await
+                        } else {
+                            None
+                        };
+                        (tunnel.id, alive, open_time.map(|d| d.as_millis() as u64))
+                    }
+                },
+            )
+            .await;
+            let _ = tx.send(Action::TunnelLiveness(results));
+        });
+    }
+
+    fn run_host_menu_item(&mut self, host_idx: usize, item: HostMenuItem) {
+        let Some(host) = self.hosts.get(host_idx) else {
+            return;
+        };
+        match item {
+            HostMenuItem::Connect => {
+                let _ = self.action_tx.send(Action::Connect(host_idx));
+            }
+            HostMenuItem::ConnectWithTunnels => {
+                self.restore_on_connect = true;
+                let _ = self.action_tx.send(Action::Connect(host_idx));
+            }
+            HostMenuItem::ConnectAs => {
+                let _ = self.action_tx.send(Action::ConnectWithOverrides(host_idx));
+            }
+            HostMenuItem::CopySshCommand => {
+                let command = host.ssh_command();
+                match crate::clipboard::copy(&command) {
+                    Ok(()) => self.notify(format!("Copied: {command}"), NotificationLevel::Success),
+                    Err(e) => self.notify(e.to_string(), NotificationLevel::Error),
+                }
+            }
+            HostMenuItem::ShowDetails => {
+                self.host_details = Some(HostDetailsState::new(host, host_idx));
+            }
+        }
+    }
+
+    fn navigate(&mut self, delta: i32) {
+        if let Some(ref mut conflict) = self.port_conflict {
+            if delta > 0 {
+                conflict.next();
+            } else {
+                conflict.previous();
+            }
+            return;
+        }
+        if let Some(ref mut modal) = self.add_modal {
+            modal.cycle_suggestion(delta);
+            return;
+        }
+        if let Some(ref mut collision) = self.socket_collision {
+            if delta > 0 {
+                collision.next();
+            } else {
+                collision.previous();
+            }
+            return;
+        }
+        if let Some(ref mut modal) = self.restore_modal {
+            if delta > 0 {
+                modal.next();
+            } else {
+                modal.previous();
+            }
+            return;
+        }
+        if let Some(ref mut discovery) = self.port_discovery {
+            if delta > 0 {
+                discovery.next();
+            } else {
+                discovery.previous();
+            }
+            return;
+        }
+        if let Some(ref mut audit) = self.forward_audit {
+            if delta > 0 {
+                audit.next();
+            } else {
+                audit.previous();
+            }
+            return;
+        }
+        if let Some(ref mut settings) = self.settings {
+            if settings.editing.is_none() {
+                if delta > 0 {
+                    settings.next();
+                } else {
+                    settings.previous();
+                }
+            }
+            return;
+        }
+        if let Some(ref mut editor) = self.key_editor {
+            if !editor.capturing {
+                if delta > 0 {
+                    editor.next();
+                } else {
+                    editor.previous();
+                }
+            }
+            return;
+        }
+        if let Some(ref mut menu) = self.host_menu {
+            if delta > 0 {
+                menu.next();
+            } else {
+                menu.previous();
+            }
+            return;
+        }
+        match self.active_panel {
+            Panel::Hosts => {
+                let max = self.filtered_host_indices.len();
+                if max == 0 {
+                    return;
+                }
+                let current = self.host_list_state.selected().unwrap_or(0);
                 let next = if delta > 0 {
                     (current + 1).min(max - 1)
                 } else {
                     current.saturating_sub(1)
                 };
-                self.tunnel_list_state.select(Some(next));
+                self.host_list_state.select(Some(next));
+            }
+            Panel::Tunnels => {
+                let visible = self.visible_tunnel_indices();
+                if visible.is_empty() {
+                    return;
+                }
+                let current = self
+                    .tunnel_list_state
+                    .selected()
+                    .and_then(|s| visible.iter().position(|&i| i == s))
+                    .unwrap_or(0);
+                let next = if delta > 0 {
+                    (current + 1).min(visible.len() - 1)
+                } else {
+                    current.saturating_sub(1)
+                };
+                self.tunnel_list_state.select(Some(visible[next]));
             }
         }
     }
@@ -485,29 +3383,119 @@ impl App {
                             .hostname
                             .as_ref()
                             .is_some_and(|h| h.to_lowercase().contains(&query))
+                        || self
+                            .history
+                            .hosts
+                            .get(&host.name)
+                            .is_some_and(|h| h.tunnel_matches(&query))
                 })
                 .map(|(i, _)| i)
                 .collect();
         }
     }
 
+    /// Re-apply the sort order, keeping the same tunnel selected.
+    fn sort_tunnels(&mut self) {
+        let selected = self
+            .tunnel_list_state
+            .selected()
+            .and_then(|i| self.tunnels.get(i))
+            .map(|t| t.id);
+        self.tunnel_sort.sort(&mut self.tunnels);
+        if let Some(id) = selected {
+            let idx = self.tunnels.iter().position(|t| t.id == id);
+            self.tunnel_list_state.select(idx);
+        }
+    }
+
+    /// Names of the groups tunnels are in, sorted.
+    pub fn tunnel_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self
+            .tunnels
+            .iter()
+            .filter_map(|t| t.group.clone())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        groups
+    }
+
+    /// The group of the tunnel at `idx` if that group is folded.
+    pub fn folded_group(&self, idx: usize) -> Option<&str> {
+        self.tunnels
+            .get(idx)?
+            .group
+            .as_deref()
+            .filter(|g| self.collapsed_groups.contains(*g))
+    }
+
+    /// Indices into `tunnels` of the rows shown in the tunnel list. A folded
+    /// group shows as its first tunnel.
+    pub fn visible_tunnel_indices(&self) -> Vec<usize> {
+        self.tunnels
+            .iter()
+            .enumerate()
+            .filter(|&(i, t)| match self.folded_group(i) {
+                Some(group) => self.tunnels[..i]
+                    .iter()
+                    .all(|t| t.group.as_deref() != Some(group)),
+                None => !self.collapse_disabled || t.enabled,
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     fn fix_tunnel_selection(&mut self) {
         if self.tunnels.is_empty() {
             self.tunnel_list_state.select(None);
         } else if let Some(selected) = self.tunnel_list_state.selected() {
-            if selected >= self.tunnels.len() {
-                self.tunnel_list_state.select(Some(self.tunnels.len() - 1));
-            }
+            let selected = selected.min(self.tunnels.len() - 1);
+            // A collapsed tunnel can't stay selected; move to the nearest shown one
+            let visible = self.visible_tunnel_indices();
+            let selected = if visible.contains(&selected) {
+                Some(selected)
+            } else {
+                visible.into_iter().min_by_key(|i| i.abs_diff(selected))
+            };
+            self.tunnel_list_state.select(selected);
         }
     }
 
-    #[allow(dead_code)]
     pub fn selected_host(&self) -> Option<&SshHost> {
         let selected = self.host_list_state.selected()?;
         let real_index = *self.filtered_host_indices.get(selected)?;
         self.hosts.get(real_index)
     }
 
+    fn selected_host_mut(&mut self) -> Option<&mut SshHost> {
+        if self.active_panel != Panel::Hosts {
+            return None;
+        }
+        let selected = self.host_list_state.selected()?;
+        let real_index = *self.filtered_host_indices.get(selected)?;
+        self.hosts.get_mut(real_index)
+    }
+
+    /// What the UI masks with; a no-op outside safe mode.
+    pub fn masker(&self) -> Masker {
+        if !self.safe_mode {
+            return Masker::default();
+        }
+        let mut masker = Masker::new(&self.hosts, &self.tunnels);
+        if let Some(ref modal) = self.add_modal {
+            masker.also_mask(modal.host_suggestions.iter().cloned());
+            masker.also_mask(modal.presets.iter().filter_map(|p| p.remote_host.clone()));
+        }
+        if let Some(ref modal) = self.restore_modal {
+            masker.also_mask(modal.entries.iter().map(|t| t.remote_host.clone()));
+        }
+        if let Some(ref audit) = self.forward_audit {
+            let known = audit.orphans.iter().filter_map(|o| o.tunnel.as_ref());
+            masker.also_mask(known.map(|t| t.remote_host.clone()));
+        }
+        masker
+    }
+
     pub fn connected_host_name(&self) -> Option<&str> {
         match &self.connection_status {
             ConnectionStatus::Connected(name) => Some(name),
@@ -515,6 +3503,103 @@ impl App {
         }
     }
 
+    /// Snapshot the connected host's tunnels into history and write it out.
+    /// Called on every tunnel change so a crash or kill loses nothing.
+    pub fn persist_tunnels(&mut self) {
+        self.sync_history();
+        if let ConnectionStatus::Connected(ref name) = self.connection_status {
+            self.history.save_tunnels(name, &self.tunnels);
+            // Add uptime accrued since the last save; disabled tunnels stop accruing
+            let now = chrono::Utc::now();
+            for tunnel in &mut self.tunnels {
+                if let Some(since) = tunnel.enabled_since {
+                    let secs = (now - since).num_seconds().max(0) as u64;
+                    self.history.add_tunnel_uptime(name, tunnel, secs);
+                    tunnel.enabled_since = tunnel.enabled.then_some(now);
+                }
+            }
+            let _ = self.history.save();
+            self.history_dirty = false;
+        }
+    }
+
+    /// Take in tunnels `stm tunnel` saved or removed since history was last
+    /// read or written, so saving doesn't undo them. For the connected host
+    /// they are added to or deleted from the list; the CLI has already
+    /// applied them to the master.
+    fn sync_history(&mut self) {
+        if !self.history.reload_saved_tunnels() {
+            return;
+        }
+        let Some(name) = self.connected_host_name().map(str::to_string) else {
+            return;
+        };
+        let saved = self.history.get_saved_tunnels(&name);
+        let removed: Vec<Uuid> = self
+            .tunnels
+            .iter()
+            .filter(|t| !t.ephemeral && !t.from_config && !t.pending)
+            .filter(|t| !saved.iter().any(|st| st.matches(t)))
+            .map(|t| t.id)
+            .collect();
+        let added: Vec<Tunnel> = saved
+            .iter()
+            .filter(|st| !self.tunnels.iter().any(|t| st.matches(t)))
+            .map(Tunnel::from)
+            .collect();
+        if removed.is_empty() && added.is_empty() {
+            return;
+        }
+        self.notify(
+            format!(
+                "history.json changed: {} tunnel(s) added, {} removed",
+                added.len(),
+                removed.len()
+            ),
+            NotificationLevel::Info,
+        );
+        for tunnel in &added {
+            if tunnel.saved_enabled == Some(true) {
+                let _ = self.action_tx.send(Action::TunnelToggled(tunnel.id, true));
+            }
+        }
+        self.tunnels.extend(added);
+        self.sort_tunnels();
+        for id in removed {
+            if let Some(idx) = self.tunnels.iter().position(|t| t.id == id) {
+                self.delete_tunnel(idx);
+            }
+        }
+        self.fix_tunnel_selection();
+    }
+
+    /// Write history if events were logged since the last save.
+    pub fn flush_history(&mut self) {
+        if self.history_dirty {
+            let _ = self.history.save();
+            self.history_dirty = false;
+        }
+    }
+
+    /// Log an event for `host` and send it to the notification sinks.
+    /// History is written on the next tick, so bursts cost one save.
+    fn emit(&mut self, kind: EventKind, host: &str, message: String) {
+        let logged = match kind {
+            EventKind::Connected => HostEventKind::Connected,
+            EventKind::Disconnected => HostEventKind::Disconnected,
+            EventKind::ConnectionLost => HostEventKind::ConnectionLost,
+            EventKind::ConnectionFailed => HostEventKind::ConnectionFailed,
+            EventKind::TunnelFailed => HostEventKind::TunnelFailed,
+        };
+        self.history.record_event(host, logged, message.clone());
+        self.history_dirty = true;
+        self.notifier.dispatch(&Event {
+            kind,
+            host: host.to_string(),
+            message,
+        });
+    }
+
     fn notify(&mut self, message: impl Into<String>, level: NotificationLevel) {
         self.notification = Some(Notification {
             message: message.into(),
@@ -523,8 +3608,94 @@ impl App {
         self.notification_ticks = 0;
     }
 
-    /// Sort hosts so recently used ones appear first.
-    pub fn sort_hosts_by_history(&mut self) {
+    /// Sanitized summary of the app state for crash reports: no host names,
+    /// only counts, the connection phase and tunnel specs.
+    pub fn state_snapshot(&self) -> String {
+        let status = match self.connection_status {
+            ConnectionStatus::Disconnected => "disconnected",
+            ConnectionStatus::Connecting => "connecting",
+            ConnectionStatus::Connected(_) => "connected",
+            ConnectionStatus::Disconnecting(_) => "disconnecting",
+            ConnectionStatus::Error(_) => "error",
+        };
+        let mut out = format!(
+            "hosts: {}\nconnection: {status}\ntunnels: {}\n",
+            self.hosts.len(),
+            self.tunnels.len()
+        );
+        for tunnel in &self.tunnels {
+            let state = if tunnel.enabled { "on" } else { "off" };
+            out.push_str(&format!("  [{state}] {}\n", tunnel.forward_spec()));
+        }
+        out
+    }
+
+    /// Sort `hosts` now if nothing holds an index into it, or else on a
+    /// later tick once nothing does.
+    fn sort_hosts_when_free(&mut self) {
+        self.hosts_unsorted = !self.host_indices_free();
+        if !self.hosts_unsorted {
+            self.sort_hosts();
+        }
+    }
+
+    /// True if neither an open overlay nor a pending connect, whose
+    /// canonicalization or socket check reports back by index, holds an
+    /// index into `hosts`, so they can be reordered.
+    fn host_indices_free(&self) -> bool {
+        self.connection_status != ConnectionStatus::Connecting
+            && self.host_menu.is_none()
+            && self.host_details.is_none()
+            && self.socket_collision.is_none()
+            && self.prompt.is_none()
+    }
+
+    /// Move the just-used host `name` to where the sort order now puts it,
+    /// shifting the hosts in between rather than resorting. The selected host
+    /// stays selected and on the same screen row where the list allows.
+    fn bump_host_recency(&mut self, name: &str) {
+        let Some(from) = self.hosts.iter().position(|h| h.name == name) else {
+            return;
+        };
+        let selected = self.selected_host().map(|h| h.name.clone());
+        let old_row = self.host_list_state.selected();
+
+        let host = self.hosts.remove(from);
+        let to = match self.host_sort {
+            HostSort::Recent => 0,
+            // Latency ties are in recency order: go first among equals
+            HostSort::Latency => {
+                let rtt = |h: &SshHost| self.reachability.rtt_ms(&h.name).unwrap_or(u64::MAX);
+                let own = rtt(&host);
+                self.hosts
+                    .iter()
+                    .position(|h| rtt(h) >= own)
+                    .unwrap_or(self.hosts.len())
+            }
+        };
+        self.hosts.insert(to, host);
+        self.rebuild_filtered_indices();
+
+        let row = selected.and_then(|name| {
+            self.filtered_host_indices
+                .iter()
+                .position(|&i| self.hosts[i].name == name)
+        });
+        if let (Some(old), Some(new)) = (old_row, row) {
+            let offset = self.host_list_state.offset_mut();
+            *offset = (*offset + new).saturating_sub(old);
+        }
+        self.host_list_state.select(row.or(old_row));
+    }
+
+    /// Sort hosts so recently used ones appear first, or in latency mode the
+    /// hosts that answered the last sweep fastest, keeping the selected host.
+    pub fn sort_hosts(&mut self) {
+        let selected = self
+            .host_list_state
+            .selected()
+            .and_then(|s| self.filtered_host_indices.get(s))
+            .map(|&i| self.hosts[i].name.clone());
         let history = &self.history;
         self.hosts.sort_by(|a, b| {
             let a_history = history.hosts.get(&a.name);
@@ -536,9 +3707,34 @@ impl App {
                 (None, None) => a.name.cmp(&b.name),
             }
         });
-        self.rebuild_filtered_indices();
-        if !self.filtered_host_indices.is_empty() {
-            self.host_list_state.select(Some(0));
+        if self.host_sort == HostSort::Latency {
+            let reachability = &self.reachability;
+            self.hosts
+                .sort_by_key(|h| reachability.rtt_ms(&h.name).unwrap_or(u64::MAX));
         }
+        self.rebuild_filtered_indices();
+        let row = selected
+            .and_then(|name| {
+                self.filtered_host_indices
+                    .iter()
+                    .position(|&i| self.hosts[i].name == name)
+            })
+            .or_else(|| (!self.filtered_host_indices.is_empty()).then_some(0));
+        self.host_list_state.select(row);
     }
 }
+
+/// First free local port above `port` that no tunnel in `taken` uses.
+fn next_free_port(port: u16, taken: &[u16]) -> Option<u16> {
+    let start = port.checked_add(1)?;
+    crate::ssh::tunnel::find_free_port(start..=u16::MAX, taken)
+}
+
+/// `5432 → 5433, 6379 → 6380`
+fn describe_remaps(remapped: &[(u16, u16)]) -> String {
+    remapped
+        .iter()
+        .map(|(old, new)| format!("{old} → {new}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,35 @@
+use std::process::{Command, Stdio};
+
+/// URL openers tried in order: (program, args before the URL).
+const OPEN_COMMANDS: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+    &[("open", &[])]
+} else if cfg!(windows) {
+    &[("cmd", &["/C", "start", ""])]
+} else {
+    // Not plain `open`: on Debian and friends that is openvt
+    &[("xdg-open", &[]), ("wslview", &[]), ("gio", &["open"])]
+};
+
+/// Open `url` in the default browser with the first available platform tool.
+/// Doesn't wait for it: some openers stay in the foreground with the browser.
+pub fn open(url: &str) -> anyhow::Result<()> {
+    for (program, args) in OPEN_COMMANDS {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        std::thread::spawn(move || child.wait());
+        return Ok(());
+    }
+    let tried: Vec<&str> = OPEN_COMMANDS.iter().map(|(program, _)| *program).collect();
+    Err(anyhow::anyhow!(
+        "No browser opener found ({})",
+        tried.join(", ")
+    ))
+}
@@ -0,0 +1,316 @@
+use clap::Subcommand;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::ssh::config::SshHost;
+use crate::ssh::connection::ConnectionManager;
+use crate::ssh::tunnel::{self, Tunnel, TunnelDirection};
+use crate::state::history::History;
+use crate::state::persistence::AppConfig;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Manage saved tunnels without the TUI
+    Tunnel {
+        #[command(subcommand)]
+        action: TunnelCommand,
+    },
+    /// Report a host's connection state.
+    /// Exit code: 0 connected, 3 degraded, 4 disconnected (1 on errors)
+    Status {
+        /// Host alias from the SSH config
+        host: String,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that stm's prerequisites (OpenSSH client, config, socket dir) are in place
+    Doctor,
+}
+
+#[derive(Subcommand)]
+pub enum TunnelCommand {
+    /// Save a tunnel for a host, and enable it if stm's master for the host is up
+    Add {
+        /// Host alias from the SSH config
+        host: String,
+        /// Forward spec: <local_port>:<remote_host>:<remote_port> or <local_port>:</socket/path>
+        spec: String,
+    },
+    /// Remove a saved tunnel, cancelling it if it is live
+    #[command(alias = "remove")]
+    Rm {
+        /// Host alias from the SSH config
+        host: String,
+        /// Forward spec: <local_port>:<remote_host>:<remote_port> or <local_port>:</socket/path>
+        spec: String,
+    },
+}
+
+/// Run a non-interactive subcommand, returning the status to exit with.
+pub async fn run(
+    command: Command,
+    config: &AppConfig,
+    ssh_configs: &[PathBuf],
+) -> anyhow::Result<ExitCode> {
+    match command {
+        Command::Tunnel { action } => run_tunnel(action, config, ssh_configs)
+            .await
+            .map(|()| ExitCode::SUCCESS),
+        Command::Status { host, json } => run_status(&host, json, config, ssh_configs).await,
+        Command::Doctor => run_doctor(config, ssh_configs).await,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum HostState {
+    /// Master is up and every tunnel saved as enabled is listening.
+    Connected,
+    /// Master is up but some tunnels saved as enabled are not listening.
+    Degraded,
+    Disconnected,
+}
+
+impl HostState {
+    fn label(self) -> &'static str {
+        match self {
+            HostState::Connected => "connected",
+            HostState::Degraded => "degraded",
+            HostState::Disconnected => "disconnected",
+        }
+    }
+
+    /// Clear of 1 for errors and 2, which clap exits with on usage errors.
+    fn exit_code(self) -> ExitCode {
+        match self {
+            HostState::Connected => ExitCode::SUCCESS,
+            HostState::Degraded => ExitCode::from(3),
+            HostState::Disconnected => ExitCode::from(4),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TunnelReport {
+    spec: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    enabled: bool,
+    listening: bool,
+}
+
+#[derive(Serialize)]
+struct StatusReport {
+    host: String,
+    status: HostState,
+    /// Session note attached to the live master.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    tunnels: Vec<TunnelReport>,
+}
+
+async fn run_status(
+    host_name: &str,
+    json: bool,
+    config: &AppConfig,
+    ssh_configs: &[PathBuf],
+) -> anyhow::Result<ExitCode> {
+    let host = find_host(ssh_configs, host_name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown host '{host_name}'"))?;
+    let master = live_master(host, config).await;
+    let live = master.is_some();
+
+    let history = History::load();
+    let tunnels: Vec<TunnelReport> = history
+        .get_saved_tunnels(host_name)
+        .iter()
+        .map(|st| TunnelReport {
+            spec: st.spec(),
+            label: st.label.clone(),
+            enabled: st.enabled,
+            // Remote forwards listen on the SSH host; count them up with the master
+            listening: live
+                && match (st.direction, &st.local_socket) {
+                    (TunnelDirection::Remote, _) => true,
+                    (TunnelDirection::Local, Some(path)) => Path::new(path).exists(),
+                    (TunnelDirection::Local, None) => !tunnel::is_port_available(st.local_port),
+                },
+        })
+        .collect();
+
+    let status = if !live {
+        HostState::Disconnected
+    } else if tunnels.iter().any(|t| t.enabled && !t.listening) {
+        HostState::Degraded
+    } else {
+        HostState::Connected
+    };
+
+    let report = StatusReport {
+        host: host_name.to_string(),
+        status,
+        note: master.and_then(|m| m.read_note()),
+        tunnels,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}: {}", report.host, status.label());
+        if let Some(ref note) = report.note {
+            println!("  note: {note}");
+        }
+        for t in &report.tunnels {
+            let state = match (t.enabled, t.listening) {
+                (_, true) => "up",
+                (true, false) => "down",
+                (false, false) => "off",
+            };
+            match t.label {
+                Some(ref label) => println!("  {:<5} {} ({label})", state, t.spec),
+                None => println!("  {:<5} {}", state, t.spec),
+            }
+        }
+    }
+    Ok(status.exit_code())
+}
+
+async fn run_doctor(config: &AppConfig, ssh_configs: &[PathBuf]) -> anyhow::Result<ExitCode> {
+    let mut ok = true;
+
+    match crate::ssh::preflight::check_ssh().await {
+        Ok(banner) => println!("ok    ssh client: {banner}"),
+        Err(problem) => {
+            ok = false;
+            println!("FAIL  ssh client: {problem}");
+            for step in problem.remediation() {
+                println!("        {step}");
+            }
+        }
+    }
+
+    if let Some(ref e) = config.load_error {
+        ok = false;
+        println!("FAIL  config: {}: {e}", AppConfig::config_path().display());
+    }
+    for warning in &config.warnings {
+        println!("warn  config: {warning}");
+    }
+
+    for path in ssh_configs {
+        match crate::ssh::config::parse_ssh_config(path) {
+            Ok(hosts) => println!(
+                "ok    ssh config: {} ({} hosts)",
+                path.display(),
+                hosts.len()
+            ),
+            Err(e) => {
+                ok = false;
+                println!("FAIL  ssh config: {}: {e}", path.display());
+            }
+        }
+    }
+
+    let socket_dir = &config.general.socket_dir;
+    let resolved = crate::ssh::connection::resolve_socket_dir(socket_dir);
+    match std::fs::create_dir_all(&resolved.path) {
+        Ok(()) => match resolved.fallback_reason {
+            Some(reason) => println!("warn  socket dir: {reason}"),
+            None => println!("ok    socket dir: {}", socket_dir.display()),
+        },
+        Err(e) => {
+            ok = false;
+            println!("FAIL  socket dir: {}: {e}", socket_dir.display());
+        }
+    }
+
+    Ok(if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+async fn run_tunnel(
+    action: TunnelCommand,
+    config: &AppConfig,
+    ssh_configs: &[PathBuf],
+) -> anyhow::Result<()> {
+    let (host_name, spec, adding) = match action {
+        TunnelCommand::Add { host, spec } => (host, spec, true),
+        TunnelCommand::Rm { host, spec } => (host, spec, false),
+    };
+
+    let (local_port, remote_host, remote_port) =
+        tunnel::parse_forward_spec(&spec).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid forward spec '{spec}', expected <local_port>:<remote_host>:<remote_port> or <local_port>:</socket/path>"
+            )
+        })?;
+    let mut tunnel = Tunnel::new(local_port, remote_host, remote_port);
+
+    // Apply to a live master when stm has one open for this host; a running
+    // stm picks the saved change up from history.json
+    let live = match find_host(ssh_configs, &host_name) {
+        Some(host) => live_master(host, config).await,
+        None => None,
+    };
+
+    let mut history = History::load();
+    if adding {
+        if history
+            .get_saved_tunnels(&host_name)
+            .iter()
+            .any(|t| t.matches(&tunnel))
+        {
+            anyhow::bail!("Tunnel {spec} is already saved for {host_name}");
+        }
+        if let Some(ref mgr) = live {
+            tunnel::add_tunnel(mgr.socket_path(), &mgr.host().display_target(), &tunnel).await?;
+            tunnel.enabled = true;
+        }
+        history.add_saved_tunnel(&host_name, &tunnel);
+        history.save()?;
+        println!(
+            "Saved tunnel {spec} for {host_name}{}",
+            if live.is_some() { " (enabled)" } else { "" }
+        );
+    } else {
+        if !history.remove_saved_tunnel(&host_name, &tunnel) {
+            anyhow::bail!("No saved tunnel {spec} for {host_name}");
+        }
+        history.save()?;
+        if let Some(ref mgr) = live {
+            // The forward may not be active; removal from history is what matters
+            let _ = tunnel::remove_tunnel(mgr.socket_path(), &mgr.host().display_target(), &tunnel)
+                .await;
+        }
+        println!("Removed tunnel {spec} for {host_name}");
+    }
+    Ok(())
+}
+
+fn find_host(ssh_configs: &[PathBuf], name: &str) -> Option<SshHost> {
+    crate::ssh::config::parse_ssh_configs(ssh_configs)
+        .0
+        .into_iter()
+        .find(|h| h.name == name)
+}
+
+/// Returns a manager for the host's master if stm's control socket is alive.
+async fn live_master(mut host: SshHost, config: &AppConfig) -> Option<ConnectionManager> {
+    // Per-host settings decide the socket name (separate_socket)
+    if let Some(host_config) = config.hosts.get(&host.name) {
+        host_config.apply(&mut host);
+    }
+    let socket_dir = crate::ssh::connection::resolve_socket_dir(&config.general.socket_dir);
+    let mgr = ConnectionManager::new(host, &socket_dir.path);
+    if !mgr.socket_path().exists() {
+        return None;
+    }
+    match mgr.check().await {
+        Ok(true) => Some(mgr),
+        _ => None,
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Clipboard tools tried in order: (program, args to copy from stdin).
+const COPY_COMMANDS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Clipboard tools tried in order: (program, args to print the clipboard).
+const PASTE_COMMANDS: &[(&str, &[&str])] = &[
+    ("pbpaste", &[]),
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+    (
+        "powershell.exe",
+        &["-NoProfile", "-Command", "Get-Clipboard"],
+    ),
+];
+
+/// Copy text to the system clipboard using the first available platform tool.
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    for (program, args) in COPY_COMMANDS {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(anyhow::anyhow!(
+        "No clipboard tool found (pbcopy, wl-copy, xclip or xsel)"
+    ))
+}
+
+/// Read text from the system clipboard using the first available platform tool.
+pub fn paste() -> anyhow::Result<String> {
+    for (program, args) in PASTE_COMMANDS {
+        let Ok(output) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        else {
+            continue;
+        };
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+        }
+    }
+    Err(anyhow::anyhow!(
+        "No clipboard tool found (pbpaste, wl-paste, xclip or xsel)"
+    ))
+}
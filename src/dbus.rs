@@ -0,0 +1,133 @@
+use tokio::sync::{mpsc, watch};
+use zbus::{connection, interface};
+
+use crate::action::Action;
+use crate::app::{App, ConnectionStatus};
+
+const BUS_NAME: &str = "io.github.hmica.Stm";
+const OBJECT_PATH: &str = "/io/github/hmica/Stm";
+
+/// State published on the bus, refreshed from the main loop.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub status: String,
+    pub host: String,
+    /// (forward spec, enabled) per tunnel, in list order.
+    pub tunnels: Vec<(String, bool)>,
+    /// Session note of the current connection, empty if none.
+    pub note: String,
+}
+
+impl Snapshot {
+    pub fn from_app(app: &App) -> Self {
+        let (status, host) = match &app.connection_status {
+            ConnectionStatus::Disconnected => ("disconnected", String::new()),
+            ConnectionStatus::Connecting => ("connecting", String::new()),
+            ConnectionStatus::Connected(name) => ("connected", name.clone()),
+            ConnectionStatus::Disconnecting(name) => ("disconnecting", name.clone()),
+            ConnectionStatus::Error(_) => ("error", String::new()),
+        };
+        Self {
+            status: status.to_string(),
+            host,
+            tunnels: app
+                .tunnels
+                .iter()
+                .map(|t| (t.forward_spec(), t.enabled))
+                .collect(),
+            note: app.session_note.clone().unwrap_or_default(),
+        }
+    }
+}
+
+struct StmService {
+    state: watch::Receiver<Snapshot>,
+    action_tx: mpsc::UnboundedSender<Action>,
+}
+
+#[interface(name = "io.github.hmica.Stm1")]
+impl StmService {
+    #[zbus(property)]
+    fn status(&self) -> String {
+        self.state.borrow().status.clone()
+    }
+
+    #[zbus(property)]
+    fn host(&self) -> String {
+        self.state.borrow().host.clone()
+    }
+
+    #[zbus(property)]
+    fn tunnels(&self) -> Vec<(String, bool)> {
+        self.state.borrow().tunnels.clone()
+    }
+
+    #[zbus(property)]
+    fn note(&self) -> String {
+        self.state.borrow().note.clone()
+    }
+
+    fn toggle_tunnel(&self, index: u32) {
+        let _ = self.action_tx.send(Action::ToggleTunnel(index as usize));
+    }
+
+    fn restore_tunnels(&self) {
+        let _ = self.action_tx.send(Action::RestoreTunnels);
+    }
+
+    fn disconnect(&self) {
+        let _ = self.action_tx.send(Action::Disconnect);
+    }
+}
+
+/// Start the session bus service. Returns the sender the main loop publishes to.
+/// Without a session bus the service silently stays off.
+pub fn spawn(action_tx: mpsc::UnboundedSender<Action>) -> watch::Sender<Snapshot> {
+    let (tx, rx) = watch::channel(Snapshot::default());
+    tokio::spawn(async move {
+        let _ = serve(rx, action_tx).await;
+    });
+    tx
+}
+
+/// Publish the app state, notifying bus clients only when it changed.
+pub fn publish(tx: &watch::Sender<Snapshot>, app: &App) {
+    let snapshot = Snapshot::from_app(app);
+    tx.send_if_modified(|current| {
+        if *current == snapshot {
+            false
+        } else {
+            *current = snapshot;
+            true
+        }
+    });
+}
+
+async fn serve(
+    mut rx: watch::Receiver<Snapshot>,
+    action_tx: mpsc::UnboundedSender<Action>,
+) -> zbus::Result<()> {
+    let service = StmService {
+        state: rx.clone(),
+        action_tx,
+    };
+    let conn = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, service)?
+        .build()
+        .await?;
+    let iface = conn
+        .object_server()
+        .interface::<_, StmService>(OBJECT_PATH)
+        .await?;
+
+    // Emit PropertiesChanged so desktop widgets don't need to poll
+    while rx.changed().await.is_ok() {
+        let emitter = iface.signal_emitter();
+        let service = iface.get().await;
+        service.status_changed(emitter).await?;
+        service.host_changed(emitter).await?;
+        service.tunnels_changed(emitter).await?;
+    }
+    Ok(())
+}
@@ -8,6 +8,8 @@ pub enum Event {
     Tick,
     Key(crossterm::event::KeyEvent),
     Resize,
+    /// The process was asked to terminate (SIGTERM/SIGHUP).
+    Shutdown,
 }
 
 pub struct EventHandler {
@@ -19,6 +21,9 @@ impl EventHandler {
     pub fn new(tick_rate: Duration) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
 
+        #[cfg(unix)]
+        spawn_signal_listener(tx.clone());
+
         let task = tokio::spawn(async move {
             let mut reader = EventStream::new();
             let mut tick_interval = tokio::time::interval(tick_rate);
@@ -55,3 +60,24 @@ impl EventHandler {
         self.rx.recv().await
     }
 }
+
+/// Forward SIGTERM and SIGHUP (terminal closed) as `Event::Shutdown` so the
+/// main loop can run the same cleanup as a normal quit.
+#[cfg(unix)]
+fn spawn_signal_listener(tx: mpsc::UnboundedSender<Event>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let (Ok(mut term), Ok(mut hup)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+        ) else {
+            return;
+        };
+        tokio::select! {
+            _ = term.recv() => {}
+            _ = hup.recv() => {}
+        }
+        let _ = tx.send(Event::Shutdown);
+    });
+}
@@ -1,7 +1,14 @@
 mod action;
 mod app;
+mod browser;
+mod cli;
+mod clipboard;
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+mod dbus;
 mod error;
 mod event;
+mod notify;
+mod scheduler;
 mod ssh;
 mod state;
 mod tui;
@@ -10,47 +17,70 @@ mod ui;
 use clap::Parser;
 use crossterm::event::{KeyCode, KeyModifiers};
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
 use action::Action;
 use app::{App, Panel};
 use event::{Event, EventHandler};
+use ui::input::EditKey;
+use ui::keymap::KeyCombo;
 
 #[derive(Parser)]
 #[command(name = "stm", about = "SSH Tunnel Manager", version)]
 struct Cli {
-    /// Path to SSH config file
+    /// Path to SSH config file; repeat to merge several
     #[arg(long)]
-    ssh_config: Option<PathBuf>,
+    ssh_config: Vec<PathBuf>,
 
     /// Auto-connect to a host on startup
     #[arg(long)]
     connect: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     let cli = Cli::parse();
 
+    if let Some(command) = cli.command {
+        let config = state::persistence::AppConfig::load();
+        ssh::configure_env(&config.ssh_env);
+        let ssh_configs = if cli.ssh_config.is_empty() {
+            config.general.ssh_configs()
+        } else {
+            cli.ssh_config
+        };
+        return cli::run(command, &config, &ssh_configs).await;
+    }
+
     tui::install_panic_hook();
     let _ = state::persistence::ensure_config_dir();
 
     let mut terminal = tui::init()?;
     let (action_tx, mut action_rx) = mpsc::unbounded_channel::<Action>();
     let mut app = App::new(action_tx);
+    ssh::configure_env(&app.config.ssh_env);
+    app.ssh_unavailable = ssh::preflight::check_ssh().await.err();
     let mut events = EventHandler::new(Duration::from_millis(250));
 
-    // Load SSH hosts from config path (CLI override or config file setting)
-    let ssh_config_path = cli
-        .ssh_config
-        .unwrap_or_else(|| app.config.general.ssh_config_path.clone());
-    if ssh_config_path.exists() {
-        app.load_hosts(&ssh_config_path);
+    // Load SSH hosts from the config files (CLI override or config file setting)
+    let mut ssh_configs = if cli.ssh_config.is_empty() {
+        app.config.general.ssh_configs()
+    } else {
+        cli.ssh_config
+    };
+    ssh_configs.retain(|path| path.exists());
+    if !ssh_configs.is_empty() {
+        app.load_hosts(&ssh_configs);
     }
 
     // Sort hosts: recently used first
-    app.sort_hosts_by_history();
+    app.sort_hosts();
+    app.start_reachability_sweep();
 
     // Auto-connect if requested
     if let Some(ref host_name) = cli.connect {
@@ -59,46 +89,70 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    // Initial render
-    terminal.draw(|frame| ui::render(frame, &mut app))?;
+    #[cfg(all(target_os = "linux", feature = "dbus"))]
+    let dbus_tx = dbus::spawn(app.action_tx.clone());
 
     loop {
+        // Checked before drawing so a shutdown from a closed terminal (SIGHUP)
+        // still reaches the cleanup below.
         if !app.running {
             break;
         }
+        tui::record_state_snapshot(app.state_snapshot());
+        #[cfg(all(target_os = "linux", feature = "dbus"))]
+        dbus::publish(&dbus_tx, &app);
+        // A closed terminal fails the draw before its SIGHUP is handled;
+        // stop without skipping the cleanup below
+        if terminal.draw(|frame| ui::render(frame, &mut app)).is_err() {
+            app.running = false;
+            break;
+        }
 
         tokio::select! {
             Some(event) = events.next() => {
                 let action = match event {
                     Event::Tick => Some(Action::Tick),
                     Event::Resize => Some(Action::Render),
+                    Event::Shutdown => Some(Action::Shutdown),
                     Event::Key(key) => map_key_to_action(&app, key.modifiers, key.code),
                 };
 
                 if let Some(action) = action {
                     app.update(action);
-                    terminal.draw(|frame| ui::render(frame, &mut app))?;
                 }
             }
             Some(action) = action_rx.recv() => {
                 app.update(action);
-                terminal.draw(|frame| ui::render(frame, &mut app))?;
             }
         }
     }
 
-    // Graceful cleanup: save tunnels and disconnect
-    if let Some(ref conn) = app.connection {
-        let name = conn.host().name.clone();
-        app.history.save_tunnels(&name, &app.tunnels);
-        let _ = app.history.save();
-    }
+    // Graceful cleanup: save tunnels and disconnect (also reached on SIGTERM/SIGHUP)
+    app.persist_tunnels();
+    app.flush_history();
+    // Relays live in this process; their forwards would be left dangling
+    ssh::tunnel::stop_relays().await;
     if let Some(mut conn) = app.connection.take() {
-        let _ = conn.disconnect().await;
+        if !app.config.general.keep_masters_on_exit {
+            let _ = conn.disconnect().await;
+        }
     }
 
     tui::restore()?;
-    Ok(())
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Cursor movement and deletion keys shared by every text field.
+fn edit_key(modifiers: KeyModifiers, code: KeyCode) -> Option<EditKey> {
+    match code {
+        KeyCode::Left => Some(EditKey::Left),
+        KeyCode::Right => Some(EditKey::Right),
+        KeyCode::Home => Some(EditKey::Home),
+        KeyCode::End => Some(EditKey::End),
+        KeyCode::Delete => Some(EditKey::Delete),
+        KeyCode::Char('u') if modifiers == KeyModifiers::CONTROL => Some(EditKey::Clear),
+        _ => None,
+    }
 }
 
 fn map_key_to_action(app: &App, modifiers: KeyModifiers, code: KeyCode) -> Option<Action> {
@@ -106,17 +160,162 @@ fn map_key_to_action(app: &App, modifiers: KeyModifiers, code: KeyCode) -> Optio
         return Some(Action::Quit);
     }
 
+    if app.ssh_unavailable.is_some() {
+        return match code {
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => Some(Action::Quit),
+            _ => None,
+        };
+    }
+
+    if app.port_conflict.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Enter => Some(Action::Select),
+            _ => None,
+        };
+    }
+
+    if app.ssh_command.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => Some(Action::Quit),
+            _ => None,
+        };
+    }
+
     if app.add_modal.is_some() {
+        if let Some(key) = edit_key(modifiers, code) {
+            return Some(Action::ModalEdit(key));
+        }
         return match code {
             KeyCode::Esc => Some(Action::Quit),
             KeyCode::Enter => Some(Action::ModalSubmit),
             KeyCode::Tab => Some(Action::ModalNextField),
+            KeyCode::BackTab => Some(Action::ModalPreviousField),
+            KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Down => Some(Action::NavigateDown),
             KeyCode::Backspace => Some(Action::ModalBackspace),
             KeyCode::Char(c) => Some(Action::ModalInput(c)),
             _ => None,
         };
     }
 
+    if app.prompt.is_some() {
+        if let Some(key) = edit_key(modifiers, code) {
+            return Some(Action::ModalEdit(key));
+        }
+        return match code {
+            KeyCode::Esc => Some(Action::Quit),
+            KeyCode::Enter => Some(Action::ModalSubmit),
+            KeyCode::Backspace => Some(Action::ModalBackspace),
+            KeyCode::Char(c) => Some(Action::ModalInput(c)),
+            _ => None,
+        };
+    }
+
+    if app.socket_collision.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Enter => Some(Action::Select),
+            _ => None,
+        };
+    }
+
+    if app.restore_modal.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('r') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Char(' ') => Some(Action::RestoreToggleItem),
+            KeyCode::Char('a') => Some(Action::RestoreToggleAll),
+            KeyCode::Enter => Some(Action::Select),
+            _ => None,
+        };
+    }
+
+    if app.port_discovery.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('D') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Enter => Some(Action::ForwardDiscoveredPort),
+            KeyCode::Char('a') => Some(Action::EditDiscoveredPort),
+            _ => None,
+        };
+    }
+
+    if app.forward_audit.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('F') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Char('a') | KeyCode::Enter => Some(Action::AdoptForward),
+            KeyCode::Char('c') => Some(Action::CancelForward),
+            _ => None,
+        };
+    }
+
+    if let Some(ref settings) = app.settings {
+        if settings.editing.is_some() {
+            if let Some(key) = edit_key(modifiers, code) {
+                return Some(Action::ModalEdit(key));
+            }
+            return match code {
+                KeyCode::Esc => Some(Action::Quit),
+                KeyCode::Enter => Some(Action::ModalSubmit),
+                KeyCode::Backspace => Some(Action::ModalBackspace),
+                KeyCode::Char(c) => Some(Action::ModalInput(c)),
+                _ => None,
+            };
+        }
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char(',') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Enter | KeyCode::Char(' ') => Some(Action::ModalSubmit),
+            KeyCode::Char('w') => Some(Action::SaveSettings),
+            _ => None,
+        };
+    }
+
+    if let Some(ref editor) = app.key_editor {
+        if editor.capturing {
+            if code == KeyCode::Esc {
+                return Some(Action::Quit);
+            }
+            return KeyCombo::from_event(modifiers, code).map(Action::BindKey);
+        }
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('K') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Enter => Some(Action::StartRebind),
+            KeyCode::Backspace | KeyCode::Delete => Some(Action::ResetKey),
+            KeyCode::Char('w') => Some(Action::SaveKeymap),
+            _ => None,
+        };
+    }
+
+    if app.host_menu.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('m') => Some(Action::Quit),
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Enter => Some(Action::Select),
+            _ => None,
+        };
+    }
+
+    if app.host_details.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => Some(Action::Quit),
+            _ => None,
+        };
+    }
+
     if app.search_mode {
         return match code {
             KeyCode::Esc => Some(Action::Quit),
@@ -134,10 +333,42 @@ fn map_key_to_action(app: &App, modifiers: KeyModifiers, code: KeyCode) -> Optio
         };
     }
 
+    if app.tunnel_marks.is_some() {
+        return match code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => {
+                Some(Action::ToggleVisualMode)
+            }
+            KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
+            KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+            KeyCode::Char(' ') => app.tunnel_list_state.selected().map(Action::MarkTunnel),
+            KeyCode::Char('t') | KeyCode::Enter => Some(Action::ToggleMarkedTunnels),
+            KeyCode::Char('d') => Some(Action::DeleteMarkedTunnels),
+            _ => None,
+        };
+    }
+
+    // Handled below by their default keys; see `[keys]`
+    let (modifiers, code) = match KeyCombo::from_event(modifiers, code) {
+        Some(pressed) => match app.keymap.translate(app.active_panel, pressed)? {
+            key if key == pressed => (modifiers, code),
+            key => key.to_event(modifiers),
+        },
+        None => (modifiers, code),
+    };
+
     match code {
         KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
         KeyCode::Char('k') | KeyCode::Up => Some(Action::NavigateUp),
         KeyCode::Char('j') | KeyCode::Down => Some(Action::NavigateDown),
+        // Needs a terminal that reports Shift+Enter; also in the host menu
+        KeyCode::Enter
+            if modifiers.contains(KeyModifiers::SHIFT) && app.active_panel == Panel::Hosts =>
+        {
+            app.host_list_state
+                .selected()
+                .and_then(|s| app.filtered_host_indices.get(s).copied())
+                .map(Action::ConnectWithOverrides)
+        }
         KeyCode::Enter => Some(Action::Select),
         KeyCode::Tab
         | KeyCode::BackTab
@@ -147,12 +378,46 @@ fn map_key_to_action(app: &App, modifiers: KeyModifiers, code: KeyCode) -> Optio
         | KeyCode::Right => Some(Action::SwitchPanel),
         KeyCode::Char('/') => Some(Action::StartSearch),
         KeyCode::Char('?') => Some(Action::ShowHelp),
+        KeyCode::Char('z') => Some(Action::ToggleZoom),
+        KeyCode::Char('m') => Some(Action::ShowHostMenu),
+        KeyCode::Char('A') => Some(Action::ToggleAgentForwarding),
+        KeyCode::Char('X') => Some(Action::CycleX11Forwarding),
+        KeyCode::Char('W') if app.active_panel == Panel::Hosts => app
+            .host_list_state
+            .selected()
+            .and_then(|s| app.filtered_host_indices.get(s).copied())
+            .map(Action::WakeHost),
         KeyCode::Char('x') => Some(Action::Disconnect),
         KeyCode::Char('a') => Some(Action::ShowAddTunnelModal),
-        KeyCode::Char('r') => Some(Action::RestoreTunnels),
+        KeyCode::Char('p') => Some(Action::QuickAddFromClipboard),
+        KeyCode::Char('r') => Some(Action::ShowRestoreModal),
+        KeyCode::Char(',') => Some(Action::ShowSettings),
+        KeyCode::Char('K') => Some(Action::ShowKeymap),
+        KeyCode::Char('S') => Some(Action::ShowSshCommand),
+        KeyCode::Char('n') => Some(Action::EditSessionNote),
+        KeyCode::Char('o') => Some(Action::ShowPortLookup),
+        KeyCode::Char('F') => Some(Action::AuditForwards),
+        KeyCode::Char('D') => Some(Action::DiscoverRemotePorts),
+        KeyCode::Char('P') => Some(Action::ToggleSafeMode),
+        KeyCode::Char(c @ '1'..='9') => Some(Action::QuickConnect(c as usize - '1' as usize)),
+        // Shift+Space where the terminal reports it; T everywhere
+        KeyCode::Char(' ')
+            if modifiers.contains(KeyModifiers::SHIFT) && app.active_panel == Panel::Tunnels =>
+        {
+            Some(Action::ToggleAllTunnels)
+        }
+        KeyCode::Char('T') => Some(Action::ToggleAllTunnels),
+        KeyCode::Char('v') if app.active_panel == Panel::Tunnels => Some(Action::ToggleVisualMode),
         KeyCode::Char(' ') => {
             if app.active_panel == Panel::Tunnels {
-                app.tunnel_list_state.selected().map(Action::ToggleTunnel)
+                // A folded group's row stands for the whole group
+                app.tunnel_list_state.selected().map(|idx| {
+                    if app.folded_group(idx).is_some() {
+                        Action::ToggleGroup(idx)
+                    } else {
+                        Action::ToggleTunnel(idx)
+                    }
+                })
             } else {
                 None
             }
@@ -164,6 +429,63 @@ fn map_key_to_action(app: &App, modifiers: KeyModifiers, code: KeyCode) -> Optio
                 None
             }
         }
+        KeyCode::Char('e') => {
+            if app.active_panel == Panel::Tunnels {
+                app.tunnel_list_state.selected().map(Action::EditTunnel)
+            } else {
+                None
+            }
+        }
+        KeyCode::Char('R') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::RetryTunnel)
+        }
+        KeyCode::Char('N') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::EditTunnelNote)
+        }
+        KeyCode::Char('I') if app.active_panel == Panel::Tunnels => app
+            .tunnel_list_state
+            .selected()
+            .map(Action::EditIdleTimeout),
+        KeyCode::Char('B') if app.active_panel == Panel::Tunnels => app
+            .tunnel_list_state
+            .selected()
+            .map(Action::EditBandwidthLimit),
+        KeyCode::Char('E') if app.active_panel == Panel::Tunnels => app
+            .tunnel_list_state
+            .selected()
+            .map(Action::ToggleEphemeral),
+        KeyCode::Char('C') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::TestTunnel)
+        }
+        KeyCode::Char('O') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::OpenTunnelUrl)
+        }
+        KeyCode::Char('H') if app.active_panel == Panel::Tunnels => app
+            .tunnel_list_state
+            .selected()
+            .map(Action::ToggleTunnelHttps),
+        KeyCode::Char('W') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::EditSchedule)
+        }
+        KeyCode::Char('G') if app.active_panel == Panel::Tunnels => app
+            .tunnel_list_state
+            .selected()
+            .map(Action::EditTunnelGroup),
+        KeyCode::Char('g') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::ToggleGroup)
+        }
+        KeyCode::Char('f') if app.active_panel == Panel::Tunnels => {
+            app.tunnel_list_state.selected().map(Action::FoldGroup)
+        }
+        KeyCode::Char('y') if app.active_panel == Panel::Tunnels => app
+            .tunnel_list_state
+            .selected()
+            .map(Action::DuplicateTunnel),
+        KeyCode::Char('c') if app.active_panel == Panel::Tunnels => {
+            Some(Action::ToggleCollapseDisabled)
+        }
+        KeyCode::Char('s') if app.active_panel == Panel::Tunnels => Some(Action::CycleTunnelSort),
+        KeyCode::Char('s') if app.active_panel == Panel::Hosts => Some(Action::CycleHostSort),
         _ => None,
     }
 }
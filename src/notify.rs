@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+use crate::action::Action;
+use crate::app::NotificationLevel;
+use crate::state::persistence::{SinkConfig, SinkKind};
+
+/// Connection events that can be routed to notification sinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Connected,
+    /// The user disconnected.
+    Disconnected,
+    /// An established master went away.
+    ConnectionLost,
+    /// A connection attempt failed.
+    ConnectionFailed,
+    TunnelFailed,
+}
+
+impl EventKind {
+    pub fn key(self) -> &'static str {
+        match self {
+            EventKind::Connected => "connected",
+            EventKind::Disconnected => "disconnected",
+            EventKind::ConnectionLost => "connection_lost",
+            EventKind::ConnectionFailed => "connection_failed",
+            EventKind::TunnelFailed => "tunnel_failed",
+        }
+    }
+
+    fn level(self) -> NotificationLevel {
+        match self {
+            EventKind::Connected => NotificationLevel::Success,
+            EventKind::Disconnected => NotificationLevel::Info,
+            EventKind::ConnectionLost | EventKind::ConnectionFailed | EventKind::TunnelFailed => {
+                NotificationLevel::Error
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Alias of the host the event is about.
+    pub host: String,
+    pub message: String,
+}
+
+/// Somewhere an event can be delivered. Delivery must not block the UI, so
+/// sinks that run programs spawn them and ignore failures.
+pub trait NotificationSink: Send + Sync {
+    fn send(&self, event: &Event);
+}
+
+/// The status bar, reached through the action channel.
+pub struct StatusBarSink {
+    tx: mpsc::UnboundedSender<Action>,
+}
+
+impl NotificationSink for StatusBarSink {
+    fn send(&self, event: &Event) {
+        let _ = self
+            .tx
+            .send(Action::Notify(event.message.clone(), event.kind.level()));
+    }
+}
+
+/// Desktop notification via `notify-send` (Linux) or `osascript` (macOS).
+pub struct DesktopSink;
+
+impl NotificationSink for DesktopSink {
+    fn send(&self, event: &Event) {
+        let title = format!("stm: {}", event.host);
+        let mut cmd = if cfg!(target_os = "macos") {
+            let script = format!(
+                "display notification {} with title {}",
+                applescript_string(&event.message),
+                applescript_string(&title)
+            );
+            let mut cmd = Command::new("osascript");
+            cmd.args(["-e", &script]);
+            cmd
+        } else {
+            let mut cmd = Command::new("notify-send");
+            cmd.args([&title, &event.message]);
+            cmd
+        };
+        spawn_quiet(&mut cmd);
+    }
+}
+
+/// Slack-compatible webhook: POSTs `{"text": ...}` with `curl`. The URL
+/// and body go in on stdin, as other users can read a process's arguments.
+pub struct WebhookSink {
+    url: String,
+}
+
+impl NotificationSink for WebhookSink {
+    fn send(&self, event: &Event) {
+        let body = serde_json::json!({
+            "text": format!("[{}] {}", event.host, event.message),
+            "event": event.kind.key(),
+            "host": event.host,
+        })
+        .to_string();
+        let config = format!(
+            "url = {}\ndata-raw = {}\n",
+            curl_config_string(&self.url),
+            curl_config_string(&body)
+        );
+        let mut cmd = Command::new("curl");
+        cmd.args([
+            "-sS",
+            "--max-time",
+            "10",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--config",
+            "-",
+        ]);
+        spawn_with_input(&mut cmd, config);
+    }
+}
+
+/// Runs a shell command with `STM_EVENT`, `STM_HOST` and `STM_MESSAGE` set.
+pub struct CommandSink {
+    command: String,
+}
+
+impl NotificationSink for CommandSink {
+    fn send(&self, event: &Event) {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", &self.command])
+            .env("STM_EVENT", event.kind.key())
+            .env("STM_HOST", &event.host)
+            .env("STM_MESSAGE", &event.message);
+        spawn_quiet(&mut cmd);
+    }
+}
+
+/// A sink with the events and hosts it is interested in.
+struct Route {
+    sink: Box<dyn NotificationSink>,
+    events: Vec<EventKind>,
+    hosts: Vec<String>,
+}
+
+impl Route {
+    fn wants(&self, event: &Event) -> bool {
+        (self.events.is_empty() || self.events.contains(&event.kind))
+            && (self.hosts.is_empty()
+                || self
+                    .hosts
+                    .iter()
+                    .any(|p| crate::ssh::config::wildcard_match(p, &event.host)))
+    }
+}
+
+/// Routes events to the sinks configured under `[[notifications]]`.
+pub struct Notifier {
+    routes: Vec<Route>,
+}
+
+impl Notifier {
+    /// The status bar gets every event unless a `status-bar` entry narrows it.
+    pub fn new(configs: &[SinkConfig], tx: mpsc::UnboundedSender<Action>) -> Self {
+        let mut routes: Vec<Route> = configs
+            .iter()
+            .map(|config| Route {
+                sink: match config.sink {
+                    SinkKind::StatusBar => Box::new(StatusBarSink { tx: tx.clone() }),
+                    SinkKind::Desktop => Box::new(DesktopSink),
+                    SinkKind::Webhook { ref url } => Box::new(WebhookSink { url: url.clone() }),
+                    SinkKind::Command { ref command } => Box::new(CommandSink {
+                        command: command.clone(),
+                    }),
+                },
+                events: config.events.clone(),
+                hosts: config.hosts.clone(),
+            })
+            .collect();
+        if !configs.iter().any(|c| c.sink == SinkKind::StatusBar) {
+            routes.push(Route {
+                sink: Box::new(StatusBarSink { tx }),
+                events: Vec::new(),
+                hosts: Vec::new(),
+            });
+        }
+        Self { routes }
+    }
+
+    pub fn dispatch(&self, event: &Event) {
+        for route in self.routes.iter().filter(|r| r.wants(event)) {
+            route.sink.send(event);
+        }
+    }
+}
+
+fn spawn_quiet(cmd: &mut Command) {
+    cmd.stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    if let Ok(mut child) = cmd.spawn() {
+        tokio::spawn(async move {
+            let _ = child.wait().await;
+        });
+    }
+}
+
+/// Like `spawn_quiet`, with `input` written to the program's stdin.
+fn spawn_with_input(cmd: &mut Command, input: String) {
+    use tokio::io::AsyncWriteExt;
+
+    cmd.stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    if let Ok(mut child) = cmd.spawn() {
+        tokio::spawn(async move {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(input.as_bytes()).await;
+            }
+            let _ = child.wait().await;
+        });
+    }
+}
+
+/// A quoted value for a curl config file.
+fn curl_config_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: EventKind, host: &str) -> Event {
+        Event {
+            kind,
+            host: host.to_string(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_route_filters() {
+        let route = Route {
+            sink: Box::new(DesktopSink),
+            events: vec![EventKind::ConnectionLost],
+            hosts: vec!["prod-*".to_string()],
+        };
+        assert!(route.wants(&event(EventKind::ConnectionLost, "prod-db")));
+        assert!(!route.wants(&event(EventKind::ConnectionLost, "staging-db")));
+        assert!(!route.wants(&event(EventKind::Connected, "prod-db")));
+    }
+
+    #[test]
+    fn test_curl_config_string() {
+        assert_eq!(
+            curl_config_string(r#"{"text":"a \"b\"\n"}"#),
+            r#""{\"text\":\"a \\\"b\\\"\\n\"}""#
+        );
+        assert_eq!(curl_config_string("line\nbreak"), r#""line\nbreak""#);
+    }
+
+    #[test]
+    fn test_status_bar_default_route() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        Notifier::new(&[], tx).dispatch(&event(EventKind::Connected, "web"));
+        assert!(matches!(rx.try_recv(), Ok(Action::Notify(..))));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let only_failures = SinkConfig {
+            sink: SinkKind::StatusBar,
+            events: vec![EventKind::ConnectionFailed],
+            hosts: Vec::new(),
+        };
+        Notifier::new(&[only_failures], tx).dispatch(&event(EventKind::Connected, "web"));
+        assert!(rx.try_recv().is_err());
+    }
+}
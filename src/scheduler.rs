@@ -0,0 +1,207 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// A daily window a tunnel should be up in, e.g. `09:00-18:00 weekdays`.
+/// Windows that end before they start run past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Schedule {
+    /// Minutes after midnight.
+    start: u16,
+    end: u16,
+    /// Bit 0 is Monday; the day a window starts on.
+    days: u8,
+}
+
+impl Schedule {
+    /// Parse `HH:MM-HH:MM` followed by optional days: `daily`, `weekdays`,
+    /// `weekends`, or names and ranges like `mon,wed` or `mon-thu`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = text.split_whitespace();
+        let window = parts.next().ok_or("expected HH:MM-HH:MM")?;
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM, got '{window}'"))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            return Err("window starts and ends at the same time".to_string());
+        }
+        let days = match parts.collect::<Vec<_>>().join("").to_lowercase().as_str() {
+            "" | "daily" => 0b111_1111,
+            "weekdays" => 0b001_1111,
+            "weekends" => 0b110_0000,
+            list => parse_days(list)?,
+        };
+        Ok(Self { start, end, days })
+    }
+
+    /// True if `at` (local time) falls inside the window.
+    pub fn is_active(&self, at: NaiveDateTime) -> bool {
+        let minute = (at.hour() * 60 + at.minute()) as u16;
+        let today = at.weekday().num_days_from_monday();
+        let on = |day: u32| self.days & (1 << day) != 0;
+        if self.start < self.end {
+            on(today) && (self.start..self.end).contains(&minute)
+        } else {
+            // Past midnight the window belongs to the day it started on
+            let yesterday = (today + 6) % 7;
+            on(today) && minute >= self.start || on(yesterday) && minute < self.end
+        }
+    }
+}
+
+fn parse_time(text: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid time '{text}'");
+    let (hours, minutes) = text.split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+fn parse_day(name: &str) -> Result<u32, String> {
+    DAY_NAMES
+        .iter()
+        .position(|&d| name.starts_with(d))
+        .map(|d| d as u32)
+        .ok_or_else(|| format!("unknown day '{name}'"))
+}
+
+/// `mon,wed` or `mon-thu,sat` as a day bitmask.
+fn parse_days(list: &str) -> Result<u8, String> {
+    let mut days = 0;
+    for item in list.split(',') {
+        let (first, last) = match item.split_once('-') {
+            Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+            None => (parse_day(item)?, parse_day(item)?),
+        };
+        // A range may wrap past Sunday, e.g. `fri-mon`
+        let mut day = first;
+        loop {
+            days |= 1 << day;
+            if day == last {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Ok(days)
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )?;
+        match self.days {
+            0b111_1111 => Ok(()),
+            0b001_1111 => write!(f, " weekdays"),
+            0b110_0000 => write!(f, " weekends"),
+            days => {
+                let names: Vec<&str> = (0..7)
+                    .filter(|d| days & (1 << d) != 0)
+                    .map(|d| DAY_NAMES[d])
+                    .collect();
+                write!(f, " {}", names.join(","))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        Self::parse(&text)
+    }
+}
+
+/// Deserialize an optional schedule, reading one that doesn't parse (edited
+/// by hand, or from an older format) as none instead of failing.
+pub fn deserialize_lenient<'de, D>(deserializer: D) -> Result<Option<Schedule>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value
+        .as_ref()
+        .and_then(|v| v.as_str())
+        .and_then(|text| Schedule::parse(text).ok()))
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// 2024-06-03 is a Monday.
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let schedule = Schedule::parse("9:00-18:00 weekdays").unwrap();
+        assert_eq!(schedule.to_string(), "09:00-18:00 weekdays");
+        assert_eq!(
+            Schedule::parse("22:00-06:30 fri-mon").unwrap().to_string(),
+            "22:00-06:30 mon,fri,sat,sun"
+        );
+        assert_eq!(
+            Schedule::parse("08:00-12:00 Mon, Wed").unwrap().to_string(),
+            "08:00-12:00 mon,wed"
+        );
+        assert_eq!(
+            Schedule::parse("08:00-12:00").unwrap().to_string(),
+            "08:00-12:00"
+        );
+        assert!(Schedule::parse("25:00-26:00").is_err());
+        assert!(Schedule::parse("09:00").is_err());
+        assert!(Schedule::parse("09:00-09:00").is_err());
+        assert!(Schedule::parse("09:00-17:00 someday").is_err());
+    }
+
+    #[test]
+    fn test_is_active() {
+        let office = Schedule::parse("09:00-18:00 weekdays").unwrap();
+        assert!(office.is_active(at(3, 9, 0)));
+        assert!(office.is_active(at(7, 17, 59)));
+        assert!(!office.is_active(at(3, 18, 0)));
+        assert!(!office.is_active(at(8, 12, 0)));
+
+        // Friday night into Saturday morning only
+        let night = Schedule::parse("22:00-06:00 fri").unwrap();
+        assert!(night.is_active(at(7, 23, 0)));
+        assert!(night.is_active(at(8, 5, 59)));
+        assert!(!night.is_active(at(7, 5, 0)));
+        assert!(!night.is_active(at(8, 23, 0)));
+    }
+
+    #[test]
+    fn test_serde_as_text() {
+        let json = serde_json::to_string(&Schedule::parse("09:00-18:00").unwrap()).unwrap();
+        assert_eq!(json, "\"09:00-18:00\"");
+        let parsed: Schedule = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.to_string(), "09:00-18:00");
+        assert!(serde_json::from_str::<Schedule>("\"later\"").is_err());
+    }
+}
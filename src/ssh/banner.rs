@@ -0,0 +1,208 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::ssh::health::{connect_tcp, tcp_endpoint};
+use crate::ssh::tunnel::Tunnel;
+
+/// How long a service gets to greet, or to answer a probe.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(800);
+/// Enough of an HTTP response to find the `<title>` in most pages.
+const MAX_REPLY: usize = 16 * 1024;
+/// Longest label taken from a page title.
+const MAX_TITLE: usize = 32;
+
+/// Postgres `SSLRequest`: the server answers a single `S` or `N`.
+const PG_SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+
+/// A label for the service behind an enabled tunnel, from what it says: its
+/// greeting (ssh, MySQL, SMTP…), a Redis `PING`, a Postgres `SSLRequest` or
+/// its HTTP root page's `<title>`. Each probe uses its own connection.
+pub async fn identify(tunnel: &Tunnel) -> Option<String> {
+    let (host, port) = tcp_endpoint(tunnel)?;
+
+    if let Some(greeting) = exchange(host, port, b"").await {
+        return parse_greeting(&greeting);
+    }
+    match exchange(host, port, b"PING\r\n").await {
+        Some(reply) if is_redis(&reply) => return Some("redis".to_string()),
+        // An HTTP server answers anything with a 400; ask it properly below
+        Some(reply) if reply.starts_with(b"HTTP/") => {}
+        _ => {
+            if let Some(b"S" | b"N") = exchange(host, port, &PG_SSL_REQUEST).await.as_deref() {
+                return Some("postgres".to_string());
+            }
+        }
+    }
+
+    let mut reply = exchange(host, port, &http_get("/")).await?;
+    // One hop for dashboards that send `/` on to a login page
+    if let Some(path) = redirect_path(&reply) {
+        reply = exchange(host, port, &http_get(&path))
+            .await
+            .unwrap_or(reply);
+    }
+    parse_http(&reply)
+}
+
+/// Send `request` (nothing, to wait for a greeting) and collect the reply
+/// until the service closes, goes quiet or has said enough.
+async fn exchange(host: &str, port: u16, request: &[u8]) -> Option<Vec<u8>> {
+    let mut stream = connect_tcp(host, port).await?;
+    if !request.is_empty() {
+        stream.write_all(request).await.ok()?;
+    }
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 4096];
+    while reply.len() < MAX_REPLY {
+        match tokio::time::timeout(REPLY_TIMEOUT, stream.read(&mut buf)).await {
+            Ok(Ok(n @ 1..)) => reply.extend_from_slice(&buf[..n]),
+            _ => break,
+        }
+    }
+    (!reply.is_empty()).then_some(reply)
+}
+
+fn http_get(path: &str) -> Vec<u8> {
+    format!(
+        "GET {path} HTTP/1.0\r\nHost: localhost\r\nUser-Agent: stm\r\nAccept: text/html\r\n\r\n"
+    )
+    .into_bytes()
+}
+
+/// Services that speak first.
+fn parse_greeting(greeting: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(greeting);
+    let name = if text.starts_with("SSH-") {
+        "ssh"
+    } else if is_mysql(greeting) {
+        if text.contains("MariaDB") {
+            "mariadb"
+        } else {
+            "mysql"
+        }
+    } else if text.starts_with("220") && text.contains("SMTP") {
+        "smtp"
+    } else if text.starts_with("220") && text.to_ascii_uppercase().contains("FTP") {
+        "ftp"
+    } else if text.starts_with("* OK") {
+        "imap"
+    } else if text.starts_with("+OK") {
+        "pop3"
+    } else {
+        return None;
+    };
+    Some(name.to_string())
+}
+
+/// MySQL's handshake packet: 3-byte length, sequence 0, protocol version 10.
+fn is_mysql(greeting: &[u8]) -> bool {
+    greeting.len() > 5 && greeting[3] == 0 && greeting[4] == 10
+}
+
+fn is_redis(reply: &[u8]) -> bool {
+    reply.starts_with(b"+PONG") || reply.starts_with(b"-NOAUTH")
+}
+
+/// The path of a same-host 3xx `Location`.
+fn redirect_path(reply: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(reply);
+    let status = text.lines().next()?.split_whitespace().nth(1)?;
+    if !status.starts_with('3') {
+        return None;
+    }
+    let location = text
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("location").then(|| value.trim())
+        })?;
+    location
+        .starts_with('/')
+        .then(|| location.to_string())
+        .filter(|path| !path.starts_with("//"))
+}
+
+/// The page's `<title>`, or `http` / `tls` when there is none.
+fn parse_http(reply: &[u8]) -> Option<String> {
+    // A TLS alert in answer to plain HTTP
+    if reply.starts_with(&[0x15, 0x03]) {
+        return Some("tls".to_string());
+    }
+    if !reply.starts_with(b"HTTP/") {
+        return None;
+    }
+    let text = String::from_utf8_lossy(reply);
+    Some(page_title(&text).unwrap_or_else(|| "http".to_string()))
+}
+
+fn page_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = html[start..end]
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    Some(match title.char_indices().nth(MAX_TITLE) {
+        Some((cut, _)) => format!("{}…", title[..cut].trim_end()),
+        None => title,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_greeting() {
+        assert_eq!(
+            parse_greeting(b"SSH-2.0-OpenSSH_9.6\r\n").as_deref(),
+            Some("ssh")
+        );
+        let mysql = b"\x4a\x00\x00\x00\x0a8.0.36\x00\x08\x00\x00\x00";
+        assert_eq!(parse_greeting(mysql).as_deref(), Some("mysql"));
+        assert_eq!(
+            parse_greeting(b"220 mail.example.com ESMTP Postfix\r\n").as_deref(),
+            Some("smtp")
+        );
+        assert_eq!(parse_greeting(b"hello\n"), None);
+    }
+
+    #[test]
+    fn test_parse_http() {
+        let reply = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\
+            <html><head><TITLE>\n  Grafana &amp; friends\n</TITLE></head></html>";
+        assert_eq!(parse_http(reply).as_deref(), Some("Grafana & friends"));
+        assert_eq!(
+            parse_http(b"HTTP/1.1 204 No Content\r\n\r\n").as_deref(),
+            Some("http")
+        );
+        assert_eq!(
+            parse_http(b"\x15\x03\x01\x00\x02\x02\x50").as_deref(),
+            Some("tls")
+        );
+
+        let long = format!("HTTP/1.1 200 OK\r\n\r\n<title>{}</title>", "x".repeat(40));
+        assert_eq!(
+            parse_http(long.as_bytes()).map(|t| t.chars().count()),
+            Some(MAX_TITLE + 1)
+        );
+    }
+
+    #[test]
+    fn test_redirect_path() {
+        let reply = b"HTTP/1.1 302 Found\r\nlocation: /login\r\n\r\n";
+        assert_eq!(redirect_path(reply).as_deref(), Some("/login"));
+        let reply = b"HTTP/1.1 301 Moved\r\nLocation: https://example.com/\r\n\r\n";
+        assert_eq!(redirect_path(reply), None);
+        assert_eq!(redirect_path(b"HTTP/1.1 200 OK\r\n\r\n"), None);
+    }
+}
@@ -0,0 +1,158 @@
+use chrono::{Local, NaiveDateTime};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Certificates expiring within this window are flagged.
+const EXPIRY_WARNING_HOURS: i64 = 24;
+
+/// Validity window of an OpenSSH certificate; `None` bounds are open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertStatus {
+    Valid(Validity),
+    ExpiringSoon(NaiveDateTime),
+    Expired(NaiveDateTime),
+    NotYetValid(NaiveDateTime),
+    Unreadable(String),
+}
+
+impl CertStatus {
+    pub fn is_warning(&self) -> bool {
+        !matches!(self, CertStatus::Valid(_))
+    }
+
+    pub fn describe(&self) -> String {
+        let fmt = |t: &NaiveDateTime| t.format("%Y-%m-%d %H:%M").to_string();
+        match self {
+            CertStatus::Valid(Validity { to: Some(to), .. }) => format!("valid until {}", fmt(to)),
+            CertStatus::Valid(_) => "valid forever".to_string(),
+            CertStatus::ExpiringSoon(to) => format!("expires soon ({})", fmt(to)),
+            CertStatus::Expired(to) => format!("EXPIRED since {}", fmt(to)),
+            CertStatus::NotYetValid(from) => format!("not valid before {}", fmt(from)),
+            CertStatus::Unreadable(e) => format!("unreadable: {e}"),
+        }
+    }
+}
+
+/// Inspect a certificate with `ssh-keygen -L` and classify it against the local clock.
+pub fn certificate_status(path: &Path) -> CertStatus {
+    let output = match Command::new("ssh-keygen")
+        .arg("-L")
+        .arg("-f")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => return CertStatus::Unreadable(e.to_string()),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return CertStatus::Unreadable(stderr.trim().to_string());
+    }
+
+    match parse_validity(&String::from_utf8_lossy(&output.stdout)) {
+        Some(validity) => classify(validity, Local::now().naive_local()),
+        None => CertStatus::Unreadable("no validity in ssh-keygen output".to_string()),
+    }
+}
+
+/// Parse the `Valid:` line of `ssh-keygen -L` output. ssh-keygen prints local times.
+pub fn parse_validity(output: &str) -> Option<Validity> {
+    let line = output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Valid:"))?
+        .trim();
+    let time = |s: &str| NaiveDateTime::parse_from_str(s.trim(), "%Y-%m-%dT%H:%M:%S").ok();
+
+    if line == "forever" {
+        return Some(Validity {
+            from: None,
+            to: None,
+        });
+    }
+    if let Some(rest) = line.strip_prefix("from ") {
+        let (from, to) = rest.split_once(" to ")?;
+        return Some(Validity {
+            from: Some(time(from)?),
+            to: Some(time(to)?),
+        });
+    }
+    if let Some(to) = line.strip_prefix("before ") {
+        return Some(Validity {
+            from: None,
+            to: Some(time(to)?),
+        });
+    }
+    if let Some(from) = line.strip_prefix("after ") {
+        return Some(Validity {
+            from: Some(time(from)?),
+            to: None,
+        });
+    }
+    None
+}
+
+fn classify(validity: Validity, now: NaiveDateTime) -> CertStatus {
+    if let Some(from) = validity.from {
+        if now < from {
+            return CertStatus::NotYetValid(from);
+        }
+    }
+    match validity.to {
+        Some(to) if now >= to => CertStatus::Expired(to),
+        Some(to) if to - now < chrono::Duration::hours(EXPIRY_WARNING_HOURS) => {
+            CertStatus::ExpiringSoon(to)
+        }
+        _ => CertStatus::Valid(validity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_parse_validity_range() {
+        let output = "id_ed25519-cert.pub:\n        Type: ssh-ed25519-cert-v01@openssh.com user certificate\n        Valid: from 2025-03-01T09:00:00 to 2025-03-02T09:00:00\n";
+        let validity = parse_validity(output).unwrap();
+        assert_eq!(validity.from, Some(at("2025-03-01T09:00:00")));
+        assert_eq!(validity.to, Some(at("2025-03-02T09:00:00")));
+    }
+
+    #[test]
+    fn test_parse_validity_forever() {
+        let validity = parse_validity("        Valid: forever\n").unwrap();
+        assert_eq!(validity.from, None);
+        assert_eq!(validity.to, None);
+    }
+
+    #[test]
+    fn test_classify() {
+        let validity = Validity {
+            from: Some(at("2025-03-01T09:00:00")),
+            to: Some(at("2025-03-02T09:00:00")),
+        };
+        assert!(matches!(
+            classify(validity, at("2025-02-28T09:00:00")),
+            CertStatus::NotYetValid(_)
+        ));
+        assert!(matches!(
+            classify(validity, at("2025-03-01T12:00:00")),
+            CertStatus::ExpiringSoon(_)
+        ));
+        assert!(matches!(
+            classify(validity, at("2025-03-03T09:00:00")),
+            CertStatus::Expired(_)
+        ));
+    }
+}
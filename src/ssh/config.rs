@@ -1,5 +1,138 @@
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+/// X11 forwarding mode for a connection (`-X` untrusted, `-Y` trusted).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum X11Forwarding {
+    #[default]
+    Off,
+    Untrusted,
+    Trusted,
+}
+
+impl X11Forwarding {
+    /// Cycle off -> untrusted -> trusted -> off.
+    pub fn next(self) -> Self {
+        match self {
+            X11Forwarding::Off => X11Forwarding::Untrusted,
+            X11Forwarding::Untrusted => X11Forwarding::Trusted,
+            X11Forwarding::Trusted => X11Forwarding::Off,
+        }
+    }
+}
+
+/// One hop of a `ProxyJump` chain: `[user@]host[:port]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl JumpHost {
+    /// Parse a single hop, accepting `[user@]host[:port]`, bracketed IPv6
+    /// literals and the `ssh://` URI form.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        let spec = spec.strip_prefix("ssh://").unwrap_or(spec);
+        let (user, rest) = match spec.rsplit_once('@') {
+            Some((user, rest)) => (Some(user.to_string()), rest),
+            None => (None, spec),
+        };
+
+        let (host, port) = if let Some(bracketed) = rest.strip_prefix('[') {
+            let (host, after) = bracketed.split_once(']')?;
+            (host, after.strip_prefix(':'))
+        } else if rest.matches(':').count() == 1 {
+            let (host, port) = rest.split_once(':')?;
+            (host, Some(port))
+        } else {
+            // Bare host, or an unbracketed IPv6 literal without a port
+            (rest, None)
+        };
+        if host.is_empty() {
+            return None;
+        }
+
+        let port = match port {
+            Some(p) => Some(p.parse().ok()?),
+            None => None,
+        };
+        Some(Self {
+            user: user.filter(|u| !u.is_empty()),
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl std::fmt::Display for JumpHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref user) = self.user {
+            write!(f, "{user}@")?;
+        }
+        match self.port {
+            Some(port) if self.host.contains(':') => write!(f, "[{}]:{port}", self.host),
+            Some(port) => write!(f, "{}:{port}", self.host),
+            None => write!(f, "{}", self.host),
+        }
+    }
+}
+
+/// Parse a `ProxyJump` value into its hops. `none` disables jumping.
+pub fn parse_proxy_jump(value: &str) -> Vec<JumpHost> {
+    if value.trim().eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    value.split(',').filter_map(JumpHost::parse).collect()
+}
+
+/// A `LocalForward` declared in the ssh config. The bind address is not kept;
+/// stm binds forwards the same way as tunnels added in the UI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalForward {
+    pub local_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+impl LocalForward {
+    /// Parse a `LocalForward` value: `[bind_address:]port host:hostport`.
+    /// Unix socket forwards are not supported and return `None`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split_whitespace();
+        let listen = parts.next()?;
+        let dest = parts.next()?;
+
+        let local_port = listen.rsplit(':').next()?.parse().ok()?;
+        let (remote_host, remote_port) = match dest.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed.split_once("]:")?;
+                (host, port)
+            }
+            None => dest.rsplit_once(':')?,
+        };
+        if remote_host.is_empty() || remote_host.contains('/') {
+            return None;
+        }
+        Some(Self {
+            local_port,
+            remote_host: remote_host.to_string(),
+            remote_port: remote_port.parse().ok()?,
+        })
+    }
+}
+
+/// Forwarding and canonicalization directives from one `Host` block (or
+/// from before the first one).
+struct ForwardBlock {
+    patterns: String,
+    forwards: Vec<LocalForward>,
+    clear_all: Option<bool>,
+    canonicalize: Option<bool>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SshHost {
     pub name: String,
@@ -7,13 +140,102 @@ pub struct SshHost {
     pub user: Option<String>,
     pub port: Option<u16>,
     pub identity_file: Option<PathBuf>,
-    pub proxy_jump: Option<String>,
+    pub certificate_file: Option<PathBuf>,
+    /// ProxyJump chain, first hop first.
+    pub proxy_jump: Vec<JumpHost>,
+    /// `ProxyCommand` from the ssh config, or built from stm's `proxy` setting.
+    pub proxy_command: Option<String>,
+    /// The config enables `CanonicalizeHostname`; resolve with `ssh -G` before connecting.
+    pub canonicalize: bool,
+    /// Hostname after canonicalization, once resolved.
+    pub canonical_hostname: Option<String>,
+    /// Set from stm config or toggled at runtime, not parsed from ssh config.
+    pub forward_agent: bool,
+    pub forward_x11: X11Forwarding,
+    /// Use a per-alias control socket instead of one per hostname:port.
+    pub separate_socket: bool,
+    /// `UserKnownHostsFile` for stm's master, from stm config.
+    pub known_hosts_file: Option<PathBuf>,
+    /// `RemoteCommand` from the ssh config (`none` is treated as unset).
+    pub remote_command: Option<String>,
+    /// `RequestTTY` from the ssh config, lowercased.
+    pub request_tty: Option<String>,
+    /// `LocalForward`s from every block matching this host, in file order.
+    pub local_forwards: Vec<LocalForward>,
+    /// First `ClearAllForwardings` value from the matching blocks.
+    pub clear_all_forwardings: Option<bool>,
+    /// `BindInterface`: network interface to send the connection from.
+    pub bind_interface: Option<String>,
+    /// `AddressFamily`, lowercased (`inet` or `inet6`; `any` is treated as unset).
+    pub address_family: Option<String>,
+    /// Top-level ssh config file the host was read from.
+    pub source: Option<PathBuf>,
 }
 
 impl SshHost {
-    /// Returns the effective hostname (hostname or name fallback).
+    /// Returns the effective hostname (canonical, hostname or name fallback).
     pub fn effective_hostname(&self) -> &str {
-        self.hostname.as_deref().unwrap_or(&self.name)
+        self.canonical_hostname
+            .as_deref()
+            .or(self.hostname.as_deref())
+            .unwrap_or(&self.name)
+    }
+
+    /// Returns true if this entry is a `Host` pattern that needs a concrete hostname.
+    pub fn is_pattern(&self) -> bool {
+        is_pattern(&self.name)
+    }
+
+    /// Create a concrete host from a pattern entry, applying its settings.
+    /// `%h` in the pattern's HostName is replaced with the concrete name.
+    pub fn instantiate(&self, concrete: &str) -> SshHost {
+        SshHost {
+            name: concrete.to_string(),
+            hostname: self.hostname.as_ref().map(|h| h.replace("%h", concrete)),
+            canonical_hostname: None,
+            ..self.clone()
+        }
+    }
+
+    /// A session-only copy of this host with another user and/or port. It is
+    /// named `user@alias:port` and gets its own control socket.
+    pub fn variant(&self, user: Option<String>, port: Option<u16>) -> SshHost {
+        let mut name = self.name.clone();
+        if let Some(ref user) = user {
+            name = format!("{user}@{name}");
+        }
+        if let Some(port) = port {
+            name = format!("{name}:{port}");
+        }
+        SshHost {
+            name,
+            user: user.or_else(|| self.user.clone()),
+            port: port.or(self.port),
+            separate_socket: true,
+            ..self.clone()
+        }
+    }
+
+    /// Returns true if the ssh config asks for a remote command or a forced TTY,
+    /// which conflict with the tunnel-only (`-N`) master.
+    pub fn forces_session(&self) -> bool {
+        self.remote_command.is_some()
+            || matches!(self.request_tty.as_deref(), Some("yes" | "force"))
+    }
+
+    /// Forwards plain `ssh` would set up for this host: the accumulated
+    /// `LocalForward`s, unless `ClearAllForwardings yes` applies.
+    pub fn config_forwards(&self) -> &[LocalForward] {
+        if self.clear_all_forwardings == Some(true) {
+            &[]
+        } else {
+            &self.local_forwards
+        }
+    }
+
+    /// Returns true if the hostname still has to be canonicalized before connecting.
+    pub fn needs_canonicalization(&self) -> bool {
+        self.canonicalize && self.canonical_hostname.is_none()
     }
 
     /// Returns the effective port (port or 22 fallback).
@@ -28,6 +250,98 @@ impl SshHost {
             None => self.effective_hostname().to_string(),
         }
     }
+
+    /// Returns the ssh options (port, identity, jump host) needed to reach this host.
+    pub fn connection_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        if let Some(ref identity) = self.identity_file {
+            args.extend(["-i".to_string(), identity.to_string_lossy().to_string()]);
+        }
+        if let Some(ref cert) = self.certificate_file {
+            args.extend([
+                "-o".to_string(),
+                format!("CertificateFile={}", cert.to_string_lossy()),
+            ]);
+        }
+        if let Some(ref interface) = self.bind_interface {
+            args.extend(["-o".to_string(), format!("BindInterface={interface}")]);
+        }
+        match self.address_family.as_deref() {
+            Some("inet") => args.push("-4".to_string()),
+            Some("inet6") => args.push("-6".to_string()),
+            _ => {}
+        }
+        if !self.proxy_jump.is_empty() {
+            args.extend(["-J".to_string(), self.jump_chain()]);
+        } else if let Some(ref command) = self.proxy_command {
+            args.extend(["-o".to_string(), format!("ProxyCommand={command}")]);
+        }
+        if self.forward_agent {
+            args.push("-A".to_string());
+        }
+        match self.forward_x11 {
+            X11Forwarding::Off => {}
+            X11Forwarding::Untrusted => args.push("-X".to_string()),
+            X11Forwarding::Trusted => args.push("-Y".to_string()),
+        }
+        args
+    }
+
+    /// Returns the certificate ssh will use: the explicit `CertificateFile`, or
+    /// the `<identity>-cert.pub` that ssh picks up next to the identity file.
+    pub fn certificate_path(&self) -> Option<PathBuf> {
+        if let Some(ref cert) = self.certificate_file {
+            return Some(cert.clone());
+        }
+        let identity = self.identity_file.as_ref()?;
+        let mut name = identity.file_name()?.to_os_string();
+        name.push("-cert.pub");
+        let implicit = identity.with_file_name(name);
+        implicit.is_file().then_some(implicit)
+    }
+
+    /// Returns the ProxyJump chain in `-J` syntax (comma-separated hops).
+    pub fn jump_chain(&self) -> String {
+        self.proxy_jump
+            .iter()
+            .map(|hop| hop.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Returns an equivalent interactive `ssh` command line, shell-quoted.
+    pub fn ssh_command(&self) -> String {
+        let mut parts = vec!["ssh".to_string()];
+        parts.extend(self.connection_args().iter().map(|a| shell_quote(a)));
+        parts.push(shell_quote(&self.display_target()));
+        parts.join(" ")
+    }
+
+    /// Returns an `ssh -N` command line opening `forwards`, given as
+    /// (`-L`/`-R`, spec) pairs, shell-quoted.
+    pub fn forward_command(&self, forwards: &[(&str, String)]) -> String {
+        let mut parts = vec!["ssh".to_string(), "-N".to_string()];
+        parts.extend(self.connection_args().iter().map(|a| shell_quote(a)));
+        for (flag, spec) in forwards {
+            parts.push(flag.to_string());
+            parts.push(shell_quote(spec));
+        }
+        parts.push(shell_quote(&self.display_target()));
+        parts.join(" ")
+    }
+}
+
+/// Quote a word for POSIX shells if it contains anything but safe characters.
+pub fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:@=,+%".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
 }
 
 /// Parse an SSH config file into a list of host entries.
@@ -39,12 +353,60 @@ pub fn parse_ssh_config(path: &Path) -> anyhow::Result<Vec<SshHost>> {
     parse_ssh_config_content(&content, path.parent())
 }
 
+/// Parse several SSH config files into one host list, each host tagged
+/// with the file it came from. An alias defined in more than one file keeps
+/// its first definition. Files that can't be read are reported, not fatal.
+pub fn parse_ssh_configs(paths: &[PathBuf]) -> (Vec<SshHost>, Vec<String>) {
+    let mut hosts: Vec<SshHost> = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match parse_ssh_config(path) {
+            Ok(parsed) => {
+                for mut host in parsed {
+                    if hosts.iter().all(|h| h.name != host.name) {
+                        host.source = Some(path.clone());
+                        hosts.push(host);
+                    }
+                }
+            }
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+    (hosts, errors)
+}
+
+/// Short name of a config file for host badges: its file name, or its
+/// directory's for a file named `config` (`~/work/ssh/config` -> `ssh`).
+pub fn source_badge(path: &Path) -> String {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = if name == "config" {
+        path.parent()
+            .and_then(|dir| dir.file_name())
+            .map(|dir| dir.to_string_lossy())
+            .unwrap_or(name)
+    } else {
+        name
+    };
+    name.trim_start_matches('.').to_string()
+}
+
 fn parse_ssh_config_content(
     content: &str,
     config_dir: Option<&Path>,
 ) -> anyhow::Result<Vec<SshHost>> {
     let mut hosts = Vec::new();
     let mut current_host: Option<SshHost> = None;
+    // LocalForward accumulates across every matching block (including
+    // `Host *`), and CanonicalizeHostname is usually set under `Host *`, so
+    // these directives are collected per block and resolved for each host at
+    // the end. Lines before the first `Host` apply to every host.
+    let mut blocks = vec![ForwardBlock {
+        patterns: "*".to_string(),
+        forwards: Vec::new(),
+        clear_all: None,
+        canonicalize: None,
+    }];
+    let mut current_block = Some(0);
 
     for line in content.lines() {
         let line = line.trim();
@@ -62,6 +424,40 @@ fn parse_ssh_config_content(
 
         let keyword_lower = keyword.to_lowercase();
 
+        match keyword_lower.as_str() {
+            "host" => {
+                blocks.push(ForwardBlock {
+                    patterns: value.to_string(),
+                    forwards: Vec::new(),
+                    clear_all: None,
+                    canonicalize: None,
+                });
+                current_block = Some(blocks.len() - 1);
+            }
+            "match" | "include" => current_block = None,
+            "localforward" => {
+                if let (Some(idx), Some(forward)) = (current_block, LocalForward::parse(value)) {
+                    blocks[idx].forwards.push(forward);
+                }
+            }
+            "clearallforwardings" => {
+                if let Some(idx) = current_block {
+                    let block = &mut blocks[idx];
+                    block.clear_all = block.clear_all.or(Some(value.eq_ignore_ascii_case("yes")));
+                }
+            }
+            "canonicalizehostname" => {
+                if let Some(idx) = current_block {
+                    let value = value.to_lowercase();
+                    let block = &mut blocks[idx];
+                    block.canonicalize = block
+                        .canonicalize
+                        .or(Some(value == "yes" || value == "always"));
+                }
+            }
+            _ => {}
+        }
+
         if keyword_lower == "host" {
             // Save previous host if any
             if let Some(host) = current_host.take() {
@@ -107,7 +503,27 @@ fn parse_ssh_config_content(
                 "identityfile" => {
                     host.identity_file = Some(expand_tilde(value));
                 }
-                "proxyjump" => host.proxy_jump = Some(value.to_string()),
+                "certificatefile" => {
+                    host.certificate_file = Some(expand_tilde(value));
+                }
+                // ssh uses whichever of ProxyJump/ProxyCommand comes first
+                "proxyjump" if host.proxy_command.is_none() => {
+                    host.proxy_jump = parse_proxy_jump(value)
+                }
+                "proxycommand" if host.proxy_jump.is_empty() => {
+                    host.proxy_command =
+                        (!value.eq_ignore_ascii_case("none")).then(|| value.to_string());
+                }
+                "remotecommand" => {
+                    host.remote_command =
+                        (!value.eq_ignore_ascii_case("none")).then(|| value.to_string());
+                }
+                "requesttty" => host.request_tty = Some(value.to_lowercase()),
+                "bindinterface" => host.bind_interface = Some(value.to_string()),
+                "addressfamily" => {
+                    let family = value.to_lowercase();
+                    host.address_family = (family != "any").then_some(family);
+                }
                 _ => {} // Ignore unknown directives
             }
         }
@@ -120,9 +536,81 @@ fn parse_ssh_config_content(
         }
     }
 
+    // `Host a b` gives the same settings to each alias
+    let mut hosts: Vec<SshHost> = hosts
+        .into_iter()
+        .flat_map(|host| {
+            if is_pattern(&host.name) {
+                return vec![host];
+            }
+            host.name
+                .split_whitespace()
+                .map(|alias| SshHost {
+                    name: alias.to_string(),
+                    ..host.clone()
+                })
+                .collect()
+        })
+        .collect();
+
+    for host in &mut hosts {
+        apply_forward_blocks(host, &blocks);
+    }
+
     Ok(hosts)
 }
 
+/// Add the forwarding directives of every block matching `host`, in file order.
+/// As in ssh, the first `ClearAllForwardings` and `CanonicalizeHostname`
+/// values obtained win.
+fn apply_forward_blocks(host: &mut SshHost, blocks: &[ForwardBlock]) {
+    let mut canonicalize = None;
+    for block in blocks {
+        let matches = block.patterns == host.name
+            || host
+                .name
+                .split_whitespace()
+                .any(|name| host_pattern_matches(&block.patterns, name));
+        if !matches {
+            continue;
+        }
+        for forward in &block.forwards {
+            if !host.local_forwards.contains(forward) {
+                host.local_forwards.push(forward.clone());
+            }
+        }
+        host.clear_all_forwardings = host.clear_all_forwardings.or(block.clear_all);
+        canonicalize = canonicalize.or(block.canonicalize);
+    }
+    host.canonicalize |= canonicalize == Some(true);
+}
+
+/// Resolve a host's final hostname with `ssh -G`, which applies
+/// CanonicalizeHostname, CanonicalDomains and CanonicalizePermittedCNAMEs.
+pub async fn resolve_hostname(alias: &str, config_path: &Path) -> anyhow::Result<String> {
+    let output = crate::ssh::command()
+        .arg("-F")
+        .arg(config_path)
+        .args(["-G", alias])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ssh -G failed for {alias}"));
+    }
+    parse_ssh_g_hostname(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| anyhow::anyhow!("No hostname in ssh -G output for {alias}"))
+}
+
+/// Extract the `hostname` line from `ssh -G` output.
+fn parse_ssh_g_hostname(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("hostname "))
+        .map(|h| h.trim().to_string())
+}
+
 /// Split a config line into (keyword, value), handling both whitespace and '=' separators.
 fn split_config_line(line: &str) -> Option<(&str, &str)> {
     // Try splitting on '=' first
@@ -146,12 +634,60 @@ fn split_config_line(line: &str) -> Option<(&str, &str)> {
     Some((keyword, value))
 }
 
-/// Check if a host pattern is wildcard-only (e.g., "*", "* !bastion").
+/// Check if a host pattern matches everything (e.g., "*", "* !bastion").
+/// Narrower patterns like `*.dev.example.com` are kept as pattern hosts.
 fn is_wildcard_only(name: &str) -> bool {
-    let parts: Vec<&str> = name.split_whitespace().collect();
-    parts
-        .iter()
-        .all(|p| p.starts_with('*') || p.starts_with('!'))
+    name.split_whitespace()
+        .all(|p| p.chars().all(|c| c == '*') || p.starts_with('!'))
+}
+
+/// Returns true if any alias on a `Host` line is a pattern rather than a
+/// concrete alias.
+pub fn is_pattern(name: &str) -> bool {
+    name.split_whitespace()
+        .any(|alias| alias.contains(['*', '?', '!']))
+}
+
+/// Match a candidate hostname against a `Host` line's patterns: at least one
+/// positive pattern must match and no `!negated` one may.
+pub fn host_pattern_matches(patterns: &str, candidate: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split([' ', '\t', ',']).filter(|p| !p.is_empty()) {
+        if let Some(negated) = pattern.strip_prefix('!') {
+            if wildcard_match(negated, candidate) {
+                return false;
+            }
+        } else if wildcard_match(pattern, candidate) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+/// Case-insensitive glob match supporting `*` and `?` anywhere.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last `*` absorb one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// Expand `~` at the start of a path to the user's home directory.
@@ -363,7 +899,128 @@ Host internal
 "#;
         let hosts = parse_ssh_config_content(config, None).unwrap();
         assert_eq!(hosts.len(), 1);
-        assert_eq!(hosts[0].proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(hosts[0].jump_chain(), "bastion");
+    }
+
+    #[test]
+    fn test_proxy_jump_chain() {
+        let config = r#"
+Host deep
+    HostName 10.1.0.9
+    ProxyJump ops@bastion.example.com:2222,inner,[fd00::1]:22
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        let chain = &hosts[0].proxy_jump;
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain[0].user.as_deref(), Some("ops"));
+        assert_eq!(chain[0].host, "bastion.example.com");
+        assert_eq!(chain[0].port, Some(2222));
+        assert_eq!(chain[1].host, "inner");
+        assert_eq!(chain[1].port, None);
+        assert_eq!(chain[2].host, "fd00::1");
+        assert_eq!(
+            hosts[0].jump_chain(),
+            "ops@bastion.example.com:2222,inner,[fd00::1]:22"
+        );
+    }
+
+    #[test]
+    fn test_proxy_jump_none_and_uri() {
+        assert!(parse_proxy_jump("none").is_empty());
+        let hops = parse_proxy_jump("ssh://me@jump:2200");
+        assert_eq!(hops[0].user.as_deref(), Some("me"));
+        assert_eq!(hops[0].port, Some(2200));
+    }
+
+    #[test]
+    fn test_certificate_file() {
+        let config = r#"
+Host fleet
+    HostName 10.0.0.7
+    CertificateFile /etc/ssh/user-cert.pub
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        assert_eq!(
+            hosts[0].certificate_file,
+            Some(PathBuf::from("/etc/ssh/user-cert.pub"))
+        );
+        assert_eq!(
+            hosts[0].connection_args(),
+            vec!["-o", "CertificateFile=/etc/ssh/user-cert.pub"]
+        );
+    }
+
+    #[test]
+    fn test_forwarding_args() {
+        let host = SshHost {
+            name: "shared".to_string(),
+            forward_agent: true,
+            forward_x11: X11Forwarding::Trusted,
+            ..Default::default()
+        };
+        assert_eq!(host.connection_args(), vec!["-A", "-Y"]);
+        assert_eq!(X11Forwarding::Trusted.next(), X11Forwarding::Off);
+    }
+
+    #[test]
+    fn test_canonicalize_from_wildcard_block() {
+        let config = r#"
+Host web1
+    User deploy
+
+Host *
+    CanonicalizeHostname yes
+    CanonicalDomains corp.example.com
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        assert!(hosts[0].canonicalize);
+        assert!(hosts[0].needs_canonicalization());
+    }
+
+    #[test]
+    fn test_canonicalize_per_block_first_value_wins() {
+        let config = r#"
+Host corp-*
+    CanonicalizeHostname yes
+
+Host corp-db
+    CanonicalizeHostname no
+
+Host home
+    CanonicalizeHostname no
+
+Host web1
+    User deploy
+
+Host *
+    CanonicalizeHostname always
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        let get = |name: &str| hosts.iter().find(|h| h.name == name).unwrap();
+        assert!(get("corp-db").canonicalize);
+        assert!(!get("home").canonicalize);
+        assert!(get("web1").canonicalize);
+    }
+
+    #[test]
+    fn test_canonical_hostname_preferred() {
+        let host = SshHost {
+            name: "web1".to_string(),
+            canonical_hostname: Some("web1.corp.example.com".to_string()),
+            canonicalize: true,
+            ..Default::default()
+        };
+        assert_eq!(host.effective_hostname(), "web1.corp.example.com");
+        assert!(!host.needs_canonicalization());
+    }
+
+    #[test]
+    fn test_parse_ssh_g_hostname() {
+        let output = "user deploy\nhostname web1.corp.example.com\nport 22\n";
+        assert_eq!(
+            parse_ssh_g_hostname(output).as_deref(),
+            Some("web1.corp.example.com")
+        );
     }
 
     #[test]
@@ -402,6 +1059,237 @@ Host internal
         assert_eq!(host.display_target(), "10.0.0.1");
     }
 
+    #[test]
+    fn test_ssh_command() {
+        let host = SshHost {
+            name: "internal".to_string(),
+            hostname: Some("10.0.0.50".to_string()),
+            user: Some("admin".to_string()),
+            port: Some(2222),
+            proxy_jump: parse_proxy_jump("bastion"),
+            ..Default::default()
+        };
+        assert_eq!(host.ssh_command(), "ssh -p 2222 -J bastion admin@10.0.0.50");
+        let forwards = [
+            ("-L", "5432:db.internal:5432".to_string()),
+            ("-R", "9000:localhost:9000".to_string()),
+        ];
+        assert_eq!(
+            host.forward_command(&forwards),
+            "ssh -N -p 2222 -J bastion -L 5432:db.internal:5432 -R 9000:localhost:9000 admin@10.0.0.50"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain"), "plain");
+        assert_eq!(shell_quote("/my keys/id"), "'/my keys/id'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_host_line_with_several_aliases() {
+        let config = r#"
+Host web1 web2
+    User deploy
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        let names: Vec<&str> = hosts.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["web1", "web2"]);
+        assert!(hosts.iter().all(|h| !h.is_pattern()));
+        assert_eq!(hosts[1].user.as_deref(), Some("deploy"));
+    }
+
+    #[test]
+    fn test_pattern_host_kept() {
+        let config = r#"
+Host *.dev.example.com
+    User dev
+    HostName %h.internal
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        assert_eq!(hosts.len(), 1);
+        assert!(hosts[0].is_pattern());
+
+        let concrete = hosts[0].instantiate("api.dev.example.com");
+        assert!(!concrete.is_pattern());
+        assert!(is_pattern("web !web-old"));
+        assert_eq!(concrete.user.as_deref(), Some("dev"));
+        assert_eq!(
+            concrete.effective_hostname(),
+            "api.dev.example.com.internal"
+        );
+    }
+
+    #[test]
+    fn test_remote_command_and_request_tty() {
+        let config = r#"
+Host tmux-box
+    HostName 10.0.0.9
+    RemoteCommand tmux new -A -s main
+    RequestTTY yes
+
+Host plain
+    HostName 10.0.0.10
+    RemoteCommand none
+    RequestTTY no
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        assert_eq!(
+            hosts[0].remote_command.as_deref(),
+            Some("tmux new -A -s main")
+        );
+        assert!(hosts[0].forces_session());
+        assert_eq!(hosts[1].remote_command, None);
+        assert!(!hosts[1].forces_session());
+    }
+
+    #[test]
+    fn test_bind_interface_and_address_family() {
+        let config = r#"
+Host vpn-box
+    HostName 10.8.0.12
+    BindInterface tun0
+    AddressFamily inet
+
+Host anywhere
+    HostName example.com
+    AddressFamily any
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        assert_eq!(
+            hosts[0].connection_args(),
+            vec![
+                "-o".to_string(),
+                "BindInterface=tun0".to_string(),
+                "-4".to_string()
+            ]
+        );
+        assert_eq!(hosts[1].address_family, None);
+        assert!(hosts[1].connection_args().is_empty());
+    }
+
+    #[test]
+    fn test_host_variant() {
+        let host = SshHost {
+            name: "web1".to_string(),
+            hostname: Some("10.0.0.7".to_string()),
+            user: Some("deploy".to_string()),
+            ..Default::default()
+        };
+        let root = host.variant(Some("root".to_string()), Some(2222));
+        assert_eq!(root.name, "root@web1:2222");
+        assert_eq!(root.display_target(), "root@10.0.0.7");
+        assert_eq!(root.effective_port(), 2222);
+        assert!(root.separate_socket);
+
+        let same_user = host.variant(None, Some(2200));
+        assert_eq!(same_user.name, "web1:2200");
+        assert_eq!(same_user.user.as_deref(), Some("deploy"));
+    }
+
+    #[test]
+    fn test_local_forward_parse() {
+        assert_eq!(
+            LocalForward::parse("8080 localhost:80"),
+            Some(LocalForward {
+                local_port: 8080,
+                remote_host: "localhost".to_string(),
+                remote_port: 80,
+            })
+        );
+        assert_eq!(
+            LocalForward::parse("127.0.0.1:5432 [fd00::5]:5432").map(|f| f.remote_host),
+            Some("fd00::5".to_string())
+        );
+        assert_eq!(LocalForward::parse("/tmp/sock /run/app.sock"), None);
+    }
+
+    #[test]
+    fn test_forwards_accumulate_from_wildcard_blocks() {
+        let config = r#"
+Host db
+    HostName 10.0.0.5
+    LocalForward 5432 localhost:5432
+
+Host *
+    LocalForward 9090 localhost:9090
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        let ports: Vec<u16> = hosts[0]
+            .config_forwards()
+            .iter()
+            .map(|f| f.local_port)
+            .collect();
+        assert_eq!(ports, vec![5432, 9090]);
+    }
+
+    #[test]
+    fn test_clear_all_forwardings_first_value_wins() {
+        let config = r#"
+Host quiet
+    HostName 10.0.0.6
+    LocalForward 8080 localhost:80
+    ClearAllForwardings yes
+
+Host loud
+    HostName 10.0.0.7
+    ClearAllForwardings no
+    LocalForward 8081 localhost:80
+
+Host *
+    ClearAllForwardings yes
+    LocalForward 9090 localhost:9090
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        assert!(hosts[0].config_forwards().is_empty());
+        assert_eq!(hosts[1].config_forwards().len(), 2);
+    }
+
+    #[test]
+    fn test_proxy_command_parse() {
+        let config = r#"
+Host office
+    HostName 10.1.0.4
+    ProxyCommand nc -X connect -x proxy.corp:3128 %h %p
+
+Host jumped
+    HostName 10.1.0.5
+    ProxyJump bastion
+    ProxyCommand ignored %h %p
+"#;
+        let hosts = parse_ssh_config_content(config, None).unwrap();
+        let args = hosts[0].connection_args();
+        assert_eq!(
+            args,
+            vec![
+                "-o".to_string(),
+                "ProxyCommand=nc -X connect -x proxy.corp:3128 %h %p".to_string()
+            ]
+        );
+        assert_eq!(hosts[1].proxy_command, None);
+        assert_eq!(hosts[1].proxy_jump.len(), 1);
+    }
+
+    #[test]
+    fn test_host_pattern_matches() {
+        assert!(host_pattern_matches(
+            "*.dev.example.com",
+            "api.dev.example.com"
+        ));
+        assert!(!host_pattern_matches(
+            "*.dev.example.com",
+            "api.prod.example.com"
+        ));
+        assert!(host_pattern_matches("web? db-*", "db-primary"));
+        assert!(host_pattern_matches("web?", "WEB1"));
+        assert!(!host_pattern_matches("web?", "web12"));
+        assert!(!host_pattern_matches(
+            "*.example.com !secret.example.com",
+            "secret.example.com"
+        ));
+    }
+
     #[test]
     fn test_wildcard_negation_skip() {
         let config = r#"
@@ -453,4 +1341,32 @@ Host server2
         assert_eq!(hosts[0].name, "server1");
         assert_eq!(hosts[1].name, "server2");
     }
+
+    #[test]
+    fn test_parse_several_configs() {
+        let dir = std::env::temp_dir().join(format!("stm-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let work = dir.join("work.conf");
+        let personal = dir.join("config");
+        std::fs::write(&work, "Host bastion\n    HostName 10.0.0.1\n").unwrap();
+        std::fs::write(
+            &personal,
+            "Host nas\n    HostName 192.168.1.5\nHost bastion\n    HostName 1.2.3.4\n",
+        )
+        .unwrap();
+
+        let missing = dir.join("missing");
+        let (hosts, errors) = parse_ssh_configs(&[work.clone(), personal.clone(), missing]);
+        let names: Vec<&str> = hosts.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["bastion", "nas"]);
+        // The first file defining an alias wins
+        assert_eq!(hosts[0].hostname.as_deref(), Some("10.0.0.1"));
+        assert_eq!(hosts[0].source.as_deref(), Some(work.as_path()));
+        assert_eq!(hosts[1].source.as_deref(), Some(personal.as_path()));
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(source_badge(&work), "work");
+        assert_eq!(source_badge(Path::new("/home/me/.ssh/config")), "ssh");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,23 +1,70 @@
 use std::path::PathBuf;
-use tokio::process::{Child, Command};
+use std::time::Duration;
+use tokio::process::Child;
 
 use crate::ssh::config::SshHost;
 
+/// How long a master gets to exit on `-O exit` before it is killed.
+pub const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How a master went away on disconnect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disconnect {
+    /// It exited on `-O exit`, or was borrowed and left running.
+    Exited,
+    /// It didn't exit in time and was killed.
+    Killed,
+    /// It didn't exit in time and there was no known process to kill.
+    Unresponsive,
+}
+
 pub struct ConnectionManager {
     child: Option<Child>,
     socket_path: PathBuf,
     host: SshHost,
+    /// Process id of the master, looked up once it is up; used to kill a
+    /// master that no longer answers on its socket.
+    master_pid: Option<u32>,
+    /// False for a master another process started and stm only attached to:
+    /// it is never told to exit, killed or cleaned up after.
+    owned: bool,
 }
 
 impl ConnectionManager {
     pub fn new(host: SshHost, socket_dir: &std::path::Path) -> Self {
-        let socket_name = format!("{}-{}", host.effective_hostname(), host.effective_port());
+        let mut socket_name = format!("{}-{}", host.effective_hostname(), host.effective_port());
+        if host.separate_socket {
+            // Aliases sharing hostname:port each get their own master
+            let alias: String = host
+                .name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            socket_name = format!("{alias}@{socket_name}");
+        }
         let socket_path = socket_dir.join(socket_name);
 
         Self {
             child: None,
             socket_path,
             host,
+            master_pid: None,
+            owned: true,
+        }
+    }
+
+    /// A manager for the master already listening on this host's socket,
+    /// started by someone else; disconnecting only detaches from it.
+    pub fn borrowed(host: SshHost, socket_dir: &std::path::Path) -> Self {
+        Self {
+            owned: false,
+            ..Self::new(host, socket_dir)
         }
     }
 
@@ -29,6 +76,63 @@ impl ConnectionManager {
         &self.socket_path
     }
 
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    pub fn set_master_pid(&mut self, pid: u32) {
+        self.master_pid = Some(pid);
+    }
+
+    /// File next to the socket recording which alias started the master.
+    fn owner_path(&self) -> PathBuf {
+        let mut path = self.socket_path.clone().into_os_string();
+        path.push(".owner");
+        PathBuf::from(path)
+    }
+
+    /// File next to the socket holding the note attached to this master.
+    fn note_path(&self) -> PathBuf {
+        let mut path = self.socket_path.clone().into_os_string();
+        path.push(".note");
+        PathBuf::from(path)
+    }
+
+    /// Note attached to this master (why it is up), if any.
+    pub fn read_note(&self) -> Option<String> {
+        std::fs::read_to_string(self.note_path())
+            .ok()
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+    }
+
+    /// Attach a note to this master, or remove it with `None`. It lives as
+    /// long as the master, so `stm status` and a later stm see it too.
+    pub fn write_note(&self, note: Option<&str>) -> std::io::Result<()> {
+        match note {
+            Some(note) => std::fs::write(self.note_path(), note),
+            None => match std::fs::remove_file(self.note_path()) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// If a live master already listens on this socket, returns the alias
+    /// that started it (`"unknown"` for masters without an owner file).
+    pub async fn live_owner(&self) -> Option<String> {
+        if !self.socket_path.exists() || !self.check().await.unwrap_or(false) {
+            return None;
+        }
+        let owner = tokio::fs::read_to_string(self.owner_path()).await.ok();
+        Some(
+            owner
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .unwrap_or_else(|| "unknown".to_string()),
+        )
+    }
+
     /// Build the SSH target string (e.g., "user@hostname" or just "hostname").
     fn ssh_target(&self) -> String {
         let hostname = self.host.effective_hostname();
@@ -44,11 +148,13 @@ impl ConnectionManager {
         if let Some(parent) = self.socket_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
+        // Left by a master that died without stm cleaning up after it
+        self.remove_state_files().await;
 
         let target = self.ssh_target();
         let socket = self.socket_path.to_string_lossy().to_string();
 
-        let mut cmd = Command::new("ssh");
+        let mut cmd = crate::ssh::command();
         cmd.args([
             "-M", // ControlMaster mode
             "-S",
@@ -64,21 +170,28 @@ impl ConnectionManager {
             "StrictHostKeyChecking=accept-new",
             "-o",
             "BatchMode=yes", // No interactive prompts
+            "-o",
+            "ClearAllForwardings=yes", // Config forwards are listed as tunnels instead
         ]);
 
-        // Add port if non-default
-        if let Some(port) = self.host.port {
-            cmd.args(["-p", &port.to_string()]);
+        // Port, identity file and proxy jump from the host entry
+        cmd.args(self.host.connection_args());
+
+        // Keep trust decisions made by stm out of the personal known_hosts
+        if let Some(ref file) = self.host.known_hosts_file {
+            let path = file.to_string_lossy();
+            let value = if path.contains(char::is_whitespace) {
+                format!("UserKnownHostsFile=\"{path}\"")
+            } else {
+                format!("UserKnownHostsFile={path}")
+            };
+            cmd.args(["-o", &value]);
         }
 
-        // Add identity file if specified
-        if let Some(ref identity) = self.host.identity_file {
-            cmd.args(["-i", &identity.to_string_lossy()]);
-        }
-
-        // Add proxy jump if specified
-        if let Some(ref proxy) = self.host.proxy_jump {
-            cmd.args(["-J", proxy]);
+        // A configured RemoteCommand/RequestTTY makes a `-N` master fail; the
+        // master only carries forwards, so override them for this process.
+        if self.host.forces_session() {
+            cmd.args(["-o", "RemoteCommand=none", "-o", "RequestTTY=no"]);
         }
 
         cmd.arg(&target);
@@ -98,7 +211,10 @@ impl ConnectionManager {
 
         // Check if connection was established
         match self.check().await {
-            Ok(true) => Ok(()),
+            Ok(true) => {
+                let _ = tokio::fs::write(self.owner_path(), &self.host.name).await;
+                Ok(())
+            }
             Ok(false) => {
                 // Try to get stderr output for error details
                 let err_msg = self.collect_stderr().await;
@@ -117,36 +233,59 @@ impl ConnectionManager {
 
     /// Check if the ControlMaster connection is alive.
     pub async fn check(&self) -> anyhow::Result<bool> {
-        let socket = self.socket_path.to_string_lossy().to_string();
-        let target = self.ssh_target();
-
-        let output = Command::new("ssh")
-            .args(["-S", &socket, "-O", "check", &target])
-            .stdin(std::process::Stdio::null())
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .output()
-            .await?;
+        crate::ssh::health::check_master(&self.socket_path, &self.ssh_target()).await
+    }
 
-        Ok(output.status.success())
+    /// Returns the health-check probe for this connection's master.
+    pub fn probe(&self) -> crate::ssh::health::Probe {
+        crate::ssh::health::Probe {
+            socket_path: self.socket_path.clone(),
+            target: self.ssh_target(),
+        }
     }
 
-    /// Disconnect the ControlMaster connection.
-    pub async fn disconnect(&mut self) -> anyhow::Result<()> {
+    /// Disconnect the ControlMaster connection. A master that doesn't answer
+    /// `-O exit` within `DISCONNECT_TIMEOUT` is killed if its PID is known.
+    /// A borrowed master is left running.
+    pub async fn disconnect(&mut self) -> Disconnect {
+        if !self.owned {
+            return Disconnect::Exited;
+        }
         let socket = self.socket_path.to_string_lossy().to_string();
         let target = self.ssh_target();
 
         // Send exit signal to ControlMaster
-        let _ = Command::new("ssh")
+        let exit = crate::ssh::command()
             .args(["-S", &socket, "-O", "exit", &target])
             .stdin(std::process::Stdio::null())
             .stdout(std::process::Stdio::null())
             .stderr(std::process::Stdio::null())
-            .output()
-            .await;
+            .kill_on_drop(true)
+            .status();
+        let outcome = match (
+            tokio::time::timeout(DISCONNECT_TIMEOUT, exit).await,
+            self.master_pid,
+        ) {
+            (Ok(_), _) => Disconnect::Exited,
+            (Err(_), None) => Disconnect::Unresponsive,
+            (Err(_), Some(pid)) => {
+                let killed = tokio::process::Command::new("kill")
+                    .args(["-KILL", &pid.to_string()])
+                    .stdin(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .status()
+                    .await
+                    .is_ok_and(|s| s.success());
+                if killed {
+                    Disconnect::Killed
+                } else {
+                    Disconnect::Unresponsive
+                }
+            }
+        };
 
         self.cleanup().await;
-        Ok(())
+        outcome
     }
 
     async fn cleanup(&mut self) {
@@ -158,6 +297,20 @@ impl ConnectionManager {
 
         // Remove socket file
         let _ = tokio::fs::remove_file(&self.socket_path).await;
+        self.remove_state_files().await;
+    }
+
+    /// Remove the owner and note files of a master that is gone, e.g. after
+    /// the connection was lost. A master that still answers keeps them.
+    pub async fn forget(&self) {
+        if self.owned && !self.check().await.unwrap_or(false) {
+            self.remove_state_files().await;
+        }
+    }
+
+    async fn remove_state_files(&self) {
+        let _ = tokio::fs::remove_file(self.owner_path()).await;
+        let _ = tokio::fs::remove_file(self.note_path()).await;
     }
 
     async fn collect_stderr(&mut self) -> Option<String> {
@@ -174,10 +327,186 @@ impl ConnectionManager {
     }
 }
 
+/// The alias recorded as having started the master on `socket_path`.
+pub fn socket_owner(socket_path: &std::path::Path) -> Option<String> {
+    let mut path = socket_path.as_os_str().to_owned();
+    path.push(".owner");
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|o| o.trim().to_string())
+        .filter(|o| !o.is_empty())
+}
+
+/// Where master sockets are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketDir {
+    pub path: PathBuf,
+    /// Why the configured directory is not used, if it was replaced, or why
+    /// it couldn't be.
+    pub fallback_reason: Option<String>,
+}
+
+/// Use `configured` for master sockets unless it can't hold Unix sockets
+/// (some network homes and container mounts); then fall back to
+/// `$XDG_RUNTIME_DIR/stm` or `/tmp/stm-$USER`, if it is private to the user.
+/// Deterministic, so the TUI and the CLI subcommands agree on where sockets are.
+pub fn resolve_socket_dir(configured: &std::path::Path) -> SocketDir {
+    let Err(e) = probe_socket_dir(configured, false) else {
+        return SocketDir {
+            path: configured.to_path_buf(),
+            fallback_reason: None,
+        };
+    };
+    let fallback = match std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(runtime) => PathBuf::from(runtime).join("stm"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
+            std::env::temp_dir().join(format!("stm-{user}"))
+        }
+    };
+    if let Err(fallback_error) = probe_socket_dir(&fallback, true) {
+        // Nothing better; let ssh report the error on connect
+        return SocketDir {
+            path: configured.to_path_buf(),
+            fallback_reason: Some(format!(
+                "{} can't hold Unix sockets ({e}), and {} can't be used instead ({fallback_error})",
+                configured.display(),
+                fallback.display()
+            )),
+        };
+    }
+    SocketDir {
+        fallback_reason: Some(format!(
+            "{} can't hold Unix sockets ({e}); using {}",
+            configured.display(),
+            fallback.display()
+        )),
+        path: fallback,
+    }
+}
+
+/// Create `dir` (private to the user) and bind a throwaway Unix socket in it.
+/// With `private`, for directories another user could have created first
+/// (under /tmp), an existing `dir` must also be ours with mode 0700, or
+/// whoever owns it could take over the master sockets.
+#[cfg(unix)]
+fn probe_socket_dir(dir: &std::path::Path, private: bool) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let not_private = || {
+        std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "not a directory of yours with mode 0700",
+        )
+    };
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if private && (!metadata.is_dir() || metadata.mode() & 0o777 != 0o700) {
+        return Err(not_private());
+    }
+    let probe = dir.join(format!(".probe-{}", std::process::id()));
+    let _ = std::fs::remove_file(&probe);
+    let result = std::os::unix::net::UnixListener::bind(&probe).map(drop);
+    // The probe is ours, so it tells whose the directory is
+    let probe_owner = std::fs::symlink_metadata(&probe).map(|m| m.uid());
+    let _ = std::fs::remove_file(&probe);
+    result?;
+    if private && probe_owner? != metadata.uid() {
+        return Err(not_private());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn probe_socket_dir(dir: &std::path::Path, _private: bool) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_socket_dir() {
+        let dir = std::env::temp_dir().join(format!("stm-test-sockets-{}", std::process::id()));
+        let resolved = resolve_socket_dir(&dir);
+        assert_eq!(resolved.path, dir);
+        assert_eq!(resolved.fallback_reason, None);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // A regular file where the directory should be can't hold sockets
+        let file = std::env::temp_dir().join(format!("stm-test-file-{}", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let resolved = resolve_socket_dir(&file);
+        assert_ne!(resolved.path, file);
+        assert!(resolved.fallback_reason.is_some());
+        let _ = std::fs::remove_file(&file);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_socket_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("stm-test-private-{}", std::process::id()));
+        assert!(probe_socket_dir(&dir, true).is_ok());
+        // Pre-created by someone else, or opened up: refused
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(probe_socket_dir(&dir, true).is_err());
+        assert!(probe_socket_dir(&dir, false).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let link = std::env::temp_dir().join(format!("stm-test-link-{}", std::process::id()));
+        std::os::unix::fs::symlink(std::env::temp_dir(), &link).unwrap();
+        assert!(probe_socket_dir(&link, true).is_err());
+        let _ = std::fs::remove_file(&link);
+    }
+
+    #[tokio::test]
+    async fn test_borrowed_master_survives_disconnect() {
+        let dir = std::env::temp_dir().join(format!("stm-test-borrowed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let host = SshHost {
+            name: "other-alias".to_string(),
+            hostname: Some("10.0.0.1".to_string()),
+            ..Default::default()
+        };
+        let mut mgr = ConnectionManager::borrowed(host, &dir);
+        std::fs::write(mgr.socket_path(), "").unwrap();
+        std::fs::write(mgr.owner_path(), "first-alias").unwrap();
+        mgr.set_master_pid(u32::MAX);
+
+        assert_eq!(mgr.disconnect().await, Disconnect::Exited);
+        assert!(mgr.socket_path().exists());
+        assert_eq!(
+            socket_owner(mgr.socket_path()).as_deref(),
+            Some("first-alias")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_forget_lost_master() {
+        let dir = std::env::temp_dir().join(format!("stm-test-lost-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let host = SshHost {
+            name: "db".to_string(),
+            hostname: Some("10.0.0.1".to_string()),
+            ..Default::default()
+        };
+        let mgr = ConnectionManager::new(host, &dir);
+        std::fs::write(mgr.owner_path(), "db").unwrap();
+        mgr.write_note(Some("migrating orders")).unwrap();
+
+        mgr.forget().await;
+        assert!(!mgr.owner_path().exists());
+        assert_eq!(mgr.read_note(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_ssh_target_with_user() {
         let host = SshHost {
@@ -233,4 +562,24 @@ mod tests {
             &PathBuf::from("/tmp/sockets/10.0.0.1-22")
         );
     }
+
+    #[test]
+    fn test_socket_path_separate_per_alias() {
+        let host = SshHost {
+            name: "client a/db".to_string(),
+            hostname: Some("10.0.0.1".to_string()),
+            separate_socket: true,
+            ..Default::default()
+        };
+        let dir = PathBuf::from("/tmp/sockets");
+        let mgr = ConnectionManager::new(host, &dir);
+        assert_eq!(
+            mgr.socket_path(),
+            &PathBuf::from("/tmp/sockets/client_a_db@10.0.0.1-22")
+        );
+        assert_eq!(
+            mgr.owner_path(),
+            PathBuf::from("/tmp/sockets/client_a_db@10.0.0.1-22.owner")
+        );
+    }
 }
@@ -0,0 +1,161 @@
+use std::path::Path;
+use std::time::Duration;
+
+/// How long listing the remote ports may take before it is given up.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A TCP port listening on the remote host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteListener {
+    pub port: u16,
+    /// Address it listens on as reported, e.g. `0.0.0.0`, `[::1]`, `*`.
+    pub address: String,
+    /// Program name, when the remote user may see it.
+    pub process: Option<String>,
+}
+
+impl RemoteListener {
+    /// Host to forward to from the remote side: `localhost` for wildcard and
+    /// loopback listeners, otherwise the address itself.
+    pub fn forward_host(&self) -> String {
+        let address = self.address.trim_start_matches('[').trim_end_matches(']');
+        let address = address.split('%').next().unwrap_or(address);
+        match address {
+            "*" | "0.0.0.0" | "::" | "127.0.0.1" | "::1" => "localhost".to_string(),
+            address => address.to_string(),
+        }
+    }
+}
+
+/// List the remote host's listening TCP ports over the master with `ss`,
+/// or `netstat` where ss is missing.
+pub async fn remote_listeners(
+    socket_path: &Path,
+    ssh_target: &str,
+) -> anyhow::Result<Vec<RemoteListener>> {
+    let socket = socket_path.to_string_lossy().to_string();
+    // With the master gone ssh would connect on its own, possibly prompting
+    // over the TUI; batch mode makes that fail instead
+    let command = crate::ssh::command()
+        .args([
+            "-S",
+            &socket,
+            "-o",
+            "ControlMaster=no",
+            "-o",
+            "BatchMode=yes",
+            ssh_target,
+            "ss -tlnp 2>/dev/null || netstat -tlnp 2>/dev/null",
+        ])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(DISCOVERY_TIMEOUT, command)
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Listing remote ports timed out after {}s",
+                DISCOVERY_TIMEOUT.as_secs()
+            )
+        })??;
+
+    let listeners = parse_listeners(&String::from_utf8_lossy(&output.stdout));
+    if listeners.is_empty() && !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!(
+            "Couldn't list remote ports (needs ss or netstat there): {}",
+            stderr.trim()
+        ));
+    }
+    Ok(listeners)
+}
+
+/// Listeners in `ss -tlnp` or `netstat -tlnp` output, one per port, by port.
+fn parse_listeners(output: &str) -> Vec<RemoteListener> {
+    let mut listeners: Vec<RemoteListener> = output.lines().filter_map(parse_row).collect();
+    // IPv4 and IPv6 listeners on one port are the same service
+    listeners.sort_by_key(|l| l.port);
+    listeners.dedup_by(|later, first| {
+        if later.port != first.port {
+            return false;
+        }
+        if first.process.is_none() {
+            first.process = later.process.take();
+        }
+        true
+    });
+    listeners
+}
+
+/// One `ss` row (`LISTEN 0 128 0.0.0.0:22 0.0.0.0:* users:(("sshd",...))`)
+/// or `netstat` row (`tcp 0 0 0.0.0.0:22 0.0.0.0:* LISTEN 917/sshd`).
+fn parse_row(row: &str) -> Option<RemoteListener> {
+    let fields: Vec<&str> = row.split_whitespace().collect();
+    let process = match *fields.first()? {
+        "LISTEN" => fields.get(5).and_then(|users| {
+            let name = users.split_once("((\"")?.1;
+            name.split('"').next().map(str::to_string)
+        }),
+        proto if proto.starts_with("tcp") => {
+            if fields.get(5) != Some(&"LISTEN") {
+                return None;
+            }
+            fields
+                .get(6)
+                .and_then(|owner| owner.split_once('/'))
+                .map(|(_, name)| name.to_string())
+        }
+        _ => return None,
+    };
+    let (address, port) = fields.get(3)?.rsplit_once(':')?;
+    Some(RemoteListener {
+        port: port.parse().ok()?,
+        address: address.to_string(),
+        process,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ss() {
+        let output = "\
+State  Recv-Q Send-Q Local Address:Port  Peer Address:Port Process
+LISTEN 0      128    0.0.0.0:22          0.0.0.0:*
+LISTEN 0      244    127.0.0.1:5432      0.0.0.0:*         users:((\"postgres\",pid=811,fd=6))
+LISTEN 0      128    [::]:22             [::]:*
+LISTEN 0      4096   127.0.0.53%lo:53    0.0.0.0:*
+LISTEN 0      511    *:8080              *:*               users:((\"node\",pid=1200,fd=19))
+";
+        let listeners = parse_listeners(output);
+        let ports: Vec<u16> = listeners.iter().map(|l| l.port).collect();
+        assert_eq!(ports, [22, 53, 5432, 8080]);
+        assert_eq!(listeners[2].process.as_deref(), Some("postgres"));
+        assert_eq!(listeners[0].process, None);
+        assert_eq!(listeners[1].forward_host(), "127.0.0.53");
+        assert_eq!(listeners[2].forward_host(), "localhost");
+        assert_eq!(listeners[3].forward_host(), "localhost");
+    }
+
+    #[test]
+    fn test_parse_netstat() {
+        let output = "\
+Active Internet connections (only servers)
+Proto Recv-Q Send-Q Local Address           Foreign Address         State       PID/Program name
+tcp        0      0 10.0.0.5:6379           0.0.0.0:*               LISTEN      -
+tcp6       0      0 :::3000                 :::*                    LISTEN      4242/grafana
+tcp6       0      0 ::1:6379                :::*                    LISTEN      933/redis-server
+";
+        let listeners = parse_listeners(output);
+        assert_eq!(listeners.len(), 2);
+        assert_eq!(listeners[0].port, 3000);
+        assert_eq!(listeners[0].process.as_deref(), Some("grafana"));
+        assert_eq!(listeners[0].forward_host(), "localhost");
+        // The first row per port wins, with the process filled in from another
+        assert_eq!(listeners[1].forward_host(), "10.0.0.5");
+        assert_eq!(listeners[1].process.as_deref(), Some("redis-server"));
+    }
+}
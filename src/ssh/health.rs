@@ -0,0 +1,659 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+use crate::ssh::tunnel::{Tunnel, TunnelDirection};
+
+/// Maximum number of `-O check` processes running at once.
+pub const MAX_CONCURRENT_CHECKS: usize = 4;
+/// Each check is delayed by a random amount up to this, to spread process spawns.
+pub const CHECK_JITTER: Duration = Duration::from_millis(500);
+
+/// A master to probe: its control socket and ssh target.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub socket_path: PathBuf,
+    pub target: String,
+}
+
+/// Ask a ControlMaster whether it is alive (`ssh -S <socket> -O check`).
+pub async fn check_master(socket_path: &Path, target: &str) -> anyhow::Result<bool> {
+    let socket = socket_path.to_string_lossy().to_string();
+    let output = crate::ssh::command()
+        .args(["-S", &socket, "-O", "check", target])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await?;
+    Ok(output.status.success())
+}
+
+/// Process id of the master on `socket_path`, from `-O check`'s
+/// `Master running (pid=1234)`.
+pub async fn master_pid(socket_path: &Path, target: &str) -> Option<u32> {
+    let socket = socket_path.to_string_lossy().to_string();
+    let output = crate::ssh::command()
+        .args(["-S", &socket, "-O", "check", target])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .await
+        .ok()?;
+    parse_master_pid(&String::from_utf8_lossy(&output.stderr))
+}
+
+fn parse_master_pid(stderr: &str) -> Option<u32> {
+    let rest = &stderr[stderr.find("pid=")? + 4..];
+    rest[..rest.find(')')?].parse().ok()
+}
+
+/// Check all masters through one scheduler, returning results in probe order.
+pub async fn check_all(probes: Vec<Probe>) -> Vec<(Probe, anyhow::Result<bool>)> {
+    run_bounded(
+        probes,
+        MAX_CONCURRENT_CHECKS,
+        CHECK_JITTER,
+        |probe| async move {
+            let result = check_master(&probe.socket_path, &probe.target).await;
+            (probe, result)
+        },
+    )
+    .await
+}
+
+/// How long a reachability probe waits for DNS plus the TCP handshake.
+pub const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Resolve `hostname` and try a TCP connection to `port`. Returns whether the
+/// connection succeeded, the first resolved address, if any, and how long the
+/// TCP handshake took when it succeeded.
+pub async fn probe_reachable(
+    hostname: &str,
+    port: u16,
+) -> (bool, Option<String>, Option<Duration>) {
+    let lookup = tokio::time::timeout(
+        REACHABILITY_TIMEOUT,
+        tokio::net::lookup_host((hostname, port)),
+    )
+    .await;
+    let Some(addr) = lookup.ok().and_then(|r| r.ok()).and_then(|mut a| a.next()) else {
+        return (false, None, None);
+    };
+    let started = std::time::Instant::now();
+    let connected =
+        tokio::time::timeout(REACHABILITY_TIMEOUT, tokio::net::TcpStream::connect(addr))
+            .await
+            .is_ok_and(|r| r.is_ok());
+    let rtt = connected.then(|| started.elapsed());
+    (connected, Some(addr.ip().to_string()), rtt)
+}
+
+/// How long a tunnel liveness probe waits to connect.
+pub const TUNNEL_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// A -L connection ssh closes within this long means the far end refused it.
+const TUNNEL_PROBE_GRACE: Duration = Duration::from_millis(500);
+
+/// Check that an enabled tunnel carries connections. A -L tunnel is probed
+/// through its listening port: ssh accepts every connection and closes it
+/// right away if the destination refuses, so an immediate EOF counts as dead.
+/// A -R tunnel's destination is reached from this machine, so it is probed
+/// directly.
+pub async fn probe_tunnel(tunnel: &Tunnel) -> bool {
+    use tokio::io::AsyncReadExt;
+
+    if tunnel.direction == TunnelDirection::Remote {
+        return if tunnel.targets_socket() {
+            connect_unix(&tunnel.remote_host).await.is_some()
+        } else {
+            connect_tcp(&tunnel.remote_host, tunnel.local_port)
+                .await
+                .is_some()
+        };
+    }
+
+    let mut buf = [0u8; 1];
+    let read = match tunnel.local_socket {
+        Some(ref path) => match connect_unix(path).await {
+            Some(mut stream) => {
+                tokio::time::timeout(TUNNEL_PROBE_GRACE, stream.read(&mut buf)).await
+            }
+            None => return false,
+        },
+        None => {
+            let Some((host, port)) = tcp_endpoint(tunnel) else {
+                return false;
+            };
+            match connect_tcp(host, port).await {
+                Some(mut stream) => {
+                    tokio::time::timeout(TUNNEL_PROBE_GRACE, stream.read(&mut buf)).await
+                }
+                None => return false,
+            }
+        }
+    };
+    // Still open after the grace period, or a banner arrived: alive
+    !matches!(read, Ok(Ok(0)) | Ok(Err(_)))
+}
+
+/// Where this machine reaches a TCP tunnel's service: the listening port of
+/// a -L tunnel, the local destination of a -R one. `None` for socket forwards.
+pub fn tcp_endpoint(tunnel: &Tunnel) -> Option<(&str, u16)> {
+    match tunnel.direction {
+        TunnelDirection::Remote if tunnel.targets_socket() => None,
+        TunnelDirection::Remote => Some((&tunnel.remote_host, tunnel.local_port)),
+        TunnelDirection::Local if tunnel.local_socket.is_some() => None,
+        TunnelDirection::Local => {
+            let host = match tunnel.bind_address.as_deref() {
+                None | Some("localhost") | Some("*") | Some("0.0.0.0") => "127.0.0.1",
+                Some("::") => "::1",
+                Some(addr) => addr,
+            };
+            Some((host, tunnel.local_port))
+        }
+    }
+}
+
+/// How long `test_tunnel` waits for an HTTP answer.
+const TUNNEL_TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Outcome of an on-demand tunnel test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TunnelTest {
+    /// Connected in `connect_ms`; `http` is the status (`200 OK`) and how
+    /// long it took when the service answered a HEAD request.
+    Passed {
+        connect_ms: u64,
+        http: Option<(String, u64)>,
+    },
+    Failed(String),
+}
+
+impl TunnelTest {
+    /// Short form for the tunnel list.
+    pub fn badge(&self) -> String {
+        match self {
+            TunnelTest::Passed {
+                http: Some((status, ms)),
+                ..
+            } => format!("✓ {status} {ms}ms"),
+            TunnelTest::Passed { connect_ms, .. } => format!("✓ {connect_ms}ms"),
+            TunnelTest::Failed(error) => format!("✗ {error}"),
+        }
+    }
+}
+
+impl std::fmt::Display for TunnelTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TunnelTest::Passed { connect_ms, http } => {
+                write!(f, "connected in {connect_ms}ms")?;
+                match http {
+                    Some((status, ms)) => write!(f, ", HTTP {status} in {ms}ms"),
+                    None => Ok(()),
+                }
+            }
+            TunnelTest::Failed(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+/// Check an enabled tunnel on demand: connect, wait out the grace period in
+/// which ssh drops connections the destination refuses, then send a `HEAD /`
+/// unless the service greeted first or the tunnel is https.
+pub async fn test_tunnel(tunnel: &Tunnel) -> TunnelTest {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let Some((host, port)) = tcp_endpoint(tunnel) else {
+        return TunnelTest::Failed("only TCP ports can be tested".to_string());
+    };
+    let started = std::time::Instant::now();
+    let Some(mut stream) = connect_tcp(host, port).await else {
+        return TunnelTest::Failed(format!("can't connect to {host}:{port}"));
+    };
+    let connect_ms = started.elapsed().as_millis() as u64;
+
+    let mut buf = [0u8; 512];
+    match tokio::time::timeout(TUNNEL_PROBE_GRACE, stream.read(&mut buf)).await {
+        Ok(Ok(0)) | Ok(Err(_)) => {
+            return TunnelTest::Failed("closed at once: the destination refused".to_string())
+        }
+        // A greeting: not HTTP
+        Ok(Ok(_)) => {
+            return TunnelTest::Passed {
+                connect_ms,
+                http: None,
+            }
+        }
+        Err(_) => {}
+    }
+    if tunnel.https {
+        return TunnelTest::Passed {
+            connect_ms,
+            http: None,
+        };
+    }
+
+    let sent = std::time::Instant::now();
+    let request = "HEAD / HTTP/1.0\r\nHost: localhost\r\nUser-Agent: stm\r\n\r\n";
+    if stream.write_all(request.as_bytes()).await.is_err() {
+        return TunnelTest::Passed {
+            connect_ms,
+            http: None,
+        };
+    }
+    let http = match tokio::time::timeout(TUNNEL_TEST_TIMEOUT, stream.read(&mut buf)).await {
+        Ok(Ok(n)) => {
+            http_status(&buf[..n]).map(|status| (status, sent.elapsed().as_millis() as u64))
+        }
+        _ => None,
+    };
+    TunnelTest::Passed { connect_ms, http }
+}
+
+/// `200 OK` from an `HTTP/1.1 200 OK` status line.
+fn http_status(reply: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(reply);
+    let line = text.lines().next()?.strip_prefix("HTTP/")?;
+    let (_, status) = line.split_once(' ')?;
+    Some(status.trim().to_string())
+}
+
+/// Time for `ssh -W` through the master to start, open a connection to a -L
+/// tunnel's destination and close it again: the path every forwarded
+/// connection takes, bastion hops included. Not a round trip, as spawning
+/// ssh is counted too. `None` if the destination can't be reached in time
+/// or the tunnel isn't a TCP -L forward.
+pub async fn tunnel_open_time(
+    socket_path: &Path,
+    target: &str,
+    tunnel: &Tunnel,
+) -> Option<Duration> {
+    if tunnel.direction != TunnelDirection::Local || tunnel.targets_socket() {
+        return None;
+    }
+    let socket = socket_path.to_string_lossy().to_string();
+    let destination = format!(
+        "{}:{}",
+        crate::ssh::tunnel::bracket_ipv6(&tunnel.remote_host),
+        tunnel.remote_port
+    );
+    let started = std::time::Instant::now();
+    let status = tokio::time::timeout(
+        TUNNEL_PROBE_TIMEOUT,
+        crate::ssh::command()
+            .args(["-S", &socket, "-W", &destination, target])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .status(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    status.success().then(|| started.elapsed())
+}
+
+/// Connect to `host:port`, giving up after `TUNNEL_PROBE_TIMEOUT`.
+pub async fn connect_tcp(host: &str, port: u16) -> Option<tokio::net::TcpStream> {
+    tokio::time::timeout(
+        TUNNEL_PROBE_TIMEOUT,
+        tokio::net::TcpStream::connect((host, port)),
+    )
+    .await
+    .ok()?
+    .ok()
+}
+
+#[cfg(unix)]
+async fn connect_unix(path: &str) -> Option<tokio::net::UnixStream> {
+    tokio::time::timeout(TUNNEL_PROBE_TIMEOUT, tokio::net::UnixStream::connect(path))
+        .await
+        .ok()?
+        .ok()
+}
+
+#[cfg(not(unix))]
+async fn connect_unix(_path: &str) -> Option<tokio::io::Empty> {
+    None
+}
+
+/// sshd's default `MaxSessions`: sessions allowed per network connection.
+pub const DEFAULT_MAX_SESSIONS: usize = 10;
+
+/// What stm has counted on a connected master, against the session limit
+/// configured for the host. The master itself is never asked: OpenSSH's
+/// control commands can't list channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MuxStats {
+    /// Other ssh sessions sharing the master.
+    pub sessions: usize,
+    /// Client connections open through its forwards, a channel each.
+    pub forwarded: usize,
+    /// Forwards set up on it.
+    pub forwards: usize,
+    /// `max_sessions` for the host, or sshd's default.
+    pub max_sessions: usize,
+}
+
+impl MuxStats {
+    /// Within a fifth of `max_sessions` of it, past which sshd refuses new
+    /// sessions with "administratively prohibited". With a limit of two or
+    /// less, only once it is reached; a limit of 0 allows forwarding only.
+    pub fn near_limit(&self) -> bool {
+        match self.max_sessions {
+            0 => false,
+            max @ 1..=2 => self.sessions >= max,
+            max => self.sessions + (max / 5).max(1) >= max,
+        }
+    }
+}
+
+impl std::fmt::Display for MuxStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} shared session(s) (limit {}), {} forwarded connection(s) over {} forward(s), as counted by stm",
+            self.sessions, self.max_sessions, self.forwarded, self.forwards
+        )?;
+        if self.near_limit() {
+            write!(f, " · near the limit, new sessions may be refused")?;
+        }
+        Ok(())
+    }
+}
+
+/// Count ssh client sessions multiplexed over a master's control socket,
+/// not counting the master's listening socket. `None` if it can't be told.
+pub async fn count_mux_clients(socket_path: &Path) -> Option<usize> {
+    let path = socket_path.to_string_lossy();
+    if cfg!(target_os = "linux") {
+        // Connections accepted on the socket are listed under its path
+        let table = tokio::fs::read_to_string("/proc/net/unix").await.ok()?;
+        return Some(count_socket_entries(&table, &path).saturating_sub(1));
+    }
+    let output = Command::new("lsof")
+        .args(["-U", "-F", "n"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let entries = listing
+        .lines()
+        .filter(|line| line.strip_prefix('n') == Some(path.as_ref()))
+        .count();
+    Some(entries.saturating_sub(1))
+}
+
+/// Count established TCP connections accepted on each of the local `ports`,
+/// i.e. clients using a -L tunnel. `None` if it can't be told.
+pub async fn count_port_clients(ports: &[u16]) -> Option<Vec<usize>> {
+    let mut local_ports = Vec::new();
+    if cfg!(target_os = "linux") {
+        for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+            if let Ok(table) = tokio::fs::read_to_string(table).await {
+                local_ports.extend(established_local_ports(&table));
+            }
+        }
+    } else {
+        let output = Command::new("lsof")
+            .args(["-nP", "-iTCP", "-sTCP:ESTABLISHED", "-F", "n"])
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .await
+            .ok()?;
+        let listing = String::from_utf8_lossy(&output.stdout);
+        local_ports.extend(listing.lines().filter_map(lsof_local_port));
+    }
+    Some(
+        ports
+            .iter()
+            .map(|port| local_ports.iter().filter(|p| *p == port).count())
+            .collect(),
+    )
+}
+
+/// Local ports of the ESTABLISHED rows in `/proc/net/tcp` or `tcp6`.
+fn established_local_ports(table: &str) -> impl Iterator<Item = u16> + '_ {
+    table.lines().skip(1).filter_map(|row| {
+        let mut fields = row.split_whitespace();
+        let local = fields.nth(1)?;
+        let state = fields.nth(1)?;
+        if state != "01" {
+            return None;
+        }
+        u16::from_str_radix(local.rsplit(':').next()?, 16).ok()
+    })
+}
+
+/// Local port of an lsof `n127.0.0.1:5432->127.0.0.1:53211` line.
+fn lsof_local_port(line: &str) -> Option<u16> {
+    let (local, _) = line.strip_prefix('n')?.split_once("->")?;
+    local.rsplit(':').next()?.parse().ok()
+}
+
+/// Count `/proc/net/unix` rows bound to `path`.
+fn count_socket_entries(table: &str, path: &str) -> usize {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|row| row.split_whitespace().nth(7))
+        .filter(|p| *p == path)
+        .count()
+}
+
+/// Run `f` over `items` with at most `limit` in flight, each started after a
+/// random delay up to `jitter`. Results keep the input order.
+pub async fn run_bounded<T, F, Fut>(
+    items: Vec<T>,
+    limit: usize,
+    jitter: Duration,
+    f: F,
+) -> Vec<Fut::Output>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+    let handles: Vec<_> = items
+        .into_iter()
+        .map(|item| {
+            let semaphore = semaphore.clone();
+            let delay = random_delay(jitter);
+            let fut = f(item);
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _permit = semaphore.acquire_owned().await;
+                fut.await
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        if let Ok(result) = handle.await {
+            results.push(result);
+        }
+    }
+    results
+}
+
+fn random_delay(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    let random = uuid::Uuid::new_v4().as_u128() as u64;
+    Duration::from_millis(random % max.as_millis().max(1) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_mux_stats() {
+        let mut stats = MuxStats {
+            sessions: 3,
+            forwarded: 5,
+            forwards: 4,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+        };
+        assert!(!stats.near_limit());
+        assert_eq!(
+            stats.to_string(),
+            "3 shared session(s) (limit 10), 5 forwarded connection(s) over 4 forward(s), as counted by stm"
+        );
+        stats.sessions = 8;
+        assert!(stats.near_limit());
+        assert!(stats.to_string().ends_with("new sessions may be refused"));
+    }
+
+    #[test]
+    fn test_mux_stats_small_limits() {
+        let near = |sessions, max_sessions| {
+            MuxStats {
+                sessions,
+                forwarded: 0,
+                forwards: 0,
+                max_sessions,
+            }
+            .near_limit()
+        };
+        assert!(!near(0, 0));
+        assert!(!near(3, 0));
+        assert!(!near(0, 1));
+        assert!(near(1, 1));
+        assert!(!near(1, 2));
+        assert!(near(2, 2));
+        assert!(!near(1, 3));
+        assert!(near(2, 3));
+        assert!(!near(15, 20));
+        assert!(near(16, 20));
+    }
+
+    #[test]
+    fn test_http_status() {
+        assert_eq!(
+            http_status(b"HTTP/1.1 302 Found\r\nLocation: /login\r\n").as_deref(),
+            Some("302 Found")
+        );
+        assert_eq!(http_status(b"SSH-2.0-OpenSSH_9.6\r\n"), None);
+        let test = TunnelTest::Passed {
+            connect_ms: 1,
+            http: Some(("200 OK".to_string(), 34)),
+        };
+        assert_eq!(test.to_string(), "connected in 1ms, HTTP 200 OK in 34ms");
+        assert_eq!(test.badge(), "✓ 200 OK 34ms");
+    }
+
+    #[tokio::test]
+    async fn test_run_bounded_limits_concurrency() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let results = run_bounded((0..10).collect(), 3, Duration::ZERO, |i| {
+            let running = running.clone();
+            let peak = peak.clone();
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                i * 2
+            }
+        })
+        .await;
+
+        assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn test_count_socket_entries() {
+        let table = "\
+Num       RefCount Protocol Flags    Type St Inode Path
+0000000000000000: 00000002 00000000 00010000 0001 01 31001 /tmp/stm/web-22
+0000000000000000: 00000003 00000000 00000000 0001 03 31002 /tmp/stm/web-22
+0000000000000000: 00000003 00000000 00000000 0001 03 31003 /tmp/stm/web-22
+0000000000000000: 00000003 00000000 00000000 0001 03 31004
+0000000000000000: 00000002 00000000 00010000 0001 01 31005 /tmp/stm/db-22
+";
+        assert_eq!(count_socket_entries(table, "/tmp/stm/web-22"), 3);
+        assert_eq!(count_socket_entries(table, "/tmp/stm/db-22"), 1);
+        assert_eq!(count_socket_entries(table, "/tmp/stm/none-22"), 0);
+    }
+
+    #[test]
+    fn test_random_delay_bounded() {
+        let max = Duration::from_millis(50);
+        for _ in 0..100 {
+            assert!(random_delay(max) < max);
+        }
+        assert_eq!(random_delay(Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_parse_master_pid() {
+        assert_eq!(
+            parse_master_pid("Master running (pid=4242)\r\n"),
+            Some(4242)
+        );
+        assert_eq!(
+            parse_master_pid("Control socket connect(/x): No such file"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_count_established_ports() {
+        let table = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid
+   0: 0100007F:1538 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000
+   1: 0100007F:1538 0100007F:D2F4 01 00000000:00000000 00:00000000 00000000  1000
+   2: 0100007F:D2F4 0100007F:1538 01 00000000:00000000 00:00000000 00000000  1000
+   3: 0100007F:1538 0100007F:D2F6 06 00000000:00000000 00:00000000 00000000  1000
+";
+        // Both ends of the one live connection; 0x1538 is 5432
+        assert_eq!(
+            established_local_ports(table).collect::<Vec<_>>(),
+            [5432, 54004]
+        );
+        assert_eq!(
+            lsof_local_port("n127.0.0.1:5432->127.0.0.1:53988"),
+            Some(5432)
+        );
+        assert_eq!(lsof_local_port("n*:5432"), None);
+    }
+
+    #[tokio::test]
+    async fn test_probe_tunnel_detects_refused_destination() {
+        // Stands in for ssh's listener: accepts, then keeps or drops the connection
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let tunnel = Tunnel::new(port, "localhost".to_string(), 80);
+
+        let keep = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(stream);
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+        });
+        assert!(probe_tunnel(&tunnel).await);
+        assert!(!probe_tunnel(&tunnel).await);
+        keep.await.unwrap();
+
+        // Nothing listening at all
+        assert!(!probe_tunnel(&tunnel).await);
+    }
+}
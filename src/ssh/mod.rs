@@ -1,3 +1,33 @@
+pub mod banner;
+pub mod certificate;
 pub mod config;
 pub mod connection;
+pub mod discovery;
+pub mod health;
+pub mod port_owner;
+pub mod preflight;
+pub mod prerequisite;
+pub mod proxy;
 pub mod tunnel;
+pub mod wol;
+
+use std::sync::OnceLock;
+
+use crate::state::persistence::SshEnvConfig;
+
+static SSH_ENV: OnceLock<SshEnvConfig> = OnceLock::new();
+
+/// Set the environment changes applied to every ssh process; call once at startup.
+pub fn configure_env(env: &SshEnvConfig) {
+    let _ = SSH_ENV.set(env.clone());
+}
+
+/// An `ssh` command with the configured `[ssh_env]` applied, so masters,
+/// forwards and checks all run with the same environment.
+pub fn command() -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new("ssh");
+    if let Some(env) = SSH_ENV.get() {
+        env.apply(&mut cmd);
+    }
+    cmd
+}
@@ -0,0 +1,291 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a terminated port owner gets to let go of the port.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The process listening on a local TCP port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortOwner {
+    pub pid: u32,
+    /// Full command line.
+    pub command: String,
+}
+
+impl PortOwner {
+    /// The control socket named on an ssh command line (`-S path` or
+    /// `-o ControlPath=path`), i.e. which master holds the port.
+    pub fn control_socket(&self) -> Option<PathBuf> {
+        let mut args = self.command.split_whitespace();
+        let program = args.next()?;
+        if program.rsplit('/').next() != Some("ssh") {
+            return None;
+        }
+        while let Some(arg) = args.next() {
+            if arg == "-S" {
+                return args.next().map(PathBuf::from);
+            }
+            if let Some(path) = arg.strip_prefix("-S") {
+                return Some(PathBuf::from(path));
+            }
+            if let Some(path) = arg.strip_prefix("ControlPath=") {
+                return Some(PathBuf::from(path));
+            }
+        }
+        None
+    }
+
+    /// True for stm itself (relays) and ssh masters on a socket in
+    /// `socket_dir`: stm stops those through their tunnels, not by pid.
+    pub fn is_stm(&self, socket_dir: &std::path::Path) -> bool {
+        self.pid == std::process::id()
+            || self
+                .control_socket()
+                .is_some_and(|s| s.starts_with(socket_dir))
+    }
+
+    /// Program name without its path, then as many args as fit in `max` chars.
+    pub fn short_command(&self, max: usize) -> String {
+        let mut args = self.command.split_whitespace();
+        let program = args.next().unwrap_or_default();
+        let program = program.rsplit('/').next().unwrap_or(program);
+        std::iter::once(program)
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(max)
+            .collect()
+    }
+}
+
+/// Find the process listening on `port` with `lsof`, or `ss` where lsof is
+/// missing. `None` if nothing listens or the owner is another user's process.
+pub async fn find(port: u16) -> Option<PortOwner> {
+    let pid = match lsof_pid(port).await {
+        Some(pid) => pid,
+        None => ss_pid(port).await?,
+    };
+    let output = Command::new("ps")
+        .args(["-o", "args=", "-p", &pid.to_string()])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(PortOwner { pid, command })
+}
+
+/// Ask the process holding `port` to exit (SIGTERM) and wait for the port to
+/// come free. `owner` was looked up earlier, so it is looked up again first:
+/// the process may have exited and its pid been reused.
+pub async fn terminate(owner: &PortOwner, port: u16) -> Result<(), String> {
+    if find(port).await.as_ref() != Some(owner) {
+        return Err(format!(
+            "pid {} no longer holds port {port}; not stopping anything",
+            owner.pid
+        ));
+    }
+    let status = Command::new("kill")
+        .arg(owner.pid.to_string())
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .map_err(|e| format!("Failed to run kill: {e}"))?;
+    if !status.success() {
+        return Err(format!("Couldn't stop pid {} (not yours?)", owner.pid));
+    }
+    let deadline = tokio::time::Instant::now() + RELEASE_TIMEOUT;
+    while tokio::time::Instant::now() < deadline {
+        if crate::ssh::tunnel::is_port_available(port) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Err(format!(
+        "pid {} still holds port {port} after SIGTERM",
+        owner.pid
+    ))
+}
+
+/// TCP ports process `pid` listens on, e.g. the -L forwards of an ssh master.
+pub async fn listening_ports(pid: u32) -> Option<Vec<u16>> {
+    let output = Command::new("lsof")
+        .args([
+            "-nP",
+            "-a",
+            "-p",
+            &pid.to_string(),
+            "-iTCP",
+            "-sTCP:LISTEN",
+            "-Fn",
+        ])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await;
+    let mut ports = match output {
+        Ok(output) if output.status.success() => {
+            parse_lsof_ports(&String::from_utf8_lossy(&output.stdout))
+        }
+        // lsof exits 1 when nothing matched; ss tells "none" from "can't say"
+        _ => {
+            let output = Command::new("ss")
+                .arg("-ltnpH")
+                .stdin(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .output()
+                .await
+                .ok()?;
+            parse_ss_ports(&String::from_utf8_lossy(&output.stdout), pid)
+        }
+    };
+    // Listeners on both IPv4 and IPv6 are listed twice
+    ports.sort_unstable();
+    ports.dedup();
+    Some(ports)
+}
+
+async fn lsof_pid(port: u16) -> Option<u32> {
+    let output = Command::new("lsof")
+        .args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-Fp"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    parse_lsof_pid(&String::from_utf8_lossy(&output.stdout))
+}
+
+async fn ss_pid(port: u16) -> Option<u32> {
+    let output = Command::new("ss")
+        .args(["-ltnpH", &format!("sport = :{port}")])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .ok()?;
+    parse_ss_pid(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// First `p<pid>` line of `lsof -F p` output.
+fn parse_lsof_pid(output: &str) -> Option<u32> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix('p')?.parse().ok())
+}
+
+/// First `pid=` in the `users:((...))` column of `ss -p` output.
+fn parse_ss_pid(output: &str) -> Option<u32> {
+    let rest = &output[output.find("pid=")? + 4..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// Ports of the `n<address>:<port>` lines of `lsof -F n` output.
+fn parse_lsof_ports(output: &str) -> Vec<u16> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix('n')?.rsplit(':').next()?.parse().ok())
+        .collect()
+}
+
+/// Local ports of the `ss -ltnp` rows owned by `pid`.
+fn parse_ss_ports(output: &str, pid: u32) -> Vec<u16> {
+    let owner = format!("pid={pid},");
+    output
+        .lines()
+        .filter(|row| row.contains(&owner))
+        .filter_map(|row| {
+            row.split_whitespace()
+                .nth(3)?
+                .rsplit(':')
+                .next()?
+                .parse()
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pids() {
+        assert_eq!(parse_lsof_pid("p4242\nf5\n"), Some(4242));
+        assert_eq!(parse_lsof_pid(""), None);
+        let ss = "LISTEN 0 128 127.0.0.1:5432 0.0.0.0:* users:((\"ssh\",pid=917,fd=5))\n";
+        assert_eq!(parse_ss_pid(ss), Some(917));
+        assert_eq!(parse_ss_pid(""), None);
+    }
+
+    #[test]
+    fn test_parse_listening_ports() {
+        assert_eq!(
+            parse_lsof_ports("p917\nf5\nn127.0.0.1:5432\nf6\nn[::1]:5432\n"),
+            [5432, 5432]
+        );
+        let ss = "\
+LISTEN 0 128 127.0.0.1:5432 0.0.0.0:* users:((\"ssh\",pid=917,fd=5))
+LISTEN 0 128 127.0.0.1:8080 0.0.0.0:* users:((\"python3\",pid=9170,fd=3))
+LISTEN 0 128 [::1]:6379 [::]:* users:((\"ssh\",pid=917,fd=7))
+";
+        assert_eq!(parse_ss_ports(ss, 917), [5432, 6379]);
+    }
+
+    #[test]
+    fn test_is_stm() {
+        let sockets = PathBuf::from("/home/me/.config/stm/sockets");
+        let owner = |pid: u32, command: &str| PortOwner {
+            pid,
+            command: command.to_string(),
+        };
+        assert!(owner(std::process::id(), "stm").is_stm(&sockets));
+        assert!(owner(917, "ssh -M -S /home/me/.config/stm/sockets/db-22 -N db").is_stm(&sockets));
+        // Someone else's master, or anything else, may be offered for stopping
+        assert!(!owner(918, "ssh -M -S /tmp/cm-db -N db").is_stm(&sockets));
+        assert!(!owner(919, "python3 -m http.server 8080").is_stm(&sockets));
+    }
+
+    #[tokio::test]
+    async fn test_terminate_checks_owner_again() {
+        // Nothing listens on the port any more: nothing is signalled
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let gone = PortOwner {
+            pid: u32::MAX,
+            command: "python3 -m http.server".to_string(),
+        };
+        let err = terminate(&gone, port).await.unwrap_err();
+        assert!(err.contains("no longer holds"));
+    }
+
+    #[test]
+    fn test_control_socket() {
+        let owner = |command: &str| PortOwner {
+            pid: 1,
+            command: command.to_string(),
+        };
+        assert_eq!(
+            owner("ssh -M -S /run/stm/db-22 -N db").control_socket(),
+            Some(PathBuf::from("/run/stm/db-22"))
+        );
+        assert_eq!(
+            owner("/usr/bin/ssh -o ControlPath=/tmp/cm -N db").control_socket(),
+            Some(PathBuf::from("/tmp/cm"))
+        );
+        assert_eq!(owner("postgres -D /var/lib/pg").control_socket(), None);
+        assert_eq!(
+            owner("/usr/lib/postgresql/16/bin/postgres -D /var/lib/pg").short_command(14),
+            "postgres -D /v"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use thiserror::Error;
+
+/// Oldest OpenSSH with `-O forward` and `-O cancel` on a ControlMaster.
+pub const MIN_OPENSSH: (u32, u32) = (6, 0);
+
+/// Why stm cannot drive the local `ssh` client.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SshUnavailable {
+    #[error("`ssh` was not found on PATH")]
+    NotFound,
+
+    #[error("`ssh -V` failed: {0}")]
+    Unrunnable(String),
+
+    #[error("`ssh` does not look like OpenSSH: {0}")]
+    NotOpenssh(String),
+
+    #[error("{banner} is too old (OpenSSH {}.{} or newer is required)", MIN_OPENSSH.0, MIN_OPENSSH.1)]
+    TooOld { banner: String },
+}
+
+impl SshUnavailable {
+    /// Steps the user can take to fix the problem.
+    pub fn remediation(&self) -> &'static [&'static str] {
+        match self {
+            SshUnavailable::NotFound | SshUnavailable::Unrunnable(_) => &[
+                "Install the OpenSSH client:",
+                "  macOS: included with the system",
+                "  Debian/Ubuntu: sudo apt install openssh-client",
+                "  Fedora: sudo dnf install openssh-clients",
+                "  Windows: enable the \"OpenSSH Client\" optional feature",
+                "Then make sure `ssh` is on the PATH stm is started with.",
+            ],
+            SshUnavailable::NotOpenssh(_) => &[
+                "stm drives OpenSSH ControlMaster sockets (-M, -S, -O forward).",
+                "Put OpenSSH's `ssh` ahead of other clients on PATH.",
+            ],
+            SshUnavailable::TooOld { .. } => &[
+                "ControlMaster `-O forward`/`-O cancel` need a newer client.",
+                "Upgrade the OpenSSH client package.",
+            ],
+        }
+    }
+}
+
+/// Check that an OpenSSH client supporting ControlMaster forwarding is on PATH.
+/// Returns the version banner on success.
+pub async fn check_ssh() -> Result<String, SshUnavailable> {
+    let output = match crate::ssh::command()
+        .arg("-V")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(SshUnavailable::NotFound),
+        Err(e) => return Err(SshUnavailable::Unrunnable(e.to_string())),
+    };
+
+    // `ssh -V` prints its banner to stderr
+    let text = if output.stderr.is_empty() {
+        output.stdout
+    } else {
+        output.stderr
+    };
+    let banner = String::from_utf8_lossy(&text)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    match parse_openssh_version(&banner) {
+        Some(version) if version >= MIN_OPENSSH => Ok(banner),
+        Some(_) => Err(SshUnavailable::TooOld { banner }),
+        None => Err(SshUnavailable::NotOpenssh(banner)),
+    }
+}
+
+/// Parse the major/minor version out of an `ssh -V` banner,
+/// e.g. "OpenSSH_9.6p1 Ubuntu-3ubuntu13, OpenSSL 3.0.13".
+pub fn parse_openssh_version(banner: &str) -> Option<(u32, u32)> {
+    let rest = banner
+        .split_whitespace()
+        .find_map(|word| word.strip_prefix("OpenSSH_"))?;
+    let rest = rest.strip_prefix("for_Windows_").unwrap_or(rest);
+    let (major, rest) = rest.split_once('.')?;
+    let minor: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_openssh_version() {
+        assert_eq!(
+            parse_openssh_version("OpenSSH_9.6p1 Ubuntu-3ubuntu13, OpenSSL 3.0.13 30 Jan 2024"),
+            Some((9, 6))
+        );
+        assert_eq!(
+            parse_openssh_version("OpenSSH_for_Windows_8.1p1, LibreSSL 3.0.2"),
+            Some((8, 1))
+        );
+        assert_eq!(
+            parse_openssh_version("OpenSSH_5.3p1, OpenSSL 1.0.1e-fips"),
+            Some((5, 3))
+        );
+        assert_eq!(parse_openssh_version("Dropbear v2022.83"), None);
+    }
+}
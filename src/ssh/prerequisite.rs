@@ -0,0 +1,114 @@
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::state::persistence::Prerequisite;
+
+/// How long a prerequisite's ping may take.
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+/// `IFF_UP` in an interface's flags.
+const IFF_UP: u32 = 0x1;
+
+/// Check `prerequisites` in order; the first that fails says why.
+pub async fn check(prerequisites: &[Prerequisite]) -> Result<(), String> {
+    for prerequisite in prerequisites {
+        let interface_down = match prerequisite.interface.as_deref() {
+            Some(name) if !interface_up(name).await => Some(name),
+            _ => None,
+        };
+        let failure = if let Some(name) = interface_down {
+            format!("interface {name} is down")
+        } else if let Some(ref target) = prerequisite.ping {
+            if ping(target).await {
+                continue;
+            }
+            format!("{target} doesn't answer ping")
+        } else {
+            continue;
+        };
+        return Err(match prerequisite.message {
+            Some(ref message) => format!("{message} ({failure})"),
+            None => format!("Connect the VPN first: {failure}"),
+        });
+    }
+    Ok(())
+}
+
+/// Whether the interface exists and is administratively up: from sysfs on
+/// Linux, `ifconfig` elsewhere.
+async fn interface_up(name: &str) -> bool {
+    if cfg!(target_os = "linux") {
+        return tokio::fs::read_to_string(format!("/sys/class/net/{name}/flags"))
+            .await
+            .is_ok_and(|flags| flags_up(&flags));
+    }
+    Command::new("ifconfig")
+        .arg(name)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .is_ok_and(|out| out.status.success() && ifconfig_up(&String::from_utf8_lossy(&out.stdout)))
+}
+
+/// sysfs `flags`, e.g. `0x1091`.
+fn flags_up(flags: &str) -> bool {
+    u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16)
+        .is_ok_and(|flags| flags & IFF_UP != 0)
+}
+
+/// `utun3: flags=8051<UP,POINTOPOINT,RUNNING,MULTICAST> mtu 1380`
+fn ifconfig_up(output: &str) -> bool {
+    output
+        .lines()
+        .next()
+        .and_then(|line| line.split_once('<'))
+        .is_some_and(|(_, flags)| flags.split([',', '>']).any(|flag| flag == "UP"))
+}
+
+/// One echo request with the system `ping`.
+async fn ping(target: &str) -> bool {
+    let child = Command::new("ping")
+        .args(["-c", "1", target])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .status();
+    matches!(
+        tokio::time::timeout(PING_TIMEOUT, child).await,
+        Ok(Ok(status)) if status.success()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interface_flags() {
+        assert!(flags_up("0x1091\n"));
+        assert!(!flags_up("0x1002\n"));
+        assert!(ifconfig_up(
+            "utun3: flags=8051<UP,POINTOPOINT,RUNNING,MULTICAST> mtu 1380\n\tinet 10.8.0.2"
+        ));
+        assert!(!ifconfig_up(
+            "en5: flags=8862<BROADCAST,SIMPLEX,MULTICAST> mtu 1500"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_missing_interface() {
+        let prerequisite = Prerequisite {
+            hosts: Vec::new(),
+            interface: Some("stm-test-none0".to_string()),
+            ping: None,
+            message: Some("Connect the corp VPN first".to_string()),
+        };
+        assert_eq!(
+            check(&[prerequisite]).await,
+            Err("Connect the corp VPN first (interface stm-test-none0 is down)".to_string())
+        );
+        assert_eq!(check(&[]).await, Ok(()));
+    }
+}
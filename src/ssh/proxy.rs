@@ -0,0 +1,40 @@
+/// Build a `ProxyCommand` that reaches the ssh server through an HTTP CONNECT
+/// or SOCKS proxy given as a URL, e.g. `http://proxy.corp:3128` or
+/// `socks5://127.0.0.1:1080`. Uses OpenBSD netcat (`nc -X`), which ships with
+/// macOS and most Linux distributions.
+pub fn proxy_command(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let protocol = match scheme.to_lowercase().as_str() {
+        "http" | "https" => "connect",
+        "socks5" | "socks5h" | "socks" => "5",
+        "socks4" | "socks4a" => "4",
+        _ => return None,
+    };
+    let authority = rest.trim_end_matches('/');
+    let (host, port) = authority.rsplit_once(':')?;
+    if host.is_empty() || authority.contains(['/', '@', ' ']) {
+        return None;
+    }
+    port.parse::<u16>().ok()?;
+    Some(format!("nc -X {protocol} -x {host}:{port} %h %p"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_command() {
+        assert_eq!(
+            proxy_command("http://proxy.corp:3128").as_deref(),
+            Some("nc -X connect -x proxy.corp:3128 %h %p")
+        );
+        assert_eq!(
+            proxy_command("socks5://127.0.0.1:1080/").as_deref(),
+            Some("nc -X 5 -x 127.0.0.1:1080 %h %p")
+        );
+        assert_eq!(proxy_command("proxy.corp:3128"), None);
+        assert_eq!(proxy_command("ftp://proxy.corp:21"), None);
+        assert_eq!(proxy_command("http://user:pw@proxy.corp:3128"), None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+use std::time::Duration;
+
+/// How long to wait for a woken host to answer on its ssh port.
+pub const WAKE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Parse a MAC address written as `aa:bb:cc:dd:ee:ff` or `aa-bb-cc-dd-ee-ff`.
+pub fn parse_mac(text: &str) -> Option<[u8; 6]> {
+    let mut mac = [0u8; 6];
+    let mut parts = text.trim().split([':', '-']);
+    for byte in &mut mac {
+        let part = parts.next().filter(|p| p.len() == 2)?;
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    parts.next().is_none().then_some(mac)
+}
+
+/// Six `0xff` bytes followed by the MAC repeated 16 times.
+pub fn magic_packet(mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac);
+    }
+    packet
+}
+
+/// Broadcast a magic packet on the local network (UDP port 9).
+pub async fn send_local(mac: [u8; 6]) -> anyhow::Result<()> {
+    let socket = tokio::net::UdpSocket::bind(("0.0.0.0", 0)).await?;
+    socket.set_broadcast(true)?;
+    socket
+        .send_to(&magic_packet(mac), ("255.255.255.255", 9))
+        .await?;
+    Ok(())
+}
+
+/// Have a host on the sleeping machine's network send the packet, through
+/// that host's running master. Needs `wakeonlan` installed there.
+pub async fn send_via(socket_path: &Path, ssh_target: &str, mac: &str) -> anyhow::Result<()> {
+    let socket = socket_path.to_string_lossy().to_string();
+    let output = crate::ssh::command()
+        .args(["-S", &socket, ssh_target, "wakeonlan", mac])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .await?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(anyhow::anyhow!("wakeonlan failed: {}", stderr.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mac() {
+        let mac = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:5e"), Some(mac));
+        assert_eq!(parse_mac("00-1A-2B-3C-4D-5E"), Some(mac));
+        assert_eq!(parse_mac("00:1a:2b:3c:4d"), None);
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:5e:6f"), None);
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:zz"), None);
+    }
+
+    #[test]
+    fn test_magic_packet() {
+        let mac = [1, 2, 3, 4, 5, 6];
+        let packet = magic_packet(mac);
+        assert_eq!(packet.len(), 102);
+        assert_eq!(&packet[..6], &[0xff; 6]);
+        assert_eq!(&packet[96..], &mac);
+    }
+}
@@ -1,13 +1,26 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::SystemTime;
 
-use crate::ssh::tunnel::Tunnel;
+use crate::scheduler::Schedule;
+use crate::ssh::tunnel::{Tunnel, TunnelDirection, TunnelSort};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct History {
     pub hosts: HashMap<String, HostHistory>,
+    /// Why history.json couldn't be read; while set, `save` won't overwrite it.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+    /// Parts of history.json that were skipped, such as invalid schedules.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    /// Modification time of history.json when it was last read or written,
+    /// to notice changes made by `stm tunnel`.
+    #[serde(skip)]
+    synced: Cell<Option<SystemTime>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +28,52 @@ pub struct HostHistory {
     pub last_used: DateTime<Utc>,
     pub use_count: u32,
     pub tunnels: Vec<SavedTunnel>,
+    /// Saved tunnels added and removed over time, oldest first.
+    #[serde(default)]
+    pub timeline: Vec<TunnelChange>,
+    /// Connections and tunnel activity, oldest first.
+    #[serde(default)]
+    pub events: Vec<HostEvent>,
+    #[serde(default)]
+    pub tunnel_sort: TunnelSort,
+    /// Tunnel groups folded to one row in the tunnel list.
+    #[serde(default)]
+    pub collapsed_groups: Vec<String>,
+}
+
+/// Most timeline entries kept per host; older ones are dropped.
+pub const MAX_TIMELINE: usize = 100;
+
+/// Most activity events kept per host.
+pub const MAX_EVENTS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostEventKind {
+    Connected,
+    Disconnected,
+    /// An established master went away.
+    ConnectionLost,
+    ConnectionFailed,
+    TunnelEnabled,
+    TunnelFailed,
+}
+
+/// Something that happened on a host, kept for the details view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostEvent {
+    pub at: DateTime<Utc>,
+    pub kind: HostEventKind,
+    pub message: String,
+}
+
+/// One change to a host's set of saved tunnels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelChange {
+    pub at: DateTime<Utc>,
+    pub added: bool,
+    /// Forward spec, `local_port:remote_host:remote_port`.
+    pub spec: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +81,41 @@ pub struct SavedTunnel {
     pub local_port: u16,
     pub remote_host: String,
     pub remote_port: u16,
+    #[serde(default)]
+    pub direction: TunnelDirection,
+    #[serde(default)]
+    pub local_socket: Option<String>,
+    #[serde(default)]
+    pub bind_address: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub idle_timeout_mins: Option<u32>,
+    #[serde(default)]
+    pub bandwidth_limit: Option<u64>,
+    #[serde(default, deserialize_with = "crate::scheduler::deserialize_lenient")]
+    pub schedule: Option<Schedule>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub https: bool,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub stats: TunnelStats,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Usage of a saved tunnel across sessions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelStats {
+    pub times_enabled: u32,
+    pub last_enabled: Option<DateTime<Utc>>,
+    /// Total time the tunnel has been enabled, in seconds.
+    pub uptime_secs: u64,
 }
 
 impl From<&Tunnel> for SavedTunnel {
@@ -30,10 +124,121 @@ impl From<&Tunnel> for SavedTunnel {
             local_port: t.local_port,
             remote_host: t.remote_host.clone(),
             remote_port: t.remote_port,
+            direction: t.direction,
+            local_socket: t.local_socket.clone(),
+            bind_address: t.bind_address.clone(),
+            label: t.label.clone(),
+            note: t.note.clone(),
+            idle_timeout_mins: t.idle_timeout_mins,
+            bandwidth_limit: t.bandwidth_limit,
+            schedule: t.schedule,
+            group: t.group.clone(),
+            https: t.https,
+            enabled: t.saved_enabled.unwrap_or(t.enabled),
+            stats: TunnelStats::default(),
+            created_at: t.created_at,
         }
     }
 }
 
+impl From<&SavedTunnel> for Tunnel {
+    /// A new, disabled tunnel for a saved entry, remembering its saved state.
+    fn from(st: &SavedTunnel) -> Self {
+        let mut tunnel = Tunnel::new(st.local_port, st.remote_host.clone(), st.remote_port);
+        tunnel.direction = st.direction;
+        tunnel.local_socket = st.local_socket.clone();
+        tunnel.bind_address = st.bind_address.clone();
+        tunnel.label = st.label.clone();
+        tunnel.note = st.note.clone();
+        tunnel.idle_timeout_mins = st.idle_timeout_mins;
+        tunnel.bandwidth_limit = st.bandwidth_limit;
+        tunnel.schedule = st.schedule;
+        tunnel.group = st.group.clone();
+        tunnel.https = st.https;
+        tunnel.created_at = st.created_at;
+        tunnel.saved_enabled = Some(st.enabled);
+        tunnel
+    }
+}
+
+impl SavedTunnel {
+    /// Returns true if this saved entry describes the same forward as `tunnel`.
+    pub fn matches(&self, tunnel: &Tunnel) -> bool {
+        self.local_port == tunnel.local_port
+            && self.remote_host == tunnel.remote_host
+            && self.remote_port == tunnel.remote_port
+            && self.direction == tunnel.direction
+            && self.local_socket == tunnel.local_socket
+            && self.bind_address == tunnel.bind_address
+    }
+
+    /// Forward spec as passed to ssh, prefixed with `R ` for remote forwards.
+    pub fn spec(&self) -> String {
+        let prefix = match self.direction {
+            TunnelDirection::Local => "",
+            TunnelDirection::Remote => "R ",
+        };
+        format!("{prefix}{}", Tunnel::from(self).forward_spec())
+    }
+}
+
+impl HostHistory {
+    /// Replace the saved tunnels, recording added and removed specs in the timeline.
+    fn set_tunnels(&mut self, tunnels: Vec<SavedTunnel>) {
+        let now = Utc::now();
+        let old: Vec<Tunnel> = self.tunnels.iter().map(Tunnel::from).collect();
+        let new: Vec<Tunnel> = tunnels.iter().map(Tunnel::from).collect();
+        let removed = self
+            .tunnels
+            .iter()
+            .zip(&old)
+            .filter(|(_, old)| !tunnels.iter().any(|t| t.matches(old)))
+            .map(|(t, _)| t);
+        let added = tunnels
+            .iter()
+            .zip(&new)
+            .filter(|(_, new)| !self.tunnels.iter().any(|t| t.matches(new)))
+            .map(|(t, _)| t);
+        let changes: Vec<TunnelChange> = removed
+            .map(|t| (false, t))
+            .chain(added.map(|t| (true, t)))
+            .map(|(added, t)| TunnelChange {
+                at: now,
+                added,
+                spec: t.spec(),
+            })
+            .collect();
+
+        self.timeline.extend(changes);
+        let excess = self.timeline.len().saturating_sub(MAX_TIMELINE);
+        self.timeline.drain(..excess);
+        self.tunnels = tunnels;
+    }
+
+    /// Times the connection was lost in the last `hours`.
+    pub fn drops_within(&self, hours: i64) -> usize {
+        let since = Utc::now() - chrono::Duration::hours(hours);
+        self.events
+            .iter()
+            .filter(|e| e.kind == HostEventKind::ConnectionLost && e.at >= since)
+            .count()
+    }
+
+    /// Returns true if any saved tunnel's ports or remote host contain `query`
+    /// (expected lowercase).
+    pub fn tunnel_matches(&self, query: &str) -> bool {
+        self.tunnels.iter().any(|t| {
+            t.local_port.to_string().contains(query)
+                || t.remote_port.to_string().contains(query)
+                || t.remote_host.to_lowercase().contains(query)
+                || [&t.label, &t.note]
+                    .into_iter()
+                    .flatten()
+                    .any(|text| text.to_lowercase().contains(query))
+        })
+    }
+}
+
 impl History {
     pub fn history_path() -> PathBuf {
         dirs::home_dir()
@@ -43,26 +248,87 @@ impl History {
 
     pub fn load() -> Self {
         let path = Self::history_path();
-        if path.exists() {
-            match std::fs::read_to_string(&path) {
-                Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
-                Err(_) => Self::default(),
-            }
-        } else {
-            Self::default()
+        let modified = file_modified(&path);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self {
+                synced: Cell::new(modified),
+                ..Self::parse(&content)
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => Self {
+                load_error: Some(e.to_string()),
+                ..Self::default()
+            },
+        }
+    }
+
+    /// History from history.json's contents. Schedules that don't parse are
+    /// dropped with a warning; any other error gives an empty history that
+    /// refuses to save over the file.
+    fn parse(content: &str) -> Self {
+        let parsed = serde_json::from_str::<serde_json::Value>(content).and_then(|value| {
+            let warnings = invalid_schedules(&value);
+            serde_json::from_value::<Self>(value).map(|history| (history, warnings))
+        });
+        match parsed {
+            Ok((history, warnings)) => Self {
+                warnings,
+                ..history
+            },
+            Err(e) => Self {
+                load_error: Some(e.to_string()),
+                ..Self::default()
+            },
         }
     }
 
     pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(ref e) = self.load_error {
+            anyhow::bail!("history.json couldn't be read at startup ({e}); not overwriting it");
+        }
         let path = Self::history_path();
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
         let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(&path, content)?;
+        crate::state::persistence::write_atomic(&path, &content)?;
+        self.synced.set(file_modified(&path));
         Ok(())
     }
 
+    /// Take saved tunnels changed on disk since the last load or save, as
+    /// `stm tunnel add`/`rm` does, so the next save keeps them. Returns true
+    /// if history.json had changed.
+    pub fn reload_saved_tunnels(&mut self) -> bool {
+        let modified = file_modified(&Self::history_path());
+        if self.load_error.is_some() || modified == self.synced.get() {
+            return false;
+        }
+        let disk = Self::load();
+        self.synced.set(modified);
+        if disk.load_error.is_some() {
+            return false;
+        }
+        self.merge_saved_tunnels(disk);
+        true
+    }
+
+    /// Saved tunnels and their timeline from `disk`; everything else, such
+    /// as events not written yet, is kept.
+    fn merge_saved_tunnels(&mut self, disk: History) {
+        for (name, host) in disk.hosts {
+            match self.hosts.get_mut(&name) {
+                Some(entry) => {
+                    entry.tunnels = host.tunnels;
+                    entry.timeline = host.timeline;
+                }
+                None => {
+                    self.hosts.insert(name, host);
+                }
+            }
+        }
+    }
+
     pub fn record_connection(&mut self, host_name: &str) {
         let entry = self
             .hosts
@@ -71,17 +337,143 @@ impl History {
                 last_used: Utc::now(),
                 use_count: 0,
                 tunnels: Vec::new(),
+                timeline: Vec::new(),
+                events: Vec::new(),
+                tunnel_sort: TunnelSort::default(),
+                collapsed_groups: Vec::new(),
             });
         entry.last_used = Utc::now();
         entry.use_count += 1;
     }
 
+    /// Add to a host's activity log. Hosts never connected to have no entry
+    /// and are left alone.
+    pub fn record_event(&mut self, host_name: &str, kind: HostEventKind, message: String) {
+        if let Some(entry) = self.hosts.get_mut(host_name) {
+            entry.events.push(HostEvent {
+                at: Utc::now(),
+                kind,
+                message,
+            });
+            let excess = entry.events.len().saturating_sub(MAX_EVENTS);
+            entry.events.drain(..excess);
+        }
+    }
+
+    /// Replace a host's saved tunnels with `tunnels`, leaving out ephemeral
+    /// ones and those declared in config.
     pub fn save_tunnels(&mut self, host_name: &str, tunnels: &[Tunnel]) {
         if let Some(entry) = self.hosts.get_mut(host_name) {
-            entry.tunnels = tunnels.iter().map(SavedTunnel::from).collect();
+            let saved = tunnels
+                .iter()
+                .filter(|t| !t.ephemeral && !t.from_config)
+                .map(|t| {
+                    // Carry usage stats over from the previously saved entry
+                    let mut saved = SavedTunnel::from(t);
+                    if let Some(old) = entry.tunnels.iter().find(|old| old.matches(t)) {
+                        saved.stats = old.stats.clone();
+                    }
+                    saved
+                })
+                .collect();
+            entry.set_tunnels(saved);
         }
     }
 
+    /// Count an enable of a saved tunnel.
+    pub fn record_tunnel_enabled(&mut self, host_name: &str, tunnel: &Tunnel) {
+        if let Some(saved) = self.saved_tunnel_mut(host_name, tunnel) {
+            saved.stats.times_enabled += 1;
+            saved.stats.last_enabled = Some(Utc::now());
+        }
+    }
+
+    /// Add time a saved tunnel spent enabled to its cumulative uptime.
+    pub fn add_tunnel_uptime(&mut self, host_name: &str, tunnel: &Tunnel, secs: u64) {
+        if let Some(saved) = self.saved_tunnel_mut(host_name, tunnel) {
+            saved.stats.uptime_secs += secs;
+        }
+    }
+
+    /// Move a saved tunnel to a new local port, keeping its stats.
+    pub fn remap_saved_tunnel(&mut self, host_name: &str, tunnel: &Tunnel, local_port: u16) {
+        let Some(entry) = self.hosts.get_mut(host_name) else {
+            return;
+        };
+        let mut tunnels = entry.tunnels.clone();
+        if let Some(saved) = tunnels.iter_mut().find(|t| t.matches(tunnel)) {
+            saved.local_port = local_port;
+            entry.set_tunnels(tunnels);
+        }
+    }
+
+    /// Point a saved tunnel at a new remote host and port, keeping its stats.
+    pub fn retarget_saved_tunnel(
+        &mut self,
+        host_name: &str,
+        tunnel: &Tunnel,
+        remote_host: &str,
+        remote_port: u16,
+    ) {
+        let Some(entry) = self.hosts.get_mut(host_name) else {
+            return;
+        };
+        let mut tunnels = entry.tunnels.clone();
+        if let Some(saved) = tunnels.iter_mut().find(|t| t.matches(tunnel)) {
+            saved.remote_host = remote_host.to_string();
+            saved.remote_port = remote_port;
+            entry.set_tunnels(tunnels);
+        }
+    }
+
+    fn saved_tunnel_mut(&mut self, host_name: &str, tunnel: &Tunnel) -> Option<&mut SavedTunnel> {
+        self.hosts
+            .get_mut(host_name)?
+            .tunnels
+            .iter_mut()
+            .find(|t| t.matches(tunnel))
+    }
+
+    /// Add a saved tunnel for a host, creating its history entry if needed.
+    /// Returns false if an identical forward is already saved.
+    pub fn add_saved_tunnel(&mut self, host_name: &str, tunnel: &Tunnel) -> bool {
+        let entry = self
+            .hosts
+            .entry(host_name.to_string())
+            .or_insert(HostHistory {
+                last_used: Utc::now(),
+                use_count: 0,
+                tunnels: Vec::new(),
+                timeline: Vec::new(),
+                events: Vec::new(),
+                tunnel_sort: TunnelSort::default(),
+                collapsed_groups: Vec::new(),
+            });
+        if entry.tunnels.iter().any(|t| t.matches(tunnel)) {
+            return false;
+        }
+        let mut tunnels = entry.tunnels.clone();
+        tunnels.push(SavedTunnel::from(tunnel));
+        entry.set_tunnels(tunnels);
+        true
+    }
+
+    /// Remove a saved tunnel by its forward. Returns false if it wasn't saved.
+    pub fn remove_saved_tunnel(&mut self, host_name: &str, tunnel: &Tunnel) -> bool {
+        let Some(entry) = self.hosts.get_mut(host_name) else {
+            return false;
+        };
+        let tunnels: Vec<SavedTunnel> = entry
+            .tunnels
+            .iter()
+            .filter(|t| !t.matches(tunnel))
+            .cloned()
+            .collect();
+        let removed = tunnels.len() != entry.tunnels.len();
+        entry.set_tunnels(tunnels);
+        removed
+    }
+
     pub fn get_saved_tunnels(&self, host_name: &str) -> Vec<SavedTunnel> {
         self.hosts
             .get(host_name)
@@ -89,14 +481,75 @@ impl History {
             .unwrap_or_default()
     }
 
+    pub fn tunnel_sort(&self, host_name: &str) -> TunnelSort {
+        self.hosts
+            .get(host_name)
+            .map(|h| h.tunnel_sort)
+            .unwrap_or_default()
+    }
+
+    pub fn set_tunnel_sort(&mut self, host_name: &str, sort: TunnelSort) {
+        if let Some(entry) = self.hosts.get_mut(host_name) {
+            entry.tunnel_sort = sort;
+        }
+    }
+
+    pub fn collapsed_groups(&self, host_name: &str) -> Vec<String> {
+        self.hosts
+            .get(host_name)
+            .map(|h| h.collapsed_groups.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_collapsed_groups(&mut self, host_name: &str, groups: Vec<String>) {
+        if let Some(entry) = self.hosts.get_mut(host_name) {
+            entry.collapsed_groups = groups;
+        }
+    }
+
+    /// Saved tunnels ordered most used first (enable count, then uptime).
+    pub fn saved_tunnels_by_usage(&self, host_name: &str) -> Vec<SavedTunnel> {
+        let mut saved = self.get_saved_tunnels(host_name);
+        saved.sort_by_key(|t| std::cmp::Reverse((t.stats.times_enabled, t.stats.uptime_secs)));
+        saved
+    }
+
     #[allow(dead_code)]
     pub fn recent_hosts(&self) -> Vec<String> {
         let mut entries: Vec<_> = self.hosts.iter().collect();
-        entries.sort_by(|a, b| b.1.last_used.cmp(&a.1.last_used));
+        entries.sort_by_key(|(_, h)| std::cmp::Reverse(h.last_used));
         entries.into_iter().map(|(name, _)| name.clone()).collect()
     }
 }
 
+fn file_modified(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Warnings for saved tunnel schedules in raw history.json that don't parse;
+/// `SavedTunnel` reads them as none.
+fn invalid_schedules(value: &serde_json::Value) -> Vec<String> {
+    let Some(hosts) = value.get("hosts").and_then(|h| h.as_object()) else {
+        return Vec::new();
+    };
+    hosts
+        .iter()
+        .flat_map(|(host, entry)| {
+            let tunnels = entry.get("tunnels").and_then(|t| t.as_array());
+            tunnels.into_iter().flatten().filter_map(move |tunnel| {
+                let schedule = tunnel.get("schedule").filter(|s| !s.is_null())?;
+                let error = match schedule.as_str() {
+                    Some(text) => Schedule::parse(text).err()?,
+                    None => "not a string".to_string(),
+                };
+                Some(format!(
+                    "{host}: ignoring saved schedule {schedule} ({error})"
+                ))
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,6 +566,48 @@ mod tests {
         assert_eq!(restored.hosts["myhost"].use_count, 2);
     }
 
+    #[test]
+    fn test_merge_saved_tunnels() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+        history.save_tunnels(
+            "myhost",
+            &[Tunnel::new(5432, "localhost".to_string(), 5432)],
+        );
+        let mut disk = history.clone();
+        disk.add_saved_tunnel("myhost", &Tunnel::new(6379, "localhost".to_string(), 6379));
+        disk.add_saved_tunnel("other", &Tunnel::new(8080, "web".to_string(), 80));
+        history.record_event("myhost", HostEventKind::Connected, "Connected".to_string());
+
+        history.merge_saved_tunnels(disk);
+        assert_eq!(history.get_saved_tunnels("myhost").len(), 2);
+        assert_eq!(history.get_saved_tunnels("other").len(), 1);
+        assert_eq!(history.hosts["myhost"].events.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_schedule_dropped_not_history() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+        history.save_tunnels(
+            "myhost",
+            &[Tunnel::new(5432, "localhost".to_string(), 5432)],
+        );
+        let mut value = serde_json::to_value(&history).unwrap();
+        value["hosts"]["myhost"]["tunnels"][0]["schedule"] = "9am-5pm".into();
+
+        let restored = History::parse(&value.to_string());
+        assert_eq!(restored.load_error, None);
+        assert_eq!(restored.get_saved_tunnels("myhost").len(), 1);
+        assert_eq!(restored.get_saved_tunnels("myhost")[0].schedule, None);
+        assert_eq!(restored.warnings.len(), 1);
+
+        // Anything else unreadable is kept from being saved over
+        let broken = History::parse("{\"hosts\": 3}");
+        assert!(broken.load_error.is_some());
+        assert!(broken.save().is_err());
+    }
+
     #[test]
     fn test_save_tunnels() {
         let mut history = History::default();
@@ -126,6 +621,179 @@ mod tests {
         assert_eq!(saved[0].local_port, 5432);
     }
 
+    #[test]
+    fn test_save_tunnels_skips_ephemeral() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+
+        let mut scratch = Tunnel::new(9229, "localhost".to_string(), 9229);
+        scratch.ephemeral = true;
+        let mut declared = Tunnel::new(8080, "localhost".to_string(), 80);
+        declared.from_config = true;
+        let db = Tunnel::new(5432, "localhost".to_string(), 5432);
+        history.save_tunnels("myhost", &[scratch, declared, db]);
+
+        let saved = history.get_saved_tunnels("myhost");
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].local_port, 5432);
+        assert_eq!(history.hosts["myhost"].timeline.len(), 1);
+    }
+
+    #[test]
+    fn test_groups_persist() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+
+        let mut grafana = Tunnel::new(3000, "localhost".to_string(), 3000);
+        grafana.group = Some("observability".to_string());
+        history.save_tunnels("myhost", &[grafana]);
+        history.set_collapsed_groups("myhost", vec!["observability".to_string()]);
+
+        let json = serde_json::to_string(&history).unwrap();
+        let restored: History = serde_json::from_str(&json).unwrap();
+        let tunnel = Tunnel::from(&restored.get_saved_tunnels("myhost")[0]);
+        assert_eq!(tunnel.group.as_deref(), Some("observability"));
+        assert_eq!(restored.collapsed_groups("myhost"), ["observability"]);
+    }
+
+    #[test]
+    fn test_save_tunnels_keeps_enabled_state() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+
+        let mut on = Tunnel::new(5432, "localhost".to_string(), 5432);
+        on.enabled = true;
+        let off = Tunnel::new(6379, "localhost".to_string(), 6379);
+        history.save_tunnels("myhost", &[on, off]);
+
+        let json = serde_json::to_string(&history).unwrap();
+        let restored: History = serde_json::from_str(&json).unwrap();
+        let saved = restored.get_saved_tunnels("myhost");
+        assert!(saved[0].enabled);
+        assert!(!saved[1].enabled);
+    }
+
+    #[test]
+    fn test_loaded_tunnel_keeps_saved_state_until_toggled() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+        let mut on = Tunnel::new(5432, "localhost".to_string(), 5432);
+        on.enabled = true;
+        history.save_tunnels("myhost", &[on]);
+
+        // Loaded disabled, as without auto_restore
+        let mut loaded = Tunnel::from(&history.get_saved_tunnels("myhost")[0]);
+        assert!(!loaded.enabled);
+        history.save_tunnels("myhost", std::slice::from_ref(&loaded));
+        assert!(history.get_saved_tunnels("myhost")[0].enabled);
+
+        loaded.saved_enabled = None;
+        history.save_tunnels("myhost", &[loaded]);
+        assert!(!history.get_saved_tunnels("myhost")[0].enabled);
+    }
+
+    #[test]
+    fn test_saved_tunnel_without_enabled_field() {
+        let json = r#"{"local_port":8080,"remote_host":"localhost","remote_port":80}"#;
+        let saved: SavedTunnel = serde_json::from_str(json).unwrap();
+        assert!(!saved.enabled);
+    }
+
+    #[test]
+    fn test_add_and_remove_saved_tunnel() {
+        let mut history = History::default();
+        let tunnel = Tunnel::new(5432, "localhost".to_string(), 5432);
+
+        assert!(history.add_saved_tunnel("newhost", &tunnel));
+        assert!(!history.add_saved_tunnel("newhost", &tunnel));
+        assert_eq!(history.get_saved_tunnels("newhost").len(), 1);
+
+        assert!(history.remove_saved_tunnel("newhost", &tunnel));
+        assert!(!history.remove_saved_tunnel("newhost", &tunnel));
+        assert!(history.get_saved_tunnels("newhost").is_empty());
+    }
+
+    #[test]
+    fn test_tunnel_stats_survive_save_and_order_by_usage() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+        let rarely = Tunnel::new(8080, "localhost".to_string(), 80);
+        let often = Tunnel::new(5432, "localhost".to_string(), 5432);
+        history.save_tunnels("myhost", &[rarely.clone(), often.clone()]);
+
+        history.record_tunnel_enabled("myhost", &often);
+        history.record_tunnel_enabled("myhost", &often);
+        history.add_tunnel_uptime("myhost", &often, 90);
+        // Re-saving the list (as on every toggle) must keep the stats
+        history.save_tunnels("myhost", &[rarely, often]);
+
+        let saved = history.saved_tunnels_by_usage("myhost");
+        assert_eq!(saved[0].local_port, 5432);
+        assert_eq!(saved[0].stats.times_enabled, 2);
+        assert_eq!(saved[0].stats.uptime_secs, 90);
+        assert!(saved[0].stats.last_enabled.is_some());
+        assert_eq!(saved[1].stats, TunnelStats::default());
+    }
+
+    #[test]
+    fn test_timeline_records_changes() {
+        let mut history = History::default();
+        history.record_connection("myhost");
+        let db = Tunnel::new(5432, "localhost".to_string(), 5432);
+        let web = Tunnel::new(8080, "localhost".to_string(), 80);
+
+        history.save_tunnels("myhost", std::slice::from_ref(&db));
+        history.save_tunnels("myhost", std::slice::from_ref(&db));
+        history.save_tunnels("myhost", &[web]);
+
+        let timeline = &history.hosts["myhost"].timeline;
+        let changes: Vec<(bool, &str)> = timeline
+            .iter()
+            .map(|c| (c.added, c.spec.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (true, "5432:localhost:5432"),
+                (false, "5432:localhost:5432"),
+                (true, "8080:localhost:80"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_capped_and_counted() {
+        let mut history = History::default();
+        history.record_event("stranger", HostEventKind::Connected, String::new());
+        assert!(history.hosts.is_empty());
+
+        history.record_connection("flaky");
+        for _ in 0..MAX_EVENTS {
+            history.record_event("flaky", HostEventKind::Connected, String::new());
+        }
+        history.record_event("flaky", HostEventKind::ConnectionLost, "reset".to_string());
+        history.record_event("flaky", HostEventKind::ConnectionLost, "reset".to_string());
+        let entry = &history.hosts["flaky"];
+        assert_eq!(entry.events.len(), MAX_EVENTS);
+        assert_eq!(entry.drops_within(24), 2);
+
+        let json = serde_json::to_string(&history).unwrap();
+        assert!(json.contains("\"connection_lost\""));
+    }
+
+    #[test]
+    fn test_tunnel_matches() {
+        let mut history = History::default();
+        history.record_connection("db-box");
+        let tunnels = vec![Tunnel::new(15432, "Postgres.internal".to_string(), 5432)];
+        history.save_tunnels("db-box", &tunnels);
+
+        let entry = &history.hosts["db-box"];
+        assert!(entry.tunnel_matches("5432"));
+        assert!(entry.tunnel_matches("postgres"));
+        assert!(!entry.tunnel_matches("6379"));
+    }
+
     #[test]
     fn test_recent_hosts_ordering() {
         let mut history = History::default();
@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::notify::EventKind;
+use crate::ssh::config::{SshHost, X11Forwarding};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -7,24 +12,499 @@ pub struct AppConfig {
     pub general: GeneralConfig,
     #[serde(default)]
     pub ui: UiConfig,
+    /// Per-host settings keyed by ssh config alias (`[hosts.<alias>]`).
+    #[serde(default)]
+    pub hosts: HashMap<String, HostConfig>,
+    /// Where connection events are delivered (`[[notifications]]`).
+    #[serde(default)]
+    pub notifications: Vec<SinkConfig>,
+    /// Environment for the ssh processes stm starts (`[ssh_env]`).
+    #[serde(default)]
+    pub ssh_env: SshEnvConfig,
+    /// Local ports suggested for well-known remote services (`[port_suggestions]`).
+    #[serde(default)]
+    pub port_suggestions: PortSuggestions,
+    /// Template variables shared by every host (`[vars]`).
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    /// Tunnels declared for matching hosts (`[[tunnels]]`).
+    #[serde(default)]
+    pub tunnels: Vec<TunnelTemplate>,
+    /// Services offered by the add-tunnel modal's preset picker (`[[presets]]`).
+    #[serde(default)]
+    pub presets: Vec<ServicePreset>,
+    /// Checks run before connecting to matching hosts (`[[prerequisites]]`).
+    #[serde(default)]
+    pub prerequisites: Vec<Prerequisite>,
+    /// Commands moved to other keys (`[keys]`), e.g. `delete_tunnel = "b"`.
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+    /// Why config.toml couldn't be read; stm runs on defaults and `save`
+    /// won't overwrite the file while this is set.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+    /// Parts of config.toml that were left out, such as unknown host columns.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// A tunnel declared in `config.toml`, set up on connect to every host its
+/// `hosts` globs match. `{name}` in `forward` and `label` is replaced with
+/// the host's variables.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelTemplate {
+    /// Host aliases (globs) to declare the tunnel for; all when empty.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// `local_port:remote_host:remote_port`, e.g. `{port_base}5432:db.{env}:5432`.
+    pub forward: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl TunnelTemplate {
+    pub fn applies_to(&self, alias: &str) -> bool {
+        matches_hosts(&self.hosts, alias)
+    }
+
+    /// The forward and label with `vars` filled in.
+    pub fn resolve(
+        &self,
+        vars: &HashMap<String, String>,
+    ) -> Result<(u16, String, u16, Option<String>), String> {
+        let spec = expand_vars(&self.forward, vars)?;
+        let (local_port, remote_host, remote_port) = crate::ssh::tunnel::parse_forward_spec(&spec)
+            .ok_or_else(|| format!("invalid forward '{spec}'"))?;
+        let label = self
+            .label
+            .as_deref()
+            .map(|label| expand_vars(label, vars))
+            .transpose()?;
+        Ok((local_port, remote_host, remote_port, label))
+    }
+}
+
+/// Whether `alias` matches one of the `hosts` globs; an empty list matches all.
+fn matches_hosts(hosts: &[String], alias: &str) -> bool {
+    hosts.is_empty()
+        || hosts
+            .iter()
+            .any(|p| crate::ssh::config::wildcard_match(p, alias))
+}
+
+/// Something that must hold before connecting to the hosts its `hosts` globs
+/// match, such as a VPN interface being up, so a missing VPN is reported at
+/// once rather than as an ssh timeout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Prerequisite {
+    /// Host aliases (globs) to check it for; all when empty.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Network interface that must be up, e.g. `wg0`.
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Address that must answer a ping, e.g. the VPN's DNS server.
+    #[serde(default)]
+    pub ping: Option<String>,
+    /// Shown when the check fails, e.g. `Connect the corp VPN first`.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl Prerequisite {
+    pub fn applies_to(&self, alias: &str) -> bool {
+        matches_hosts(&self.hosts, alias)
+    }
+}
+
+/// Replace each `{name}` in `text` with its value in `vars`.
+pub fn expand_vars(text: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in '{text}'"))?;
+        let name = &after[..end];
+        let value = vars
+            .get(name)
+            .ok_or_else(|| format!("unknown variable {{{name}}} in '{text}'"))?;
+        out.push_str(value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Remote ports stm treats as well-known services unless
+/// `port_suggestions.services` overrides the list.
+pub const WELL_KNOWN_SERVICES: &[u16] = &[
+    1433,  // SQL Server
+    1521,  // Oracle
+    2181,  // ZooKeeper
+    3306,  // MySQL / MariaDB
+    5432,  // PostgreSQL
+    5672,  // RabbitMQ
+    5984,  // CouchDB
+    6379,  // Redis
+    8086,  // InfluxDB
+    9042,  // Cassandra
+    9092,  // Kafka
+    9200,  // Elasticsearch
+    11211, // memcached
+    27017, // MongoDB
+];
+
+/// A service the add-tunnel modal can fill its ports in for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServicePreset {
+    pub name: String,
+    pub remote_port: u16,
+    /// Local port to forward on; the `[port_suggestions]` one when unset.
+    #[serde(default)]
+    pub local_port: Option<u16>,
+    /// Remote host to fill in; the modal's own is kept when unset.
+    #[serde(default)]
+    pub remote_host: Option<String>,
+}
+
+/// Presets offered after the user's own from `[[presets]]`.
+const BUILTIN_PRESETS: &[(&str, u16)] = &[
+    ("PostgreSQL", 5432),
+    ("MySQL", 3306),
+    ("Redis", 6379),
+    ("MongoDB", 27017),
+    ("Elasticsearch", 9200),
+    ("RabbitMQ", 5672),
+    ("HTTP", 8080),
+    ("HTTPS", 8443),
+];
+
+/// Maps a remote service port to the local port to forward it on, so the
+/// same service lands on the same local port whatever host it runs on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortSuggestions {
+    /// Added to a well-known service's port (5432 -> 15432).
+    #[serde(default = "default_port_offset")]
+    pub offset: u16,
+    /// Remote ports that get the offset; the built-in list when unset.
+    #[serde(default)]
+    pub services: Option<Vec<u16>>,
+    /// `[remote, local]` pairs that win over the offset.
+    #[serde(default)]
+    pub map: Vec<[u16; 2]>,
+}
+
+impl Default for PortSuggestions {
+    fn default() -> Self {
+        Self {
+            offset: default_port_offset(),
+            services: None,
+            map: Vec::new(),
+        }
+    }
+}
+
+impl PortSuggestions {
+    /// The conventional local port for `remote_port`, if it is a known service.
+    pub fn suggest(&self, remote_port: u16) -> Option<u16> {
+        if let Some([_, local]) = self.map.iter().find(|[remote, _]| *remote == remote_port) {
+            return Some(*local);
+        }
+        let services = self.services.as_deref().unwrap_or(WELL_KNOWN_SERVICES);
+        if !services.contains(&remote_port) {
+            return None;
+        }
+        remote_port.checked_add(self.offset).filter(|&p| p != 0)
+    }
+}
+
+/// Environment variables set or removed for every spawned ssh, e.g. to pin
+/// `SSH_AUTH_SOCK` or drop `SSH_ASKPASS` regardless of the desktop session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshEnvConfig {
+    /// Variables to set; a leading `~/` in values is expanded.
+    #[serde(default)]
+    pub set: HashMap<String, String>,
+    /// Variables to remove.
+    #[serde(default)]
+    pub clear: Vec<String>,
+}
+
+impl SshEnvConfig {
+    pub fn apply(&self, cmd: &mut tokio::process::Command) {
+        for name in &self.clear {
+            cmd.env_remove(name);
+        }
+        for (name, value) in &self.set {
+            match value.strip_prefix("~/") {
+                Some(rest) => cmd.env(name, dirs::home_dir().unwrap_or_default().join(rest)),
+                None => cmd.env(name, value),
+            };
+        }
+    }
+}
+
+/// One notification sink and the events it receives.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub sink: SinkKind,
+    /// Event types to deliver; all when empty.
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// Host aliases (globs) to deliver for; all when empty.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "sink", rename_all = "kebab-case")]
+pub enum SinkKind {
+    StatusBar,
+    Desktop,
+    Webhook { url: String },
+    Command { command: String },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HostConfig {
+    #[serde(default)]
+    pub forward_agent: bool,
+    #[serde(default)]
+    pub forward_x11: X11Forwarding,
+    /// known_hosts file for this host's master, overriding `general.known_hosts_file`.
+    #[serde(default)]
+    pub known_hosts_file: Option<PathBuf>,
+    /// Give this alias its own master even if another alias shares its hostname:port.
+    #[serde(default)]
+    pub separate_socket: bool,
+    /// Proxy URL for this host, overriding `general.proxy`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Remote host pre-filled in the add-tunnel modal instead of `localhost`.
+    #[serde(default)]
+    pub default_remote_host: Option<String>,
+    /// MAC address for Wake-on-LAN (`W`).
+    #[serde(default)]
+    pub mac_address: Option<String>,
+    /// Alias whose running master sends the wake packet, for hosts on another network.
+    #[serde(default)]
+    pub wol_relay: Option<String>,
+    /// Cap on forwards over this host's master, overriding `general.max_forwards`.
+    #[serde(default)]
+    pub max_forwards: Option<usize>,
+    /// The server's `MaxSessions`, if not sshd's default of 10.
+    #[serde(default)]
+    pub max_sessions: Option<usize>,
+    /// Template variables for this host, overriding `[vars]`.
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    /// Labels shown in the hosts panel's `tags` column, e.g. `["prod", "db"]`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl HostConfig {
+    /// Apply these settings to a host parsed from the ssh config.
+    pub fn apply(&self, host: &mut SshHost) {
+        host.forward_agent = self.forward_agent;
+        host.forward_x11 = self.forward_x11;
+        host.separate_socket = self.separate_socket;
+        if let Some(ref file) = self.known_hosts_file {
+            host.known_hosts_file = Some(file.clone());
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
     #[serde(default = "default_ssh_config_path")]
     pub ssh_config_path: PathBuf,
+    /// Several ssh config files (work, personal) merged into one host list;
+    /// replaces `ssh_config_path` when set.
+    #[serde(default)]
+    pub ssh_config_paths: Vec<PathBuf>,
     #[serde(default = "default_socket_dir")]
     pub socket_dir: PathBuf,
     #[serde(default)]
     pub auto_restore: bool,
     #[serde(default = "default_max_recent")]
     pub max_recent_hosts: usize,
+    #[serde(default)]
+    pub keep_masters_on_exit: bool,
+    /// Dedicated `UserKnownHostsFile` for masters started by stm.
+    #[serde(default)]
+    pub known_hosts_file: Option<PathBuf>,
+    /// `[first, last]` local ports to remap to automatically when a restored
+    /// tunnel's port is taken. Without it, stm asks for a port.
+    #[serde(default)]
+    pub auto_remap_range: Option<[u16; 2]>,
+    /// Without `auto_remap_range`, move such a tunnel to the nearest free
+    /// port above its own instead of asking.
+    #[serde(default)]
+    pub auto_remap: bool,
+    /// Cap on forwards over one master; further tunnels wait in a queue until
+    /// one is disabled. Unlimited when unset.
+    #[serde(default)]
+    pub max_forwards: Option<usize>,
+    /// HTTP CONNECT or SOCKS proxy (`http://host:port`, `socks5://host:port`)
+    /// for reaching hosts that have no ProxyCommand/ProxyJump of their own.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Probe hosts (DNS + TCP to the ssh port) in the background.
+    #[serde(default)]
+    pub reachability_sweep: bool,
+    /// Seconds a cached reachability result stays fresh.
+    #[serde(default = "default_reachability_ttl")]
+    pub reachability_ttl_secs: u64,
+    /// Connect through each enabled tunnel periodically to check it works.
+    #[serde(default)]
+    pub probe_tunnels: bool,
+    /// Label unlabelled tunnels on enable from what answers on them: an HTTP
+    /// page title, or the protocol (postgres, redis, ssh…).
+    #[serde(default)]
+    pub auto_label: bool,
+    /// Relay -L tunnels through stm so their traffic can be counted.
+    #[serde(default)]
+    pub traffic_stats: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
     #[serde(default = "default_true")]
     pub show_all_hosts: bool,
+    #[serde(default)]
+    pub layout: PanelLayout,
+    /// Share of the main area given to the hosts panel, in percent.
+    #[serde(default = "default_hosts_percent")]
+    pub hosts_percent: u16,
+    /// Switch to the tunnels panel once a connection is established.
+    #[serde(default)]
+    pub focus_tunnels_on_connect: bool,
+    /// Start with disabled tunnels folded into one summary row.
+    #[serde(default)]
+    pub collapse_disabled_tunnels: bool,
+    /// Order of the hosts panel.
+    #[serde(default)]
+    pub host_sort: HostSort,
+    /// Start in safe mode: hostnames, addresses and users masked for demos.
+    #[serde(default)]
+    pub safe_mode: bool,
+    /// Fields of a hosts panel row, in order, e.g. `["dot", "alias:16", "target"]`.
+    #[serde(
+        default = "default_host_columns",
+        deserialize_with = "deserialize_host_columns"
+    )]
+    pub host_columns: Vec<HostColumn>,
+}
+
+/// Something a hosts panel row can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostField {
+    /// Connection or reachability dot.
+    Dot,
+    Alias,
+    /// `user@hostname:port`.
+    Target,
+    /// When stm last connected, e.g. `3d ago`.
+    LastUsed,
+    /// `tags` from `[hosts.<alias>]`.
+    Tags,
+    /// Number of saved tunnels; on/total for the connected host.
+    Tunnels,
+}
+
+impl HostField {
+    const ALL: [HostField; 6] = [
+        HostField::Dot,
+        HostField::Alias,
+        HostField::Target,
+        HostField::LastUsed,
+        HostField::Tags,
+        HostField::Tunnels,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HostField::Dot => "dot",
+            HostField::Alias => "alias",
+            HostField::Target => "target",
+            HostField::LastUsed => "last_used",
+            HostField::Tags => "tags",
+            HostField::Tunnels => "tunnels",
+        }
+    }
+}
+
+/// A hosts panel column: a field, padded or cut to `width` when set.
+/// Written `"field"` or `"field:width"` in `ui.host_columns`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HostColumn {
+    pub field: HostField,
+    pub width: Option<u16>,
+}
+
+impl TryFrom<String> for HostColumn {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let (name, width) = match text.split_once(':') {
+            Some((name, width)) => {
+                let width = width
+                    .parse()
+                    .map_err(|_| format!("bad width in host column '{text}'"))?;
+                (name, Some(width))
+            }
+            None => (text.as_str(), None),
+        };
+        let field = HostField::ALL
+            .into_iter()
+            .find(|f| f.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown host column '{name}' (dot, alias, target, last_used, tags, tunnels)"
+                )
+            })?;
+        Ok(Self { field, width })
+    }
+}
+
+impl From<HostColumn> for String {
+    fn from(column: HostColumn) -> String {
+        match column.width {
+            Some(width) => format!("{}:{width}", column.field.name()),
+            None => column.field.name().to_string(),
+        }
+    }
+}
+
+/// How the hosts panel is ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostSort {
+    /// Most recently used first.
+    #[default]
+    Recent,
+    /// Experimental: hosts that answered the last reachability sweep,
+    /// fastest first, then the rest by recent use.
+    Latency,
+}
+
+/// How the hosts and tunnels panels are arranged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PanelLayout {
+    /// Hosts on the left, tunnels on the right.
+    #[default]
+    HostsLeft,
+    /// Tunnels above hosts.
+    TunnelsTop,
+    /// One panel at a time with a tab strip; Tab switches.
+    Tabbed,
 }
 
 fn default_ssh_config_path() -> PathBuf {
@@ -41,6 +521,63 @@ fn default_max_recent() -> usize {
     10
 }
 
+fn default_hosts_percent() -> u16 {
+    35
+}
+
+fn default_port_offset() -> u16 {
+    10000
+}
+
+fn default_reachability_ttl() -> u64 {
+    300
+}
+
+/// `ui.host_columns` without the entries that don't parse, so a typo doesn't
+/// fail the whole config; `AppConfig::load` warns about them.
+fn deserialize_host_columns<'de, D>(deserializer: D) -> Result<Vec<HostColumn>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let columns: Vec<HostColumn> = Vec::<toml::Value>::deserialize(deserializer)?
+        .into_iter()
+        .filter_map(|entry| HostColumn::try_from(entry.as_str()?.to_string()).ok())
+        .collect();
+    if columns.is_empty() {
+        return Ok(default_host_columns());
+    }
+    Ok(columns)
+}
+
+/// Warnings for the `ui.host_columns` entries in `content` that are left out.
+fn ignored_host_columns(content: &str) -> Vec<String> {
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let entries = table
+        .get("ui")
+        .and_then(|ui| ui.get("host_columns"))
+        .and_then(|columns| columns.as_array());
+    entries
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let error = match entry.as_str() {
+                Some(text) => HostColumn::try_from(text.to_string()).err()?,
+                None => format!("host column {entry}: not a string"),
+            };
+            Some(format!("Ignoring {error}"))
+        })
+        .collect()
+}
+
+fn default_host_columns() -> Vec<HostColumn> {
+    [HostField::Dot, HostField::Alias, HostField::Target]
+        .into_iter()
+        .map(|field| HostColumn { field, width: None })
+        .collect()
+}
+
 fn default_true() -> bool {
     true
 }
@@ -49,9 +586,33 @@ impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
             ssh_config_path: default_ssh_config_path(),
+            ssh_config_paths: Vec::new(),
             socket_dir: default_socket_dir(),
             auto_restore: false,
             max_recent_hosts: default_max_recent(),
+            keep_masters_on_exit: false,
+            known_hosts_file: None,
+            auto_remap_range: None,
+            auto_remap: false,
+            max_forwards: None,
+            proxy: None,
+            reachability_sweep: false,
+            probe_tunnels: false,
+            auto_label: false,
+            traffic_stats: false,
+            reachability_ttl_secs: default_reachability_ttl(),
+        }
+    }
+}
+
+impl GeneralConfig {
+    /// The ssh config files to read hosts from, first one winning for an
+    /// alias defined in several.
+    pub fn ssh_configs(&self) -> Vec<PathBuf> {
+        if self.ssh_config_paths.is_empty() {
+            vec![self.ssh_config_path.clone()]
+        } else {
+            self.ssh_config_paths.clone()
         }
     }
 }
@@ -60,11 +621,51 @@ impl Default for UiConfig {
     fn default() -> Self {
         Self {
             show_all_hosts: true,
+            layout: PanelLayout::default(),
+            hosts_percent: default_hosts_percent(),
+            focus_tunnels_on_connect: false,
+            collapse_disabled_tunnels: false,
+            host_sort: HostSort::default(),
+            safe_mode: false,
+            host_columns: default_host_columns(),
         }
     }
 }
 
 impl AppConfig {
+    /// Variables for `[[tunnels]]` templates on `host`: `{host}` (the alias)
+    /// and `{hostname}`, then `[vars]`, then `[hosts.<alias>.vars]`.
+    pub fn template_vars(&self, host: &SshHost) -> HashMap<String, String> {
+        let mut vars = HashMap::from([
+            ("host".to_string(), host.name.clone()),
+            (
+                "hostname".to_string(),
+                host.hostname.clone().unwrap_or_else(|| host.name.clone()),
+            ),
+        ]);
+        vars.extend(self.vars.clone());
+        if let Some(host_config) = self.hosts.get(&host.name) {
+            vars.extend(host_config.vars.clone());
+        }
+        vars
+    }
+
+    /// `[[presets]]`, then the built-in ones they don't rename.
+    pub fn service_presets(&self) -> Vec<ServicePreset> {
+        let mut presets = self.presets.clone();
+        for &(name, remote_port) in BUILTIN_PRESETS {
+            if !presets.iter().any(|p| p.name.eq_ignore_ascii_case(name)) {
+                presets.push(ServicePreset {
+                    name: name.to_string(),
+                    remote_port,
+                    local_port: None,
+                    remote_host: None,
+                });
+            }
+        }
+        presets
+    }
+
     pub fn config_path() -> PathBuf {
         dirs::home_dir()
             .unwrap_or_default()
@@ -73,28 +674,145 @@ impl AppConfig {
 
     pub fn load() -> Self {
         let path = Self::config_path();
-        if path.exists() {
-            match std::fs::read_to_string(&path) {
-                Ok(content) => toml::from_str(&content).unwrap_or_default(),
-                Err(_) => Self::default(),
-            }
-        } else {
-            Self::default()
+        let loaded = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map(|config| Self {
+                    warnings: ignored_host_columns(&content),
+                    ..config
+                })
+                .map_err(|e| describe_toml_error(&content, &e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        };
+        loaded.unwrap_or_else(|e| Self {
+            load_error: Some(e),
+            ..Self::default()
+        })
+    }
+
+    /// Write `values`, keyed `table.key`, into config.toml, leaving the rest
+    /// of the file on disk (comments included) as it is.
+    pub fn save_settings(&self, values: &[(&str, toml_edit::Value)]) -> anyhow::Result<()> {
+        if let Some(ref e) = self.load_error {
+            anyhow::bail!("config.toml couldn't be read at startup ({e}); fix it and restart stm");
         }
+        let path = Self::config_path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let content = with_values(&content, values)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(&path, &content)?;
+        Ok(())
     }
+}
 
-    #[allow(dead_code)]
-    pub fn save(&self) -> anyhow::Result<()> {
+impl AppConfig {
+    /// Write `keys` as config.toml's `[keys]` table, leaving the rest of the
+    /// file on disk (comments included) as it is.
+    pub fn save_keys(keys: &BTreeMap<String, String>) -> anyhow::Result<()> {
         let path = Self::config_path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let content = with_keys(&content, keys)?;
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let content = toml::to_string_pretty(self)?;
-        std::fs::write(&path, content)?;
+        write_atomic(&path, &content)?;
         Ok(())
     }
 }
 
+fn parse_document(content: &str) -> anyhow::Result<toml_edit::DocumentMut> {
+    content.parse().map_err(|e: toml_edit::TomlError| {
+        anyhow::anyhow!(
+            "config.toml doesn't parse ({}); not touching it",
+            e.message()
+        )
+    })
+}
+
+/// `content` with each `table.key` in `values` set, keeping the comments
+/// around values it replaces.
+fn with_values(content: &str, values: &[(&str, toml_edit::Value)]) -> anyhow::Result<String> {
+    let mut document = parse_document(content)?;
+    for (path, value) in values {
+        let Some((table, key)) = path.split_once('.') else {
+            anyhow::bail!("Setting {path} has no table");
+        };
+        let Some(table) = document
+            .entry(table)
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+        else {
+            anyhow::bail!("{table} in config.toml isn't a table; not touching it");
+        };
+        match table.get_mut(key).and_then(|item| item.as_value_mut()) {
+            Some(old) => {
+                let decor = old.decor().clone();
+                *old = value.clone();
+                *old.decor_mut() = decor;
+            }
+            None => {
+                table.insert(key, toml_edit::Item::Value(value.clone()));
+            }
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// `content` with its `[keys]` table replaced by `keys`, or removed if empty.
+fn with_keys(content: &str, keys: &BTreeMap<String, String>) -> anyhow::Result<String> {
+    let mut document = parse_document(content)?;
+    if keys.is_empty() {
+        document.remove("keys");
+        return Ok(document.to_string());
+    }
+    // The table keeps its place in the file; an inline `keys` is replaced
+    let mut table = match document.remove("keys") {
+        Some(toml_edit::Item::Table(table)) => table,
+        _ => toml_edit::Table::new(),
+    };
+    table.retain(|name, _| keys.contains_key(name));
+    for (name, key) in keys {
+        table.insert(name, toml_edit::value(key.as_str()));
+    }
+    document.insert("keys", toml_edit::Item::Table(table));
+    Ok(document.to_string())
+}
+
+/// A one-line description of a parse error in `content`, with its line.
+fn describe_toml_error(content: &str, error: &toml::de::Error) -> String {
+    let message = error.message().lines().collect::<Vec<_>>().join(", ");
+    match error.span() {
+        Some(span) => {
+            let line = content[..span.start].matches('\n').count() + 1;
+            format!("line {line}: {message}")
+        }
+        None => message,
+    }
+}
+
+/// Write `content` to `path` via a temporary sibling file and a rename, so a
+/// crash mid-write never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
 /// Ensure config directory and example config exist.
 pub fn ensure_config_dir() -> anyhow::Result<PathBuf> {
     let config_dir = dirs::home_dir().unwrap_or_default().join(".config/stm");
@@ -114,6 +832,70 @@ mod tests {
         assert!(config.ui.show_all_hosts);
     }
 
+    #[test]
+    fn test_with_keys_keeps_rest_of_file() {
+        let content = "\
+# my settings
+[general]
+auto_restore = true # on purpose
+
+[keys]
+delete_tunnel = \"b\"
+toggle_all = \"ctrl+t\"
+
+[ui]
+layout = \"tabbed\"
+";
+        let keys = BTreeMap::from([("toggle_all".to_string(), "f2".to_string())]);
+        let updated = with_keys(content, &keys).unwrap();
+        assert!(updated.starts_with("# my settings\n[general]\nauto_restore = true # on purpose\n"));
+        assert!(updated.contains("toggle_all = \"f2\""));
+        assert!(!updated.contains("delete_tunnel"));
+        assert!(updated.find("[keys]") < updated.find("[ui]"));
+
+        let cleared = with_keys(&updated, &BTreeMap::new()).unwrap();
+        assert!(!cleared.contains("[keys]"));
+        assert!(with_keys("[general", &keys).is_err());
+    }
+
+    #[test]
+    fn test_unreadable_config_not_saved_over() {
+        let config = AppConfig {
+            load_error: Some("expected `=`".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(config.save_settings(&[]).is_err());
+    }
+
+    #[test]
+    fn test_with_values_keeps_rest_of_file() {
+        let content = "\
+# my settings
+[general]
+auto_restore = false # on purpose
+# socket_dir = \"~/.ssh/sockets\"
+
+[ui]
+layout = \"tabbed\"
+";
+        let values = [
+            ("general.auto_restore", toml_edit::Value::from(true)),
+            ("ui.hosts_percent", toml_edit::Value::from(40)),
+        ];
+        let updated = with_values(content, &values).unwrap();
+        assert!(updated.starts_with(
+            "# my settings\n[general]\nauto_restore = true # on purpose\n# socket_dir"
+        ));
+        assert!(updated.contains("layout = \"tabbed\"\nhosts_percent = 40\n"));
+        assert!(!updated.contains("max_recent_hosts"));
+
+        let created = with_values("", &values).unwrap();
+        let config: AppConfig = toml::from_str(&created).unwrap();
+        assert!(config.general.auto_restore);
+        assert_eq!(config.ui.hosts_percent, 40);
+        assert!(with_values("[general", &values).is_err());
+    }
+
     #[test]
     fn test_config_roundtrip() {
         let config = AppConfig::default();
@@ -129,6 +911,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_host_columns() {
+        let config: AppConfig = toml::from_str(
+            r#"
+[ui]
+host_columns = ["dot", "alias:12", "tags", "tunnels"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.ui.host_columns.len(), 4);
+        assert_eq!(
+            config.ui.host_columns[1],
+            HostColumn {
+                field: HostField::Alias,
+                width: Some(12)
+            }
+        );
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        assert!(serialized.contains(r#""alias:12""#));
+
+        // A typo drops that column, not the rest of the config
+        let content =
+            "[general]\nauto_restore = true\n[ui]\nhost_columns = [\"uptime\", \"alias\"]";
+        let config: AppConfig = toml::from_str(content).unwrap();
+        assert!(config.general.auto_restore);
+        assert_eq!(config.ui.host_columns.len(), 1);
+        let warnings = ignored_host_columns(content);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("unknown host column 'uptime'"));
+        assert_eq!(AppConfig::default().ui.host_columns.len(), 3);
+    }
+
     #[test]
     fn test_partial_config_parse() {
         let toml_str = r#"
@@ -140,9 +954,228 @@ auto_restore = true
         assert_eq!(config.general.max_recent_hosts, 10); // default
     }
 
+    #[test]
+    fn test_layout_parse() {
+        let toml_str = r#"
+[ui]
+layout = "tunnels-top"
+hosts_percent = 40
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.ui.layout, PanelLayout::TunnelsTop);
+        assert_eq!(config.ui.hosts_percent, 40);
+        assert!(config.ui.show_all_hosts); // default
+    }
+
+    #[test]
+    fn test_tunnel_templates() {
+        let toml_str = r#"
+[vars]
+env = "dev"
+port_base = "1"
+
+[hosts.prod-db.vars]
+env = "prod"
+port_base = "3"
+
+[[tunnels]]
+hosts = ["*-db"]
+forward = "{port_base}5432:db.{env}.internal:5432"
+label = "postgres-{env}"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        let template = &config.tunnels[0];
+        assert!(template.applies_to("prod-db"));
+        assert!(!template.applies_to("web"));
+
+        let mut vars = config.vars.clone();
+        assert_eq!(
+            template.resolve(&vars),
+            Ok((
+                15432,
+                "db.dev.internal".to_string(),
+                5432,
+                Some("postgres-dev".to_string())
+            ))
+        );
+        vars.extend(config.hosts["prod-db"].vars.clone());
+        assert_eq!(template.resolve(&vars).unwrap().0, 35432);
+
+        vars.remove("env");
+        assert!(template.resolve(&vars).unwrap_err().contains("{env}"));
+    }
+
+    #[test]
+    fn test_prerequisites_parse() {
+        let toml_str = r#"
+[[prerequisites]]
+hosts = ["corp-*", "jenkins"]
+interface = "wg0"
+message = "Connect the corp VPN first"
+
+[[prerequisites]]
+hosts = ["lab"]
+ping = "10.20.0.1"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.prerequisites.len(), 2);
+        let vpn = &config.prerequisites[0];
+        assert_eq!(vpn.interface.as_deref(), Some("wg0"));
+        assert!(vpn.applies_to("corp-db") && vpn.applies_to("jenkins"));
+        assert!(!vpn.applies_to("lab"));
+        assert_eq!(config.prerequisites[1].ping.as_deref(), Some("10.20.0.1"));
+    }
+
+    #[test]
+    fn test_port_suggestions() {
+        let mut ports = PortSuggestions::default();
+        assert_eq!(ports.suggest(5432), Some(15432));
+        assert_eq!(ports.suggest(6379), Some(16379));
+        assert_eq!(ports.suggest(8443), None);
+
+        ports.map = vec![[5432, 25432]];
+        ports.services = Some(vec![8443]);
+        assert_eq!(ports.suggest(5432), Some(25432));
+        assert_eq!(ports.suggest(6379), None);
+        assert_eq!(ports.suggest(8443), Some(18443));
+
+        ports.offset = 60000;
+        assert_eq!(ports.suggest(8443), None);
+    }
+
+    #[test]
+    fn test_ssh_env() {
+        let toml_str = r#"
+[ssh_env]
+clear = ["SSH_ASKPASS"]
+
+[ssh_env.set]
+SSH_AUTH_SOCK = "/run/user/1000/agent.sock"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        let mut cmd = tokio::process::Command::new("ssh");
+        config.ssh_env.apply(&mut cmd);
+        let envs: Vec<_> = cmd.as_std().get_envs().collect();
+        assert!(envs.contains(&(std::ffi::OsStr::new("SSH_ASKPASS"), None)));
+        assert!(envs.contains(&(
+            std::ffi::OsStr::new("SSH_AUTH_SOCK"),
+            Some(std::ffi::OsStr::new("/run/user/1000/agent.sock"))
+        )));
+    }
+
+    #[test]
+    fn test_notifications_parse() {
+        let toml_str = r#"
+[[notifications]]
+sink = "webhook"
+url = "https://hooks.slack.com/services/T0/B0/x"
+events = ["connection_lost"]
+hosts = ["prod-*"]
+
+[[notifications]]
+sink = "desktop"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.notifications.len(), 2);
+        assert_eq!(config.notifications[0].events, [EventKind::ConnectionLost]);
+        assert_eq!(config.notifications[1].sink, SinkKind::Desktop);
+        assert!(config.notifications[1].events.is_empty());
+
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        let deserialized: AppConfig = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.notifications, config.notifications);
+    }
+
+    #[test]
+    fn test_host_config_parse() {
+        let toml_str = r#"
+[hosts.shared-box]
+forward_agent = true
+forward_x11 = "untrusted"
+known_hosts_file = "/home/user/.config/stm/known_hosts.client-a"
+default_remote_host = "10.0.0.5"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        let host_config = &config.hosts["shared-box"];
+        assert!(host_config.forward_agent);
+        assert_eq!(host_config.forward_x11, X11Forwarding::Untrusted);
+        assert_eq!(host_config.default_remote_host.as_deref(), Some("10.0.0.5"));
+
+        let mut host = SshHost {
+            known_hosts_file: Some(PathBuf::from("/general/known_hosts")),
+            ..Default::default()
+        };
+        host_config.apply(&mut host);
+        assert_eq!(
+            host.known_hosts_file,
+            Some(PathBuf::from("/home/user/.config/stm/known_hosts.client-a"))
+        );
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("stm-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert!(!dir.join("state.json.tmp").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_empty_config_parse() {
         let config: AppConfig = toml::from_str("").unwrap();
         assert!(!config.general.auto_restore);
     }
+
+    #[test]
+    fn test_service_presets() {
+        let toml_str = r#"
+[[presets]]
+name = "Grafana"
+remote_port = 3000
+local_port = 13000
+
+[[presets]]
+name = "postgresql"
+remote_port = 6432
+remote_host = "pgbouncer"
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        let presets = config.service_presets();
+        assert_eq!(presets[0].name, "Grafana");
+        assert_eq!(presets[0].local_port, Some(13000));
+        // A user preset named like a built-in replaces it
+        assert_eq!(presets[1].remote_port, 6432);
+        assert_eq!(
+            presets
+                .iter()
+                .filter(|p| p.name.eq_ignore_ascii_case("PostgreSQL"))
+                .count(),
+            1
+        );
+        assert!(presets
+            .iter()
+            .any(|p| p.name == "Redis" && p.remote_port == 6379));
+        assert_eq!(
+            AppConfig::default().service_presets().len(),
+            BUILTIN_PRESETS.len()
+        );
+    }
+
+    #[test]
+    fn test_ssh_configs() {
+        let config = AppConfig::default();
+        assert_eq!(config.general.ssh_configs(), [default_ssh_config_path()]);
+        let toml_str = r#"
+[general]
+ssh_config_paths = ["/home/me/.ssh/config", "/home/me/work/ssh_config"]
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.general.ssh_configs().len(), 2);
+    }
 }
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Last-known reachability of hosts, keyed by alias, so health shows up
+/// immediately on startup while a background sweep refreshes it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ReachabilityCache {
+    pub hosts: HashMap<String, Reachability>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reachability {
+    /// A TCP connection to the ssh port succeeded.
+    pub reachable: bool,
+    /// Address the hostname resolved to, if DNS succeeded.
+    pub address: Option<String>,
+    /// TCP handshake time to the ssh port, if it answered.
+    #[serde(default)]
+    pub rtt_ms: Option<u64>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl Reachability {
+    /// Returns true if the result is older than `ttl_secs`.
+    pub fn is_stale(&self, ttl_secs: u64, now: DateTime<Utc>) -> bool {
+        (now - self.checked_at).num_seconds() >= ttl_secs as i64
+    }
+}
+
+impl ReachabilityCache {
+    pub fn cache_path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".config/stm/reachability.json")
+    }
+
+    pub fn load() -> Self {
+        match std::fs::read_to_string(Self::cache_path()) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::cache_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        crate::state::persistence::write_atomic(&path, &content)?;
+        Ok(())
+    }
+
+    pub fn get(&self, host_name: &str) -> Option<&Reachability> {
+        self.hosts.get(host_name)
+    }
+
+    pub fn record(
+        &mut self,
+        host_name: &str,
+        reachable: bool,
+        address: Option<String>,
+        rtt_ms: Option<u64>,
+    ) {
+        self.hosts.insert(
+            host_name.to_string(),
+            Reachability {
+                reachable,
+                address,
+                rtt_ms,
+                checked_at: Utc::now(),
+            },
+        );
+    }
+
+    /// Measured round trip of a host that answered in the latest sweep.
+    pub fn rtt_ms(&self, host_name: &str) -> Option<u64> {
+        self.get(host_name)
+            .filter(|r| r.reachable)
+            .and_then(|r| r.rtt_ms)
+    }
+
+    /// Returns true if `host_name` has no result or only a stale one.
+    pub fn needs_refresh(&self, host_name: &str, ttl_secs: u64) -> bool {
+        self.get(host_name)
+            .is_none_or(|r| r.is_stale(ttl_secs, Utc::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staleness() {
+        let mut cache = ReachabilityCache::default();
+        assert!(cache.needs_refresh("web", 300));
+
+        cache.record("web", true, Some("10.0.0.5".to_string()), Some(12));
+        assert!(!cache.needs_refresh("web", 300));
+        assert_eq!(cache.rtt_ms("web"), Some(12));
+
+        cache.hosts.get_mut("web").unwrap().checked_at = Utc::now() - chrono::Duration::minutes(10);
+        assert!(cache.needs_refresh("web", 300));
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let restored: ReachabilityCache = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get("web"), cache.get("web"));
+
+        cache.record("db", false, None, None);
+        assert_eq!(cache.rtt_ms("db"), None);
+    }
+}
@@ -4,6 +4,8 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, stdout};
+use std::path::PathBuf;
+use std::sync::Mutex;
 
 pub type Tui = Terminal<CrosstermBackend<io::Stdout>>;
 
@@ -20,10 +22,52 @@ pub fn restore() -> io::Result<()> {
     Ok(())
 }
 
+/// Last sanitized app state, included in crash reports.
+static STATE_SNAPSHOT: Mutex<String> = Mutex::new(String::new());
+
+/// Record the current app state so a panic can include it in the crash report.
+pub fn record_state_snapshot(snapshot: String) {
+    if let Ok(mut guard) = STATE_SNAPSHOT.lock() {
+        *guard = snapshot;
+    }
+}
+
 pub fn install_panic_hook() {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
         let _ = restore();
+        let report_path = write_crash_report(panic_info);
         original_hook(panic_info);
+        if let Some(path) = report_path {
+            eprintln!(
+                "stm crashed. A crash report was written to {}",
+                path.display()
+            );
+        }
     }));
 }
+
+/// Write panic message, backtrace and the last state snapshot to
+/// `~/.config/stm/crash-<timestamp>.log`.
+fn write_crash_report(panic_info: &std::panic::PanicHookInfo) -> Option<PathBuf> {
+    let backtrace = std::backtrace::Backtrace::force_capture();
+    // try_lock: the panic may have happened while the snapshot was being written
+    let snapshot = STATE_SNAPSHOT
+        .try_lock()
+        .map(|s| s.clone())
+        .unwrap_or_else(|_| "<unavailable>".to_string());
+
+    let report = format!(
+        "stm {} crash report\n\n{panic_info}\n\n== State ==\n{snapshot}\n== Backtrace ==\n{backtrace}\n",
+        env!("CARGO_PKG_VERSION"),
+    );
+
+    let dir = dirs::home_dir()?.join(".config/stm");
+    let path = dir.join(format!(
+        "crash-{}.log",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::fs::create_dir_all(&dir).ok()?;
+    std::fs::write(&path, report).ok()?;
+    Some(path)
+}
@@ -1,192 +1,747 @@
 use ratatui::{
-    layout::{Constraint, Flex, Layout, Rect},
-    style::{Modifier, Style},
+    layout::{Constraint, Flex, Layout},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph},
     Frame,
 };
 
+use crate::ssh::tunnel::{Tunnel, TunnelDirection};
+use crate::state::persistence::ServicePreset;
+use crate::ui::input::{self, EditKey, TextInput};
+use crate::ui::safe_mode::Masker;
 use crate::ui::theme;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ModalField {
+    Preset,
+    Direction,
     LocalPort,
     RemoteHost,
     RemotePort,
+    BindAddress,
+    Label,
+    Save,
+}
+
+impl ModalField {
+    /// Tab order.
+    const ALL: [ModalField; 8] = [
+        ModalField::Preset,
+        ModalField::Direction,
+        ModalField::LocalPort,
+        ModalField::RemoteHost,
+        ModalField::RemotePort,
+        ModalField::BindAddress,
+        ModalField::Label,
+        ModalField::Save,
+    ];
 }
 
 #[derive(Debug, Clone)]
 pub struct AddModalState {
-    pub local_port: String,
-    pub remote_host: String,
-    pub remote_port: String,
+    pub direction: TunnelDirection,
+    pub local_port: TextInput,
+    pub remote_host: TextInput,
+    pub remote_port: TextInput,
+    /// Empty for ssh's default (loopback).
+    pub bind_address: TextInput,
+    /// Optional name for the tunnel list.
+    pub label: TextInput,
+    /// Only for this connection: never written to history.
+    pub ephemeral: bool,
     pub active_field: ModalField,
     pub error_message: Option<String>,
+    /// Local port the last submit found taken, for offering to free it.
+    pub busy_port: Option<u16>,
+    /// Remote hosts used before on this connection, most used first.
+    pub host_suggestions: Vec<String>,
+    /// While cycling with ↑/↓: the text typed before, and the shown match.
+    suggestion: Option<(String, usize)>,
+    /// Services to fill the ports in for, local ports already resolved.
+    pub presets: Vec<ServicePreset>,
+    /// Index into `presets` of the one last filled in.
+    preset: Option<usize>,
 }
 
 impl AddModalState {
     pub fn new() -> Self {
         Self {
-            local_port: String::new(),
-            remote_host: "localhost".to_string(),
-            remote_port: String::new(),
+            direction: TunnelDirection::Local,
+            // A leading `/` switches to a Unix socket path to listen on
+            local_port: TextInput::default().with_filter(|text, c| {
+                port_or_range(text, c) || text.starts_with('/') || text.is_empty() && c == '/'
+            }),
+            remote_host: TextInput::new("localhost"),
+            remote_port: TextInput::default().with_filter(port_or_range),
+            bind_address: TextInput::default(),
+            label: TextInput::default(),
+            ephemeral: false,
             active_field: ModalField::LocalPort,
             error_message: None,
+            busy_port: None,
+            host_suggestions: Vec::new(),
+            suggestion: None,
+            presets: Vec::new(),
+            preset: None,
+        }
+    }
+
+    /// A modal pre-filled to forward `local_port` to `host:port`.
+    pub fn with_endpoint(host: String, port: u16, local_port: u16) -> Self {
+        let mut modal = Self::new();
+        modal.local_port.set(local_port.to_string());
+        modal.remote_host.set(host);
+        modal.remote_port.set(port.to_string());
+        modal
+    }
+
+    /// A modal copying `tunnel`, with its listening port moved to the next
+    /// one not in `taken` (and, for `-L`, free locally).
+    pub fn duplicate(tunnel: &Tunnel, taken: &[u16]) -> Self {
+        let mut modal = Self::new();
+        modal.direction = tunnel.direction;
+        modal.local_port.set(
+            tunnel
+                .local_socket
+                .clone()
+                .unwrap_or_else(|| tunnel.local_port.to_string()),
+        );
+        modal.remote_host.set(tunnel.remote_host.clone());
+        if tunnel.remote_port != 0 {
+            modal.remote_port.set(tunnel.remote_port.to_string());
+        }
+        modal
+            .bind_address
+            .set(tunnel.bind_address.clone().unwrap_or_default());
+        modal.label.set(tunnel.label.clone().unwrap_or_default());
+        modal.ephemeral = tunnel.ephemeral;
+        if tunnel.binds_locally() {
+            let start = tunnel.local_port.saturating_add(1);
+            if let Some(port) = crate::ssh::tunnel::find_free_port(start..=u16::MAX, taken) {
+                modal.local_port.set(port.to_string());
+            }
+        } else if tunnel.direction == TunnelDirection::Remote {
+            // The remote side can't be probed; only skip ports stm already uses
+            let start = tunnel.remote_port.saturating_add(1);
+            if let Some(port) = (start..=u16::MAX).find(|p| !taken.contains(p)) {
+                modal.remote_port.set(port.to_string());
+            }
+            modal.active_field = ModalField::RemotePort;
         }
+        modal
     }
 
     pub fn next_field(&mut self) {
-        self.active_field = match self.active_field {
-            ModalField::LocalPort => ModalField::RemoteHost,
-            ModalField::RemoteHost => ModalField::RemotePort,
-            ModalField::RemotePort => ModalField::LocalPort,
+        self.active_field = input::cycle(&ModalField::ALL, self.active_field, false);
+    }
+
+    pub fn previous_field(&mut self) {
+        self.active_field = input::cycle(&ModalField::ALL, self.active_field, true);
+    }
+
+    /// The text field with focus; the preset, direction and save fields have none.
+    fn active_input(&mut self) -> Option<&mut TextInput> {
+        match self.active_field {
+            ModalField::Preset | ModalField::Direction | ModalField::Save => None,
+            ModalField::LocalPort => Some(&mut self.local_port),
+            ModalField::RemoteHost => Some(&mut self.remote_host),
+            ModalField::RemotePort => Some(&mut self.remote_port),
+            ModalField::BindAddress => Some(&mut self.bind_address),
+            ModalField::Label => Some(&mut self.label),
+        }
+    }
+
+    /// Suggestions starting with `prefix`, other than `prefix` itself.
+    fn matching_hosts(&self, prefix: &str) -> Vec<&String> {
+        let prefix = prefix.to_lowercase();
+        self.host_suggestions
+            .iter()
+            .filter(|h| h.to_lowercase().starts_with(&prefix) && h.len() > prefix.len())
+            .collect()
+    }
+
+    /// Suggestions for the remote host as typed, for the ↑/↓ hint.
+    pub fn host_matches(&self) -> usize {
+        match self.suggestion {
+            Some((ref typed, _)) => self.matching_hosts(typed).len(),
+            None => self.matching_hosts(self.remote_host.text()).len(),
+        }
+    }
+
+    /// The preset last filled in, if any.
+    pub fn preset(&self) -> Option<&ServicePreset> {
+        self.presets.get(self.preset?)
+    }
+
+    /// Fill in the next (or previous) preset; going past either end picks
+    /// none and leaves the fields as they are.
+    fn cycle_preset(&mut self, delta: i32) {
+        if self.presets.is_empty() {
+            return;
+        }
+        // Positions 0..len are the presets, len is none
+        let count = self.presets.len() as i32 + 1;
+        let from = self.preset.map_or(count - 1, |i| i as i32);
+        let next = (from + delta).rem_euclid(count) as usize;
+        self.apply_preset((next < self.presets.len()).then_some(next));
+    }
+
+    /// Fill in the next preset whose name starts with `c`.
+    fn jump_to_preset(&mut self, c: char) {
+        let len = self.presets.len();
+        let start = self.preset.map_or(0, |i| i + 1);
+        let found = (start..start + len).map(|i| i % len).find(|&i| {
+            self.presets[i]
+                .name
+                .chars()
+                .next()
+                .is_some_and(|first| first.eq_ignore_ascii_case(&c))
+        });
+        if found.is_some() {
+            self.apply_preset(found);
+        }
+    }
+
+    fn apply_preset(&mut self, index: Option<usize>) {
+        let previous = self.preset().map(|p| p.name.clone());
+        self.preset = index;
+        let Some(preset) = self.preset().cloned() else {
+            return;
         };
+        // For -R the local side is the service itself
+        match (self.direction, preset.local_port) {
+            (TunnelDirection::Remote, _) => self.local_port.set(preset.remote_port.to_string()),
+            (TunnelDirection::Local, Some(port)) => self.local_port.set(port.to_string()),
+            (TunnelDirection::Local, None) => {}
+        }
+        self.remote_port.set(preset.remote_port.to_string());
+        if let Some(host) = preset.remote_host {
+            self.remote_host.set(host);
+        }
+        // Name the tunnel after the service unless the user named it
+        if self.label.is_empty() || previous.as_deref() == Some(self.label.text()) {
+            self.label.set(preset.name);
+        }
+    }
+
+    /// Fill the remote host with the next (or previous) suggestion matching
+    /// what was typed; going past either end brings the typed text back.
+    /// On the preset field, cycle the presets instead.
+    pub fn cycle_suggestion(&mut self, delta: i32) {
+        if self.active_field == ModalField::Preset {
+            self.cycle_preset(delta);
+            self.error_message = None;
+            return;
+        }
+        if self.active_field != ModalField::RemoteHost {
+            return;
+        }
+        let (typed, current) = match self.suggestion.take() {
+            Some((typed, i)) => (typed, Some(i)),
+            None => (self.remote_host.text().to_string(), None),
+        };
+        let matches = self.matching_hosts(&typed);
+        if matches.is_empty() {
+            return;
+        }
+        // Positions 0..len are the matches, len is the typed text
+        let count = matches.len() as i32 + 1;
+        let from = current.map_or(count - 1, |i| i as i32);
+        let next = (from + delta).rem_euclid(count) as usize;
+        match matches.get(next).map(|host| host.to_string()) {
+            Some(host) => {
+                self.remote_host.set(host);
+                self.suggestion = Some((typed, next));
+            }
+            None => self.remote_host.set(typed),
+        }
+        self.error_message = None;
     }
 
     pub fn input(&mut self, c: char) {
-        match self.active_field {
-            ModalField::LocalPort => {
-                if c.is_ascii_digit() {
-                    self.local_port.push(c);
+        self.suggestion = None;
+        if let Some(field) = self.active_input() {
+            field.insert(c);
+        } else if self.active_field == ModalField::Preset {
+            match c {
+                ' ' => self.cycle_preset(1),
+                // Typing a port goes straight to the local port field
+                '0'..='9' | '/' => {
+                    self.active_field = ModalField::LocalPort;
+                    self.local_port.insert(c);
                 }
+                c => self.jump_to_preset(c),
             }
-            ModalField::RemoteHost => {
-                self.remote_host.push(c);
-            }
-            ModalField::RemotePort => {
-                if c.is_ascii_digit() {
-                    self.remote_port.push(c);
+        } else if self.active_field == ModalField::Direction {
+            match c.to_ascii_lowercase() {
+                'l' => self.direction = TunnelDirection::Local,
+                'r' => self.direction = TunnelDirection::Remote,
+                ' ' => {
+                    self.direction = match self.direction {
+                        TunnelDirection::Local => TunnelDirection::Remote,
+                        TunnelDirection::Remote => TunnelDirection::Local,
+                    }
                 }
+                _ => {}
+            }
+        } else {
+            match c.to_ascii_lowercase() {
+                'y' => self.ephemeral = false,
+                'n' => self.ephemeral = true,
+                ' ' => self.ephemeral = !self.ephemeral,
+                _ => {}
             }
         }
         self.error_message = None;
     }
 
     pub fn backspace(&mut self) {
-        match self.active_field {
-            ModalField::LocalPort => {
-                self.local_port.pop();
-            }
-            ModalField::RemoteHost => {
-                self.remote_host.pop();
+        self.suggestion = None;
+        if let Some(field) = self.active_input() {
+            field.backspace();
+        }
+        self.error_message = None;
+    }
+
+    pub fn edit(&mut self, key: EditKey) {
+        self.suggestion = None;
+        if let Some(field) = self.active_input() {
+            field.edit(key);
+        } else if self.active_field == ModalField::Preset {
+            match key {
+                EditKey::Left => self.cycle_preset(-1),
+                EditKey::Right => self.cycle_preset(1),
+                _ => {}
             }
-            ModalField::RemotePort => {
-                self.remote_port.pop();
+        }
+    }
+
+    /// The Unix socket path typed as the local side, if any.
+    pub fn local_socket(&self) -> Option<String> {
+        let text = self.local_port.text();
+        text.starts_with('/').then(|| text.to_string())
+    }
+
+    /// The bind address typed in, if any.
+    pub fn bind_address(&self) -> Option<String> {
+        let bind = self.bind_address.text().trim();
+        (!bind.is_empty()).then(|| bind.to_string())
+    }
+
+    /// True if the typed -R bind address only works with `GatewayPorts`.
+    pub fn needs_gateway_ports(&self) -> bool {
+        let mut tunnel = Tunnel::new(0, String::new(), 0);
+        tunnel.direction = self.direction;
+        tunnel.bind_address = self.bind_address();
+        tunnel.wants_gateway_ports()
+    }
+
+    /// The label typed in, if any.
+    pub fn label(&self) -> Option<String> {
+        let label = self.label.text().trim();
+        (!label.is_empty()).then(|| label.to_string())
+    }
+
+    /// Check the fields and return the `(local port, host, remote port)` of
+    /// each tunnel to add: one, or one per port when a range was typed.
+    pub fn validate(&mut self) -> Option<Vec<(u16, String, u16)>> {
+        match self.check() {
+            Ok(tunnels) => Some(tunnels),
+            Err(message) => {
+                self.error_message = Some(message);
+                None
             }
         }
-        self.error_message = None;
     }
 
-    pub fn validate(&mut self) -> Option<(u16, String, u16)> {
-        let local_port: u16 = match self.local_port.parse() {
-            Ok(p) if p > 0 => p,
-            _ => {
-                self.error_message = Some("Invalid local port".to_string());
-                return None;
+    fn check(&mut self) -> Result<Vec<(u16, String, u16)>, String> {
+        // With a socket path as the host, the destination's port is unused
+        let socket = crate::ssh::tunnel::is_socket_path(self.remote_host.text());
+        let remote = self.direction == TunnelDirection::Remote;
+        self.busy_port = None;
+
+        if let Some(path) = self.local_socket() {
+            if remote {
+                return Err("-R tunnels need a local port, not a socket".to_string());
             }
-        };
+            if let Some(problem) = crate::ssh::tunnel::socket_path_problem(&path) {
+                return Err(problem);
+            }
+        }
 
         if self.remote_host.is_empty() {
-            self.error_message = Some("Remote host cannot be empty".to_string());
-            return None;
+            return Err("Remote host cannot be empty".to_string());
+        }
+        let Some(remote_host) = crate::ssh::tunnel::normalize_remote_host(self.remote_host.text())
+        else {
+            return Err(format!("Invalid remote host '{}'", self.remote_host.text()));
+        };
+
+        let bind = self.bind_address();
+        if let Some(ref bind) = bind {
+            if !crate::ssh::tunnel::is_valid_bind_address(bind) {
+                return Err(format!("Invalid bind address '{bind}'"));
+            }
         }
 
-        let remote_port: u16 = match self.remote_port.parse() {
-            Ok(p) if p > 0 => p,
-            _ => {
-                self.error_message = Some("Invalid remote port".to_string());
-                return None;
+        let ports = if self.is_range() {
+            if socket || self.local_socket().is_some() {
+                return Err("Port ranges can't be used with sockets".to_string());
             }
+            self.range_ports()?
+        } else {
+            vec![(
+                self.local_port(socket, remote)?,
+                self.remote_port(socket, remote)?,
+            )]
         };
 
-        if !crate::ssh::tunnel::is_port_available(local_port) {
-            self.error_message = Some(format!("Port {local_port} is already in use"));
-            return None;
+        // -R forwards connect to the local port rather than listen on it
+        if self.direction == TunnelDirection::Local && self.local_socket().is_none() {
+            if let Some(&(port, _)) = ports
+                .iter()
+                .find(|&&(port, _)| !crate::ssh::tunnel::is_bind_available(bind.as_deref(), port))
+            {
+                self.busy_port = Some(port);
+                return Err(format!("Port {port} is already in use"));
+            }
         }
 
-        Some((local_port, self.remote_host.clone(), remote_port))
+        Ok(ports
+            .into_iter()
+            .map(|(local, remote)| (local, remote_host.clone(), remote))
+            .collect())
+    }
+
+    /// True if either port field holds a range like `8000-8005`.
+    pub fn is_range(&self) -> bool {
+        self.local_socket().is_none()
+            && (self.local_port.text().contains('-') || self.remote_port.text().contains('-'))
+    }
+
+    /// Pair up a local and a remote port range of the same length; a blank
+    /// side uses the other side's ports.
+    fn range_ports(&self) -> Result<Vec<(u16, u16)>, String> {
+        let parse = |field: &TextInput, side: &str| {
+            if field.is_empty() {
+                return Ok(None);
+            }
+            crate::ssh::tunnel::parse_port_range(field.text())
+                .map(Some)
+                .ok_or_else(|| format!("Invalid {side} port range"))
+        };
+        let (local, remote) = match (
+            parse(&self.local_port, "local")?,
+            parse(&self.remote_port, "remote")?,
+        ) {
+            (Some(local), Some(remote)) => (local, remote),
+            (Some(local), None) => (local.clone(), local),
+            (None, Some(remote)) => (remote.clone(), remote),
+            (None, None) => return Err("Invalid local port".to_string()),
+        };
+        if local.len() != remote.len() {
+            return Err(format!(
+                "Ranges differ in length ({} local, {} remote ports)",
+                local.len(),
+                remote.len()
+            ));
+        }
+        if local.len() > crate::ssh::tunnel::MAX_PORT_RANGE {
+            return Err(format!(
+                "At most {} ports per range",
+                crate::ssh::tunnel::MAX_PORT_RANGE
+            ));
+        }
+        Ok(local.zip(remote).collect())
+    }
+
+    fn local_port(&mut self, socket: bool, remote: bool) -> Result<u16, String> {
+        match self.local_port.text().parse() {
+            _ if socket && remote => Ok(0),
+            _ if self.local_socket().is_some() => Ok(0),
+            Ok(p) if p > 0 => Ok(p),
+            // Left blank on a -L tunnel: let the OS pick one
+            Err(_) if self.local_port.is_empty() && !remote => {
+                let port = crate::ssh::tunnel::os_free_port(self.bind_address().as_deref())
+                    .ok_or_else(|| "No free local port".to_string())?;
+                self.local_port.set(port.to_string());
+                Ok(port)
+            }
+            _ => Err("Invalid local port".to_string()),
+        }
+    }
+
+    fn remote_port(&self, socket: bool, remote: bool) -> Result<u16, String> {
+        match self.remote_port.text().parse() {
+            _ if socket && !remote => Ok(0),
+            Ok(p) if p > 0 => Ok(p),
+            _ => Err("Invalid remote port".to_string()),
+        }
     }
 }
 
-pub fn render(frame: &mut Frame, state: &AddModalState) {
+/// Port fields take digits and one `-` between the ends of a range.
+fn port_or_range(text: &str, c: char) -> bool {
+    c.is_ascii_digit() || c == '-' && !text.is_empty() && !text.contains('-')
+}
+
+pub fn render(frame: &mut Frame, state: &AddModalState, masker: &Masker) {
     let area = frame.area();
 
     let [modal_area] = Layout::horizontal([Constraint::Percentage(50)])
         .flex(Flex::Center)
         .areas(area);
-    let [modal_area] = Layout::vertical([Constraint::Length(12)])
+    let [modal_area] = Layout::vertical([Constraint::Length(22)])
         .flex(Flex::Center)
         .areas(modal_area);
 
     frame.render_widget(Clear, modal_area);
 
     let block = Block::default()
-        .title(" Add Tunnel (-L) ")
+        .title(format!(" Add Tunnel ({}) ", state.direction.flag()))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(theme::BORDER_FOCUSED));
 
     let inner = block.inner(modal_area);
     frame.render_widget(block, modal_area);
 
-    let [_, field1, _, field2, _, field3, _, error_area, _] = Layout::vertical([
-        Constraint::Length(1), // padding
-        Constraint::Length(1), // local port
-        Constraint::Length(1), // spacing
-        Constraint::Length(1), // remote host
-        Constraint::Length(1), // spacing
-        Constraint::Length(1), // remote port
-        Constraint::Length(1), // spacing
-        Constraint::Length(1), // error message
-        Constraint::Min(0),    // remaining
-    ])
-    .areas(inner);
-
-    render_field(
+    let [_, preset_area, _, field0, _, field1, _, field2, _, field3, _, field4, _, field5, _, field6, _, error_area, _] =
+        Layout::vertical([
+            Constraint::Length(1), // padding
+            Constraint::Length(1), // preset
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // direction
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // local port
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // remote host
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // remote port
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // bind address
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // label
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // save
+            Constraint::Length(1), // spacing
+            Constraint::Length(1), // error message
+            Constraint::Min(0),    // remaining
+        ])
+        .areas(inner);
+
+    let direction = match state.direction {
+        TunnelDirection::Local => "-L local (l/r/space)",
+        TunnelDirection::Remote => "-R remote (l/r/space)",
+    };
+    let active = |field| state.active_field == field;
+    // The field being typed in stays readable; the rest go through the masker
+    let masked = |input: &TextInput, field, placeholder| {
+        if active(field) || input.is_empty() {
+            input.spans(active(field), placeholder)
+        } else {
+            vec![Span::styled(
+                masker.mask(input.text()),
+                Style::default().fg(theme::TEXT_PRIMARY),
+            )]
+        }
+    };
+    let mut preset = match state.preset() {
+        Some(preset) => {
+            let host = preset
+                .remote_host
+                .as_deref()
+                .map(|h| format!("{h}:"))
+                .unwrap_or_default();
+            let local = preset
+                .local_port
+                .map(|p| format!("{p} → "))
+                .unwrap_or_default();
+            vec![Span::styled(
+                masker.mask(&format!(
+                    "{}  {local}{host}{}",
+                    preset.name, preset.remote_port
+                )),
+                input::value_style(active(ModalField::Preset)),
+            )]
+        }
+        None => vec![Span::styled("none", Style::default().fg(theme::TEXT_DIM))],
+    };
+    if active(ModalField::Preset) {
+        preset.push(Span::styled(
+            "█",
+            Style::default().fg(theme::BORDER_FOCUSED),
+        ));
+        preset.push(Span::styled(
+            "  ←→ or first letter",
+            Style::default().fg(theme::TEXT_DIM),
+        ));
+    }
+    input::render_field(frame, preset_area, "Preset:", preset);
+    let mut direction = vec![Span::styled(
+        direction,
+        input::value_style(active(ModalField::Direction)),
+    )];
+    if active(ModalField::Direction) {
+        direction.push(Span::styled(
+            "█",
+            Style::default().fg(theme::BORDER_FOCUSED),
+        ));
+    }
+    input::render_field(frame, field0, "Direction:", direction);
+    let local_placeholder = match state.direction {
+        TunnelDirection::Local => "any free port",
+        TunnelDirection::Remote => "",
+    };
+    input::render_field(
         frame,
         field1,
         "Local Port:",
-        &state.local_port,
-        state.active_field == ModalField::LocalPort,
-    );
-    render_field(
-        frame,
-        field2,
-        "Remote Host:",
-        &state.remote_host,
-        state.active_field == ModalField::RemoteHost,
+        state
+            .local_port
+            .spans(active(ModalField::LocalPort), local_placeholder),
     );
-    render_field(
+    // For -R the host is reached from this machine
+    let host_label = match state.direction {
+        TunnelDirection::Local => "Remote Host:",
+        TunnelDirection::Remote => "Local Host:",
+    };
+    let mut host = masked(&state.remote_host, ModalField::RemoteHost, "");
+    let matches = state.host_matches();
+    if active(ModalField::RemoteHost) && matches > 0 {
+        host.push(Span::styled(
+            format!("  ↑↓ {matches} used before"),
+            Style::default().fg(theme::TEXT_DIM),
+        ));
+    }
+    input::render_field(frame, field2, host_label, host);
+    input::render_field(
         frame,
         field3,
         "Remote Port:",
-        &state.remote_port,
-        state.active_field == ModalField::RemotePort,
+        state.remote_port.spans(active(ModalField::RemotePort), ""),
+    );
+    input::render_field(
+        frame,
+        field4,
+        "Bind Address:",
+        masked(&state.bind_address, ModalField::BindAddress, "loopback"),
+    );
+    input::render_field(
+        frame,
+        field5,
+        "Label:",
+        masked(&state.label, ModalField::Label, "optional"),
     );
+    let save = if state.ephemeral {
+        "no, this connection only (y/n/space)"
+    } else {
+        "yes, to history (y/n/space)"
+    };
+    let mut save = vec![Span::styled(
+        save,
+        input::value_style(active(ModalField::Save)),
+    )];
+    if active(ModalField::Save) {
+        save.push(Span::styled(
+            "█",
+            Style::default().fg(theme::BORDER_FOCUSED),
+        ));
+    }
+    input::render_field(frame, field6, "Save:", save);
 
     if let Some(ref error) = state.error_message {
-        let err_line =
-            Line::from(Span::styled(error, Style::default().fg(theme::ERROR_COLOR))).centered();
+        let err_line = Line::from(Span::styled(
+            masker.mask(error),
+            Style::default().fg(theme::ERROR_COLOR),
+        ))
+        .centered();
         frame.render_widget(Paragraph::new(err_line), error_area);
+    } else if state.needs_gateway_ports() {
+        let hint = Line::from(Span::styled(
+            "Needs GatewayPorts yes/clientspecified on the server",
+            Style::default().fg(theme::WARNING),
+        ))
+        .centered();
+        frame.render_widget(Paragraph::new(hint), error_area);
     }
 }
 
-fn render_field(frame: &mut Frame, area: Rect, label: &str, value: &str, active: bool) {
-    let label_style = Style::default().fg(theme::TEXT_DIM);
-    let value_style = if active {
-        Style::default()
-            .fg(theme::HIGHLIGHT_FG)
-            .add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(theme::TEXT_PRIMARY)
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let cursor = if active { "█" } else { "" };
+    /// A -R modal, so validation doesn't depend on which local ports are free.
+    fn modal(local: &str, remote: &str) -> AddModalState {
+        let mut modal = AddModalState::new();
+        modal.direction = TunnelDirection::Remote;
+        modal.local_port.set(local);
+        modal.remote_port.set(remote);
+        modal
+    }
+
+    fn ports(modal: &mut AddModalState) -> Vec<(u16, u16)> {
+        modal
+            .validate()
+            .unwrap()
+            .into_iter()
+            .map(|(local, _, remote)| (local, remote))
+            .collect()
+    }
 
-    let line = Line::from(vec![
-        Span::styled(format!(" {label:<14}"), label_style),
-        Span::styled(value, value_style),
-        Span::styled(cursor, Style::default().fg(theme::BORDER_FOCUSED)),
-    ]);
+    #[test]
+    fn test_single_ports() {
+        let mut single = modal("8080", "80");
+        assert!(!single.is_range());
+        assert_eq!(ports(&mut single), [(8080, 80)]);
+    }
 
-    frame.render_widget(Paragraph::new(line), area);
+    #[test]
+    fn test_port_ranges() {
+        let mut both = modal("8000-8002", "9000-9002");
+        assert!(both.is_range());
+        assert_eq!(ports(&mut both), [(8000, 9000), (8001, 9001), (8002, 9002)]);
+
+        // A blank side takes the other side's ports
+        assert_eq!(
+            ports(&mut modal("", "5000-5001")),
+            [(5000, 5000), (5001, 5001)]
+        );
+        assert_eq!(
+            ports(&mut modal("5000-5001", "")),
+            [(5000, 5000), (5001, 5001)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_port_ranges() {
+        let mut mismatched = modal("8000-8002", "9000-9001");
+        assert!(mismatched.validate().is_none());
+        assert_eq!(
+            mismatched.error_message.as_deref(),
+            Some("Ranges differ in length (3 local, 2 remote ports)")
+        );
+
+        let mut single_and_range = modal("8000", "9000-9001");
+        assert!(single_and_range.validate().is_none());
+
+        let mut backwards = modal("8002-8000", "");
+        assert!(backwards.validate().is_none());
+        assert_eq!(
+            backwards.error_message.as_deref(),
+            Some("Invalid local port range")
+        );
+
+        let max = crate::ssh::tunnel::MAX_PORT_RANGE as u16;
+        assert_eq!(
+            ports(&mut modal(&format!("10000-{}", 10000 + max - 1), "")).len(),
+            usize::from(max)
+        );
+        let mut too_long = modal(&format!("10000-{}", 10000 + max), "");
+        assert!(too_long.validate().is_none());
+        assert_eq!(
+            too_long.error_message,
+            Some(format!("At most {max} ports per range"))
+        );
+    }
 }
@@ -0,0 +1,152 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::ssh::tunnel::Tunnel;
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// A port the master listens on that no enabled tunnel accounts for.
+#[derive(Debug, Clone)]
+pub struct Orphan {
+    pub port: u16,
+    /// A known tunnel on the same local port, disabled or only saved; its
+    /// destination is a guess at the forward's.
+    pub tunnel: Option<Tunnel>,
+}
+
+/// Forwards found on the live master but missing from stm's list.
+#[derive(Debug, Clone)]
+pub struct ForwardAuditState {
+    pub orphans: Vec<Orphan>,
+    pub selected: usize,
+}
+
+impl ForwardAuditState {
+    /// Orphans are the `listening` ports not in `expected`, each matched to
+    /// the first of `known` with that local port.
+    pub fn new(listening: &[u16], expected: &[u16], known: &[Tunnel]) -> Self {
+        let orphans = listening
+            .iter()
+            .filter(|port| !expected.contains(port))
+            .map(|&port| Orphan {
+                port,
+                tunnel: known
+                    .iter()
+                    .find(|t| t.binds_locally() && t.local_port == port)
+                    .cloned(),
+            })
+            .collect();
+        Self {
+            orphans,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.orphans.len().saturating_sub(1));
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Take the selected orphan off the list once it is dealt with.
+    pub fn take_selected(&mut self) -> Option<Orphan> {
+        if self.selected >= self.orphans.len() {
+            return None;
+        }
+        let orphan = self.orphans.remove(self.selected);
+        self.selected = self.selected.min(self.orphans.len().saturating_sub(1));
+        Some(orphan)
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &ForwardAuditState, host_name: &str, masker: &Masker) {
+    let area = frame.area();
+
+    let height = (state.orphans.len() as u16 + 3).min(area.height.saturating_sub(2));
+    let [modal_area] = Layout::horizontal([Constraint::Length(60)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = state
+        .orphans
+        .iter()
+        .map(|orphan| {
+            let spans = match orphan.tunnel {
+                Some(ref tunnel) => {
+                    let mut spans = vec![Span::raw(" ")];
+                    if let Some(ref label) = tunnel.label {
+                        spans.push(Span::styled(
+                            format!("{} ", masker.mask(label)),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ));
+                    }
+                    spans.push(Span::raw(masker.mask(&tunnel.describe())));
+                    spans
+                }
+                None => vec![
+                    Span::raw(format!(" {} → ", orphan.port)),
+                    Span::styled("destination unknown", Style::default().fg(theme::TEXT_DIM)),
+                ],
+            };
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!(" Forwards on {host_name} not in stm "))
+                .title_bottom(
+                    Line::from(" a adopt · c cancel · Esc close ")
+                        .style(Style::default().fg(theme::TEXT_DIM)),
+                )
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphans_matched_to_known_tunnels() {
+        let known = vec![Tunnel::new(5432, "db".to_string(), 5432)];
+        let mut state = ForwardAuditState::new(&[5432, 6379, 8080], &[6379], &known);
+        assert_eq!(state.orphans.len(), 2);
+        assert_eq!(
+            state.orphans[0]
+                .tunnel
+                .as_ref()
+                .map(|t| t.remote_host.as_str()),
+            Some("db")
+        );
+        assert!(state.orphans[1].tunnel.is_none());
+
+        state.next();
+        assert_eq!(state.take_selected().map(|o| o.port), Some(8080));
+        assert_eq!(state.selected, 0);
+        assert_eq!(state.take_selected().map(|o| o.port), Some(5432));
+        assert!(state.take_selected().is_none());
+    }
+}
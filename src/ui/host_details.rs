@@ -0,0 +1,218 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::ssh::certificate::CertStatus;
+use crate::ssh::config::SshHost;
+use crate::ssh::health::MuxStats;
+use crate::state::history::{HostEventKind, HostHistory};
+use crate::state::reachability::Reachability;
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+#[derive(Debug, Clone)]
+pub struct HostDetailsState {
+    /// Index into `App::hosts`.
+    pub host_idx: usize,
+    /// Certificate check result, if the host authenticates with one.
+    pub certificate: Option<CertStatus>,
+}
+
+impl HostDetailsState {
+    pub fn new(host: &SshHost, host_idx: usize) -> Self {
+        Self {
+            host_idx,
+            certificate: host
+                .certificate_path()
+                .map(|path| crate::ssh::certificate::certificate_status(&path)),
+        }
+    }
+}
+
+/// Timeline and activity entries shown in the details view.
+const TIMELINE_ROWS: usize = 10;
+
+pub fn render(
+    frame: &mut Frame,
+    state: &HostDetailsState,
+    host: &SshHost,
+    history: Option<&HostHistory>,
+    reachability: Option<&Reachability>,
+    mux: Option<MuxStats>,
+    masker: &Masker,
+) {
+    let area = frame.area();
+
+    let [modal_area] = Layout::horizontal([Constraint::Percentage(60)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Percentage(70)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let unset = "-".to_string();
+    let mut fields = vec![
+        ("Alias", host.name.clone()),
+        ("HostName", masker.mask(host.effective_hostname())),
+        (
+            "User",
+            host.user
+                .as_deref()
+                .map_or_else(|| unset.clone(), |u| masker.hide(u)),
+        ),
+        ("Port", host.effective_port().to_string()),
+        (
+            "IdentityFile",
+            host.identity_file
+                .as_ref()
+                .map(|p| masker.hide(&p.display().to_string()))
+                .unwrap_or_else(|| unset.clone()),
+        ),
+        (
+            "ProxyJump",
+            if host.proxy_jump.is_empty() {
+                unset.clone()
+            } else {
+                // Chain visualization: every hop, then the target itself
+                let mut hops: Vec<String> = host.proxy_jump.iter().map(|h| h.to_string()).collect();
+                hops.push(host.display_target());
+                masker.mask(&hops.join(" → "))
+            },
+        ),
+    ];
+    if let Some(ref command) = host.proxy_command {
+        fields.push(("ProxyCommand", masker.hide(command)));
+    }
+    if let Some(ref source) = host.source {
+        fields.push(("Config file", masker.hide(&source.display().to_string())));
+    }
+    if let Some(ref command) = host.remote_command {
+        // Only used by interactive sessions; the tunnel master overrides it
+        let command = masker.mask(command);
+        fields.push(("RemoteCommand", format!("{command} (not run by stm)")));
+    }
+
+    if let Some(r) = reachability {
+        let state = if r.reachable {
+            "reachable"
+        } else {
+            "unreachable"
+        };
+        let address = r
+            .address
+            .as_deref()
+            .map_or_else(|| "no DNS answer".to_string(), |a| masker.hide(a));
+        fields.push((
+            "Reachability",
+            format!(
+                "{state} ({address}) at {}",
+                r.checked_at.format("%Y-%m-%d %H:%M UTC")
+            ),
+        ));
+    }
+
+    match history {
+        Some(h) => {
+            fields.push((
+                "Last used",
+                h.last_used.format("%Y-%m-%d %H:%M UTC").to_string(),
+            ));
+            fields.push(("Connections", h.use_count.to_string()));
+            let drops = h.drops_within(24);
+            if drops > 0 {
+                fields.push(("Dropped", format!("{drops} time(s) in the last 24h")));
+            }
+            fields.push(("Saved tunnels", h.tunnels.len().to_string()));
+        }
+        None => fields.push(("Last used", "never".to_string())),
+    }
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let dim = Style::default().fg(theme::TEXT_DIM);
+
+    let mut lines = vec![Line::from("")];
+    lines.extend(fields.into_iter().map(|(label, value)| {
+        Line::from(vec![
+            Span::styled(format!("  {label:<14}"), bold),
+            Span::styled(value, dim),
+        ])
+    }));
+
+    if let Some(ref cert) = state.certificate {
+        let style = if cert.is_warning() {
+            Style::default().fg(theme::ERROR_COLOR)
+        } else {
+            dim
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<14}", "Certificate"), bold),
+            Span::styled(cert.describe(), style),
+        ]));
+    }
+
+    if let Some(stats) = mux {
+        let style = if stats.near_limit() {
+            Style::default().fg(theme::ERROR_COLOR)
+        } else {
+            dim
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<14}", "Multiplexing"), bold),
+            Span::styled(stats.to_string(), style),
+        ]));
+    }
+
+    // Most recent tunnel-set changes, newest first
+    if let Some(h) = history.filter(|h| !h.timeline.is_empty()) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("  Tunnel history", bold)));
+        for change in h.timeline.iter().rev().take(TIMELINE_ROWS) {
+            let (sign, color) = if change.added {
+                ("+", theme::CONNECTED)
+            } else {
+                ("-", theme::ERROR_COLOR)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {}  ", change.at.format("%Y-%m-%d %H:%M")), dim),
+                Span::styled(
+                    format!("{sign} {}", masker.mask(&change.spec)),
+                    Style::default().fg(color),
+                ),
+            ]));
+        }
+    }
+
+    // Connections and tunnel activity, newest first
+    if let Some(h) = history.filter(|h| !h.events.is_empty()) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("  Recent activity", bold)));
+        for event in h.events.iter().rev().take(TIMELINE_ROWS) {
+            let color = match event.kind {
+                HostEventKind::Connected | HostEventKind::TunnelEnabled => theme::CONNECTED,
+                HostEventKind::Disconnected => theme::TEXT_PRIMARY,
+                HostEventKind::ConnectionLost
+                | HostEventKind::ConnectionFailed
+                | HostEventKind::TunnelFailed => theme::ERROR_COLOR,
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {}  ", event.at.format("%Y-%m-%d %H:%M")), dim),
+                Span::styled(masker.mask(&event.message), Style::default().fg(color)),
+            ]));
+        }
+    }
+
+    let details = Paragraph::new(lines).block(
+        Block::default()
+            .title(format!(" {} ", host.name))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+    );
+
+    frame.render_widget(details, modal_area);
+}
@@ -7,6 +7,8 @@ use ratatui::{
 };
 
 use crate::app::{App, ConnectionStatus, Panel};
+use crate::ssh::config::X11Forwarding;
+use crate::state::persistence::{HostColumn, HostField, HostSort};
 use crate::ui::theme;
 
 pub fn render(frame: &mut Frame, area: Rect, app: &mut App) {
@@ -17,8 +19,11 @@ pub fn render(frame: &mut Frame, area: Rect, app: &mut App) {
         theme::BORDER_UNFOCUSED
     };
 
+    let by_latency = app.host_sort == HostSort::Latency;
     let title = if app.search_mode {
         format!(" Hosts [/{}] ", app.search_query)
+    } else if by_latency {
+        format!(" Hosts ({}) · by latency ", app.filtered_host_indices.len())
     } else {
         format!(" Hosts ({}) ", app.filtered_host_indices.len())
     };
@@ -30,7 +35,7 @@ pub fn render(frame: &mut Frame, area: Rect, app: &mut App) {
 
     if app.filtered_host_indices.is_empty() {
         let msg = if app.hosts.is_empty() {
-            "No SSH hosts found in ~/.ssh/config"
+            "No SSH hosts found in the ssh config"
         } else {
             "No matching hosts"
         };
@@ -41,11 +46,14 @@ pub fn render(frame: &mut Frame, area: Rect, app: &mut App) {
     }
 
     let connected_name = app.connected_host_name().map(|s| s.to_string());
+    // Which file a host came from only matters with several
+    let badges = app.ssh_config_paths.len() > 1;
 
     let items: Vec<ListItem> = app
         .filtered_host_indices
         .iter()
-        .map(|&idx| {
+        .enumerate()
+        .map(|(row, &idx)| {
             let host = &app.hosts[idx];
             let is_connected = connected_name.as_deref() == Some(&host.name);
             let is_connecting = matches!(&app.connection_status, ConnectionStatus::Connecting)
@@ -54,28 +62,101 @@ pub fn render(frame: &mut Frame, area: Rect, app: &mut App) {
                     .as_ref()
                     .is_some_and(|c| c.host().name == host.name);
 
-            let (dot, dot_color) = if is_connected {
-                ("● ", theme::CONNECTED)
+            let (dot, dot_color) = if host.is_pattern() {
+                ("◇", theme::TEXT_DIM)
+            } else if is_connected {
+                ("●", theme::CONNECTED)
             } else if is_connecting {
-                ("◌ ", theme::HIGHLIGHT_FG)
+                ("◌", theme::HIGHLIGHT_FG)
             } else {
-                ("○ ", theme::DISCONNECTED)
+                // Last-known reachability; dimmed once older than the TTL
+                let ttl = app.config.general.reachability_ttl_secs;
+                match app.reachability.get(&host.name) {
+                    Some(r) if r.is_stale(ttl, chrono::Utc::now()) => {
+                        (if r.reachable { "○" } else { "✕" }, theme::TEXT_DIM)
+                    }
+                    Some(r) if r.reachable => ("○", theme::CONNECTED),
+                    Some(_) => ("✕", theme::ERROR_COLOR),
+                    None => ("○", theme::DISCONNECTED),
+                }
             };
 
-            let name_span = Span::styled(
-                &host.name,
-                Style::default()
-                    .fg(theme::TEXT_PRIMARY)
-                    .add_modifier(Modifier::BOLD),
-            );
-            let detail = format!("  {}", host.display_target());
-            let detail_span = Span::styled(detail, Style::default().fg(theme::TEXT_DIM));
-
-            ListItem::new(Line::from(vec![
-                Span::styled(dot, Style::default().fg(dot_color)),
-                name_span,
-                detail_span,
-            ]))
+            let dim = Style::default().fg(theme::TEXT_DIM);
+            let cell = |field| match field {
+                HostField::Dot => Span::styled(dot, Style::default().fg(dot_color)),
+                HostField::Alias => Span::styled(
+                    host.name.clone(),
+                    Style::default()
+                        .fg(theme::TEXT_PRIMARY)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                // Safe mode shows aliases only
+                HostField::Target if app.safe_mode => Span::raw(""),
+                HostField::Target => Span::styled(host.display_target(), dim),
+                HostField::LastUsed => Span::styled(
+                    app.history
+                        .hosts
+                        .get(&host.name)
+                        .map(|h| format_ago(chrono::Utc::now() - h.last_used))
+                        .unwrap_or_default(),
+                    dim,
+                ),
+                HostField::Tags => Span::styled(
+                    app.config
+                        .hosts
+                        .get(&host.name)
+                        .map(|c| c.tags.join(","))
+                        .unwrap_or_default(),
+                    Style::default().fg(theme::INFO),
+                ),
+                HostField::Tunnels => {
+                    let text = if is_connected {
+                        let on = app.tunnels.iter().filter(|t| t.enabled).count();
+                        format!("⇄{on}/{}", app.tunnels.len())
+                    } else {
+                        match app.history.hosts.get(&host.name).map(|h| h.tunnels.len()) {
+                            Some(saved @ 1..) => format!("⇄{saved}"),
+                            _ => String::new(),
+                        }
+                    };
+                    Span::styled(text, dim)
+                }
+            };
+
+            // Quick-select digit for the first nine rows
+            let index = if row < 9 {
+                format!("{} ", row + 1)
+            } else {
+                "  ".to_string()
+            };
+
+            let mut spans = vec![Span::styled(index, dim)];
+            spans.extend(layout_row(&app.config.ui.host_columns, cell));
+            if let Some(rtt) = app.reachability.rtt_ms(&host.name).filter(|_| by_latency) {
+                spans.push(Span::styled(
+                    format!("  {rtt}ms"),
+                    Style::default().fg(theme::TEXT_DIM),
+                ));
+            }
+            if let Some(source) = host.source.as_deref().filter(|_| badges) {
+                spans.push(Span::styled(
+                    format!(" [{}]", crate::ssh::config::source_badge(source)),
+                    Style::default().fg(theme::INFO),
+                ));
+            }
+
+            // Forwarding badges: security-relevant, so always visible
+            let warning = Style::default().fg(theme::WARNING);
+            if host.forward_agent {
+                spans.push(Span::styled(" [A]", warning));
+            }
+            match host.forward_x11 {
+                X11Forwarding::Off => {}
+                X11Forwarding::Untrusted => spans.push(Span::styled(" [X]", warning)),
+                X11Forwarding::Trusted => spans.push(Span::styled(" [Y]", warning)),
+            }
+
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
@@ -90,3 +171,43 @@ pub fn render(frame: &mut Frame, area: Rect, app: &mut App) {
 
     frame.render_stateful_widget(list, area, &mut app.host_list_state);
 }
+
+/// Lay out a row's cells in the configured columns: one space after the dot,
+/// two between the others. Columns with a width are padded or cut with `…`;
+/// empty ones without are left out.
+fn layout_row<'a>(columns: &[HostColumn], cell: impl Fn(HostField) -> Span<'a>) -> Vec<Span<'a>> {
+    let mut spans = Vec::new();
+    let mut previous = None;
+    for column in columns {
+        let mut span = cell(column.field);
+        if let Some(width) = column.width.map(usize::from) {
+            let text: String = if span.content.chars().count() > width {
+                let cut: String = span.content.chars().take(width.saturating_sub(1)).collect();
+                format!("{cut}…")
+            } else {
+                format!("{:<width$}", span.content)
+            };
+            span.content = text.into();
+        } else if span.content.is_empty() {
+            continue;
+        }
+        match previous {
+            None => {}
+            Some(HostField::Dot) => spans.push(Span::raw(" ")),
+            Some(_) => spans.push(Span::raw("  ")),
+        }
+        spans.push(span);
+        previous = Some(column.field);
+    }
+    spans
+}
+
+/// Time since something happened, e.g. `just now`, `5m ago`, `3d ago`.
+fn format_ago(elapsed: chrono::Duration) -> String {
+    match elapsed.num_minutes() {
+        ..1 => "just now".to_string(),
+        mins @ ..60 => format!("{mins}m ago"),
+        mins @ ..1440 => format!("{}h ago", mins / 60),
+        mins => format!("{}d ago", mins / 1440),
+    }
+}
@@ -0,0 +1,100 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::ui::theme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostMenuItem {
+    Connect,
+    ConnectWithTunnels,
+    ConnectAs,
+    CopySshCommand,
+    ShowDetails,
+}
+
+impl HostMenuItem {
+    pub const ALL: [HostMenuItem; 5] = [
+        HostMenuItem::Connect,
+        HostMenuItem::ConnectWithTunnels,
+        HostMenuItem::ConnectAs,
+        HostMenuItem::CopySshCommand,
+        HostMenuItem::ShowDetails,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HostMenuItem::Connect => "Connect",
+            HostMenuItem::ConnectWithTunnels => "Connect and restore tunnels",
+            HostMenuItem::ConnectAs => "Connect as other user/port",
+            HostMenuItem::CopySshCommand => "Copy ssh command",
+            HostMenuItem::ShowDetails => "Show details",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HostMenuState {
+    /// Index into `App::hosts` of the host the menu was opened on.
+    pub host_idx: usize,
+    pub selected: usize,
+}
+
+impl HostMenuState {
+    pub fn new(host_idx: usize) -> Self {
+        Self {
+            host_idx,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(HostMenuItem::ALL.len() - 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn item(&self) -> HostMenuItem {
+        HostMenuItem::ALL[self.selected]
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &HostMenuState, host_name: &str) {
+    let area = frame.area();
+
+    let [modal_area] = Layout::horizontal([Constraint::Length(36)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(HostMenuItem::ALL.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = HostMenuItem::ALL
+        .iter()
+        .map(|item| ListItem::new(Line::from(format!(" {}", item.label()))))
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!(" {host_name} "))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
@@ -0,0 +1,236 @@
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::ui::theme;
+
+/// Editing keys a text field handles besides typed characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKey {
+    Left,
+    Right,
+    Home,
+    End,
+    /// Delete the character under the cursor.
+    Delete,
+    /// Empty the field (Ctrl+U).
+    Clear,
+}
+
+/// A single-line text field shared by the modals: the text, a cursor,
+/// which characters it takes, and whether it is shown masked.
+#[derive(Debug, Clone)]
+pub struct TextInput {
+    text: String,
+    /// Byte offset into `text`, always on a char boundary.
+    cursor: usize,
+    /// Whether a typed character fits, given the text so far.
+    accepts: fn(&str, char) -> bool,
+    /// Shown as `•` per character, for passwords and passphrases.
+    pub secret: bool,
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl TextInput {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            cursor: text.len(),
+            text,
+            accepts: |_, _| true,
+            secret: false,
+        }
+    }
+
+    /// A field that only takes characters `accepts` allows.
+    pub fn with_filter(mut self, accepts: fn(&str, char) -> bool) -> Self {
+        self.accepts = accepts;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replace the text, with the cursor at its end.
+    pub fn set(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+    }
+
+    /// Type `c` at the cursor; false if the field doesn't take it.
+    pub fn insert(&mut self, c: char) -> bool {
+        if !(self.accepts)(&self.text, c) {
+            return false;
+        }
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        true
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn edit(&mut self, key: EditKey) {
+        match key {
+            EditKey::Left => {
+                if let Some(c) = self.text[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            EditKey::Right => {
+                if let Some(c) = self.text[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            EditKey::Home => self.cursor = 0,
+            EditKey::End => self.cursor = self.text.len(),
+            EditKey::Delete => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+            }
+            EditKey::Clear => self.set(""),
+        }
+    }
+
+    /// The field's text with the cursor drawn in when `active`; an empty
+    /// inactive field shows `placeholder` instead.
+    pub fn spans(&self, active: bool, placeholder: &str) -> Vec<Span<'static>> {
+        if !active {
+            if self.text.is_empty() {
+                return vec![Span::styled(
+                    placeholder.to_string(),
+                    Style::default().fg(theme::TEXT_DIM),
+                )];
+            }
+            return vec![Span::styled(
+                self.shown(&self.text),
+                Style::default().fg(theme::TEXT_PRIMARY),
+            )];
+        }
+
+        let style = value_style(true);
+        let (before, after) = self.text.split_at(self.cursor);
+        let mut after = after.chars();
+        let cursor = match after.next() {
+            Some(c) => Span::styled(
+                self.shown(&c.to_string()),
+                style.add_modifier(Modifier::REVERSED),
+            ),
+            None => Span::styled("█", Style::default().fg(theme::BORDER_FOCUSED)),
+        };
+        vec![
+            Span::styled(self.shown(before), style),
+            cursor,
+            Span::styled(self.shown(after.as_str()), style),
+        ]
+    }
+
+    fn shown(&self, text: &str) -> String {
+        if self.secret {
+            "•".repeat(text.chars().count())
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The field after `current` in `fields`, or before it going `back`,
+/// wrapping around at either end.
+pub fn cycle<T: Copy + PartialEq>(fields: &[T], current: T, back: bool) -> T {
+    let idx = fields.iter().position(|&f| f == current).unwrap_or(0);
+    let step = if back { fields.len() - 1 } else { 1 };
+    fields[(idx + step) % fields.len()]
+}
+
+/// Style of a field's value, bold while it has focus.
+pub fn value_style(active: bool) -> Style {
+    if active {
+        Style::default()
+            .fg(theme::HIGHLIGHT_FG)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(theme::TEXT_PRIMARY)
+    }
+}
+
+/// One `Label:  value` row of a form.
+pub fn render_field(frame: &mut Frame, area: Rect, label: &str, value: Vec<Span<'static>>) {
+    let mut spans = vec![Span::styled(
+        format!(" {label:<14}"),
+        Style::default().fg(theme::TEXT_DIM),
+    )];
+    spans.extend(value);
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_at_cursor() {
+        let mut input = TextInput::new("dbhost");
+        input.edit(EditKey::Home);
+        input.edit(EditKey::Right);
+        input.edit(EditKey::Right);
+        input.insert('-');
+        assert_eq!(input.text(), "db-host");
+        input.edit(EditKey::Delete);
+        assert_eq!(input.text(), "db-ost");
+        input.backspace();
+        input.backspace();
+        input.backspace();
+        assert_eq!(input.text(), "ost");
+        input.edit(EditKey::End);
+        input.insert('é');
+        input.edit(EditKey::Left);
+        input.insert('x');
+        assert_eq!(input.text(), "ostxé");
+        input.edit(EditKey::Clear);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_filter_and_mask() {
+        let mut port = TextInput::default().with_filter(|_, c| c.is_ascii_digit());
+        assert!(port.insert('5'));
+        assert!(!port.insert('a'));
+        assert_eq!(port.text(), "5");
+
+        let mut secret = TextInput::new("hunter2");
+        secret.secret = true;
+        let shown: String = secret
+            .spans(false, "")
+            .iter()
+            .map(|s| s.content.to_string())
+            .collect();
+        assert_eq!(shown, "•••••••");
+    }
+
+    #[test]
+    fn test_cycle() {
+        let fields = [1, 2, 3];
+        assert_eq!(cycle(&fields, 3, false), 1);
+        assert_eq!(cycle(&fields, 1, true), 3);
+        assert_eq!(cycle(&fields, 2, true), 1);
+    }
+}
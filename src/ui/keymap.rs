@@ -0,0 +1,489 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::app::Panel;
+use crate::ui::theme;
+
+/// A key a command can be bound to: a character, optionally with Ctrl, or
+/// a function key. Shift is part of the character (`T`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+impl KeyCombo {
+    const fn char(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            ctrl: false,
+        }
+    }
+
+    /// The combo for a key press, if it is one commands can be bound to.
+    /// Shift+Space stays toggle-all, whatever space is bound to.
+    pub fn from_event(modifiers: KeyModifiers, code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::Char(' ') if modifiers.contains(KeyModifiers::SHIFT) => None,
+            KeyCode::Char(_) | KeyCode::F(_) => Some(Self {
+                code,
+                ctrl: modifiers.contains(KeyModifiers::CONTROL),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The key press this combo stands for, keeping the modifiers of the
+    /// `pressed` one that the combo doesn't cover.
+    pub fn to_event(self, pressed: KeyModifiers) -> (KeyModifiers, KeyCode) {
+        let mut modifiers = pressed - KeyModifiers::CONTROL - KeyModifiers::SHIFT;
+        if self.ctrl {
+            modifiers |= KeyModifiers::CONTROL;
+        }
+        (modifiers, self.code)
+    }
+
+    /// Parse `t`, `T`, `space`, `ctrl+b` or `f5`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (ctrl, key) = match text.strip_prefix("ctrl+") {
+            Some(key) => (true, key),
+            None => (false, text),
+        };
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ if key == "space" => KeyCode::Char(' '),
+            _ => KeyCode::F(
+                key.strip_prefix('f')?
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=12).contains(n))?,
+            ),
+        };
+        Some(Self { code, ctrl })
+    }
+
+    /// Keys stm keeps for navigation and leaving screens.
+    fn is_reserved(self) -> bool {
+        match self.code {
+            KeyCode::Char(c) if self.ctrl => c == 'c',
+            KeyCode::Char(c) => "qjkhl?123456789".contains(c),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+/// A command that can be rebound in `[keys]`.
+#[derive(Debug)]
+pub struct Binding {
+    /// Its name in `[keys]`.
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: KeyCombo,
+    /// The panel the key works in; everywhere when unset.
+    pub panel: Option<Panel>,
+}
+
+impl Binding {
+    fn applies_in(&self, panel: Panel) -> bool {
+        self.panel.is_none_or(|p| p == panel)
+    }
+
+    /// Both could see the same key press.
+    fn overlaps(&self, other: &Binding) -> bool {
+        self.panel.is_none() || other.panel.is_none() || self.panel == other.panel
+    }
+}
+
+const fn global(name: &'static str, key: char, description: &'static str) -> Binding {
+    Binding {
+        name,
+        description,
+        default: KeyCombo::char(key),
+        panel: None,
+    }
+}
+
+const fn hosts(name: &'static str, key: char, description: &'static str) -> Binding {
+    Binding {
+        panel: Some(Panel::Hosts),
+        ..global(name, key, description)
+    }
+}
+
+const fn tunnels(name: &'static str, key: char, description: &'static str) -> Binding {
+    Binding {
+        panel: Some(Panel::Tunnels),
+        ..global(name, key, description)
+    }
+}
+
+/// Everything `[keys]` can rebind, by the key it is bound to out of the box.
+/// Navigation, Enter, Tab, q/Esc, `?` and the quick-connect digits stay fixed.
+pub const BINDINGS: &[Binding] = &[
+    global("search", '/', "Search hosts"),
+    global("zoom", 'z', "Zoom the focused panel"),
+    global("host_menu", 'm', "Host menu"),
+    global("agent_forwarding", 'A', "Toggle agent forwarding"),
+    global("x11_forwarding", 'X', "Cycle X11 forwarding"),
+    global("disconnect", 'x', "Disconnect"),
+    global("add_tunnel", 'a', "Add tunnel"),
+    global("paste_tunnel", 'p', "Add tunnel from clipboard"),
+    global("restore", 'r', "Choose saved tunnels to restore"),
+    global("settings", ',', "Settings"),
+    global("keys", 'K', "Rebind keys"),
+    global("ssh_command", 'S', "Copy the equivalent ssh command"),
+    global("session_note", 'n', "Note why this connection is up"),
+    global("port_lookup", 'o', "What's on a local port?"),
+    global("audit_forwards", 'F', "Audit the master's forwards"),
+    global("discover_ports", 'D', "Discover remote listening ports"),
+    global("safe_mode", 'P', "Safe mode"),
+    global("toggle_all", 'T', "Enable / disable all tunnels"),
+    hosts("wake_host", 'W', "Wake the host (Wake-on-LAN)"),
+    hosts("host_sort", 's', "Cycle host order"),
+    tunnels("toggle_tunnel", ' ', "Toggle tunnel on/off"),
+    tunnels("visual_mode", 'v', "Visual mode"),
+    tunnels("delete_tunnel", 'd', "Delete tunnel"),
+    tunnels("edit_tunnel", 'e', "Edit tunnel remote host:port"),
+    tunnels("retry_tunnel", 'R', "Retry failed tunnel"),
+    tunnels("tunnel_note", 'N', "Note on the tunnel"),
+    tunnels("idle_timeout", 'I', "Idle timeout"),
+    tunnels("bandwidth_limit", 'B', "Bandwidth limit"),
+    tunnels("temporary", 'E', "Make the tunnel temporary"),
+    tunnels("test_tunnel", 'C', "Test the tunnel"),
+    tunnels("open_browser", 'O', "Open the tunnel in the browser"),
+    tunnels("https", 'H', "Switch http / https"),
+    tunnels("schedule", 'W', "Schedule"),
+    tunnels("tunnel_group", 'G', "Put the tunnel in a group"),
+    tunnels("toggle_group", 'g', "Toggle the tunnel's group"),
+    tunnels("fold_group", 'f', "Fold the tunnel's group"),
+    tunnels("duplicate", 'y', "Duplicate tunnel"),
+    tunnels("collapse_disabled", 'c', "Collapse disabled tunnels"),
+    tunnels("tunnel_sort", 's', "Cycle tunnel sort"),
+];
+
+/// Keys moved away from their defaults, from `[keys]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keymap {
+    /// Index into `BINDINGS` → the key it is bound to instead.
+    overrides: BTreeMap<usize, KeyCombo>,
+}
+
+impl Keymap {
+    /// The keymap for a `[keys]` table, and what in it couldn't be used.
+    pub fn from_config(keys: &BTreeMap<String, String>) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+        for (name, key) in keys {
+            let Some(idx) = BINDINGS.iter().position(|b| b.name == name) else {
+                errors.push(format!("[keys]: unknown command '{name}'"));
+                continue;
+            };
+            let result = KeyCombo::parse(key)
+                .ok_or_else(|| format!("'{key}' is not a key (try 'b', 'ctrl+b' or 'f5')"))
+                .and_then(|combo| keymap.bind(idx, combo));
+            if let Err(e) = result {
+                errors.push(format!("[keys] {name}: {e}"));
+            }
+        }
+        (keymap, errors)
+    }
+
+    /// The `[keys]` table for this keymap: only what differs from the defaults.
+    pub fn to_config(&self) -> BTreeMap<String, String> {
+        self.overrides
+            .iter()
+            .map(|(&idx, key)| (BINDINGS[idx].name.to_string(), key.to_string()))
+            .collect()
+    }
+
+    pub fn key(&self, idx: usize) -> KeyCombo {
+        self.overrides
+            .get(&idx)
+            .copied()
+            .unwrap_or(BINDINGS[idx].default)
+    }
+
+    pub fn is_rebound(&self, idx: usize) -> bool {
+        self.overrides.contains_key(&idx)
+    }
+
+    /// Bind `BINDINGS[idx]` to `key`, unless stm reserves the key or another
+    /// command that could see it already has it.
+    pub fn bind(&mut self, idx: usize, key: KeyCombo) -> Result<(), String> {
+        if key.is_reserved() {
+            return Err(format!("'{key}' is reserved"));
+        }
+        let binding = &BINDINGS[idx];
+        if let Some(other) = (0..BINDINGS.len())
+            .find(|&j| j != idx && self.key(j) == key && BINDINGS[j].overlaps(binding))
+        {
+            return Err(format!(
+                "'{key}' is already bound to {}",
+                BINDINGS[other].description
+            ));
+        }
+        if key == binding.default {
+            self.overrides.remove(&idx);
+        } else {
+            self.overrides.insert(idx, key);
+        }
+        Ok(())
+    }
+
+    /// Put `BINDINGS[idx]` back on its default key, if that is free.
+    pub fn reset(&mut self, idx: usize) -> Result<(), String> {
+        self.bind(idx, BINDINGS[idx].default)
+    }
+
+    /// The default key a press stands for in `panel`, which the key handling
+    /// is written against; `None` for the old key of a rebound command.
+    pub fn translate(&self, panel: Panel, pressed: KeyCombo) -> Option<KeyCombo> {
+        if let Some((&idx, _)) = self
+            .overrides
+            .iter()
+            .find(|&(&idx, &key)| key == pressed && BINDINGS[idx].applies_in(panel))
+        {
+            return Some(BINDINGS[idx].default);
+        }
+        let moved = self
+            .overrides
+            .keys()
+            .any(|&idx| BINDINGS[idx].default == pressed && BINDINGS[idx].applies_in(panel));
+        (!moved).then_some(pressed)
+    }
+}
+
+/// Key binding editor: rebinds a copy of the keymap until it is written.
+#[derive(Debug, Clone)]
+pub struct KeymapEditorState {
+    pub draft: Keymap,
+    pub selected: usize,
+    /// Waiting for the new key of the selected command.
+    pub capturing: bool,
+    pub error_message: Option<String>,
+    /// The draft differs from the keymap stm is running with.
+    pub dirty: bool,
+}
+
+impl KeymapEditorState {
+    pub fn new(keymap: &Keymap) -> Self {
+        Self {
+            draft: keymap.clone(),
+            selected: 0,
+            capturing: false,
+            error_message: None,
+            dirty: false,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(BINDINGS.len() - 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Bind the selected command to the key just pressed.
+    pub fn capture(&mut self, key: KeyCombo) {
+        self.capturing = false;
+        self.apply(|keymap, idx| keymap.bind(idx, key));
+    }
+
+    pub fn reset(&mut self) {
+        self.apply(Keymap::reset);
+    }
+
+    fn apply(&mut self, change: impl FnOnce(&mut Keymap, usize) -> Result<(), String>) {
+        let before = self.draft.clone();
+        match change(&mut self.draft, self.selected) {
+            Ok(()) => {
+                self.dirty |= self.draft != before;
+                self.error_message = None;
+            }
+            Err(message) => self.error_message = Some(message),
+        }
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &KeymapEditorState) {
+    let area = frame.area();
+
+    let height = (BINDINGS.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let [modal_area] = Layout::horizontal([Constraint::Length(78)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let dim = Style::default().fg(theme::TEXT_DIM);
+    let items: Vec<ListItem> = BINDINGS
+        .iter()
+        .enumerate()
+        .map(|(i, binding)| {
+            let key = if state.capturing && i == state.selected {
+                "…".to_string()
+            } else {
+                state.draft.key(i).to_string()
+            };
+            let mut spans = vec![
+                Span::styled(
+                    format!(" {key:<8}"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!("{:<36}", binding.description)),
+                Span::styled(format!("{:<19}", binding.name), dim),
+            ];
+            if state.draft.is_rebound(i) {
+                spans.push(Span::styled(format!("was {}", binding.default), dim));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let footer = match (&state.error_message, state.capturing) {
+        (Some(error), _) => {
+            Line::from(format!(" {error} ")).style(Style::default().fg(theme::ERROR_COLOR))
+        }
+        (None, true) => Line::from(format!(
+            " Press the new key for {} · Esc cancels ",
+            BINDINGS[state.selected].description
+        ))
+        .style(Style::default().fg(theme::WARNING)),
+        (None, false) => {
+            Line::from(" Enter rebind · Backspace default · w write config.toml · Esc close ")
+                .style(dim)
+        }
+    };
+    let title = if state.dirty {
+        " Keys (unsaved) "
+    } else {
+        " Keys "
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .title_bottom(footer)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idx(name: &str) -> usize {
+        BINDINGS.iter().position(|b| b.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_key_combo_parse() {
+        for text in ["t", "T", ",", "space", "ctrl+b", "f5"] {
+            assert_eq!(KeyCombo::parse(text).unwrap().to_string(), text);
+        }
+        assert_eq!(KeyCombo::parse("f13"), None);
+        assert_eq!(KeyCombo::parse("shift+x"), None);
+    }
+
+    #[test]
+    fn test_rebind_and_translate() {
+        let mut keymap = Keymap::default();
+        let b = KeyCombo::char('b');
+        let d = KeyCombo::char('d');
+        keymap.bind(idx("delete_tunnel"), b).unwrap();
+
+        // The new key stands for the old one, which now does nothing
+        assert_eq!(keymap.translate(Panel::Tunnels, b), Some(d));
+        assert_eq!(keymap.translate(Panel::Tunnels, d), None);
+        // Only in the panel the command works in
+        assert_eq!(keymap.translate(Panel::Hosts, b), Some(b));
+        assert_eq!(keymap.translate(Panel::Hosts, d), Some(d));
+
+        assert_eq!(keymap.to_config()["delete_tunnel"], "b");
+        keymap.reset(idx("delete_tunnel")).unwrap();
+        assert_eq!(keymap, Keymap::default());
+    }
+
+    #[test]
+    fn test_shift_space_is_not_translated() {
+        let mut keymap = Keymap::default();
+        keymap
+            .bind(idx("toggle_tunnel"), KeyCombo::char('b'))
+            .unwrap();
+
+        assert_eq!(
+            KeyCombo::from_event(KeyModifiers::SHIFT, KeyCode::Char(' ')),
+            None
+        );
+        let space = KeyCombo::from_event(KeyModifiers::NONE, KeyCode::Char(' ')).unwrap();
+        assert_eq!(keymap.translate(Panel::Tunnels, space), None);
+
+        let alt_b = KeyCombo::from_event(KeyModifiers::ALT, KeyCode::Char('b')).unwrap();
+        let key = keymap.translate(Panel::Tunnels, alt_b).unwrap();
+        assert_eq!(
+            key.to_event(KeyModifiers::ALT),
+            (KeyModifiers::ALT, KeyCode::Char(' '))
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut keymap = Keymap::default();
+        let err = keymap
+            .bind(idx("test_tunnel"), KeyCombo::char('x'))
+            .unwrap_err();
+        assert!(err.contains("Disconnect"));
+        assert!(keymap
+            .bind(idx("wake_host"), KeyCombo::char('q'))
+            .unwrap_err()
+            .contains("reserved"));
+        // Different panels may share a key
+        keymap.bind(idx("wake_host"), KeyCombo::char('d')).unwrap();
+
+        let keys = BTreeMap::from([
+            ("zoom".to_string(), "Z".to_string()),
+            ("warp".to_string(), "w".to_string()),
+            ("search".to_string(), "ctrl+".to_string()),
+        ]);
+        let (keymap, errors) = Keymap::from_config(&keys);
+        assert_eq!(keymap.key(idx("zoom")), KeyCombo::char('Z'));
+        assert_eq!(errors.len(), 2);
+    }
+}
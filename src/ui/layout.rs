@@ -0,0 +1,110 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+
+use crate::app::Panel;
+use crate::state::persistence::PanelLayout;
+
+/// Screen areas assigned to each panel. A panel with no area is not drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PanelAreas {
+    pub hosts: Option<Rect>,
+    pub tunnels: Option<Rect>,
+    pub tabs: Option<Rect>,
+}
+
+/// Split the main area according to the configured arrangement.
+/// Zoom overrides the arrangement and gives the focused panel everything.
+pub fn panel_areas(
+    area: Rect,
+    layout: PanelLayout,
+    hosts_percent: u16,
+    active: Panel,
+    zoomed: bool,
+) -> PanelAreas {
+    let hosts_percent = hosts_percent.clamp(10, 90);
+    let single = |rect: Rect| match active {
+        Panel::Hosts => PanelAreas {
+            hosts: Some(rect),
+            ..Default::default()
+        },
+        Panel::Tunnels => PanelAreas {
+            tunnels: Some(rect),
+            ..Default::default()
+        },
+    };
+
+    if zoomed {
+        return single(area);
+    }
+
+    match layout {
+        PanelLayout::HostsLeft => {
+            let [hosts, tunnels] = Layout::horizontal([
+                Constraint::Percentage(hosts_percent),
+                Constraint::Percentage(100 - hosts_percent),
+            ])
+            .areas(area);
+            PanelAreas {
+                hosts: Some(hosts),
+                tunnels: Some(tunnels),
+                tabs: None,
+            }
+        }
+        PanelLayout::TunnelsTop => {
+            let [tunnels, hosts] = Layout::vertical([
+                Constraint::Percentage(100 - hosts_percent),
+                Constraint::Percentage(hosts_percent),
+            ])
+            .areas(area);
+            PanelAreas {
+                hosts: Some(hosts),
+                tunnels: Some(tunnels),
+                tabs: None,
+            }
+        }
+        PanelLayout::Tabbed => {
+            let [tabs, body] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(area);
+            PanelAreas {
+                tabs: Some(tabs),
+                ..single(body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect::new(0, 0, 100, 40);
+
+    #[test]
+    fn test_hosts_left_split() {
+        let areas = panel_areas(AREA, PanelLayout::HostsLeft, 35, Panel::Hosts, false);
+        assert_eq!(areas.hosts.unwrap().width, 35);
+        assert_eq!(areas.tunnels.unwrap().x, 35);
+        assert!(areas.tabs.is_none());
+    }
+
+    #[test]
+    fn test_tunnels_top_split() {
+        let areas = panel_areas(AREA, PanelLayout::TunnelsTop, 25, Panel::Hosts, false);
+        assert_eq!(areas.tunnels.unwrap().y, 0);
+        assert_eq!(areas.hosts.unwrap().height, 10);
+    }
+
+    #[test]
+    fn test_tabbed_shows_active_panel_only() {
+        let areas = panel_areas(AREA, PanelLayout::Tabbed, 35, Panel::Tunnels, false);
+        assert!(areas.hosts.is_none());
+        assert_eq!(areas.tabs.unwrap().height, 1);
+        assert_eq!(areas.tunnels.unwrap().y, 1);
+    }
+
+    #[test]
+    fn test_zoom_overrides_layout() {
+        let areas = panel_areas(AREA, PanelLayout::HostsLeft, 35, Panel::Hosts, true);
+        assert_eq!(areas.hosts, Some(AREA));
+        assert!(areas.tunnels.is_none());
+    }
+}
@@ -1,15 +1,29 @@
 pub mod add_modal;
+pub mod forward_audit;
+pub mod host_details;
 pub mod host_list;
+pub mod host_menu;
+pub mod input;
+pub mod keymap;
+pub mod layout;
+pub mod port_conflict;
+pub mod port_discovery;
+pub mod prompt;
+pub mod restore_modal;
+pub mod safe_mode;
+pub mod settings;
+pub mod socket_collision;
+pub mod ssh_command;
 pub mod status_bar;
 pub mod theme;
 pub mod tunnel_list;
 
 use ratatui::{
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Rect},
     Frame,
 };
 
-use crate::app::{App, Panel};
+use crate::app::{App, ConnectionStatus, Panel};
 
 pub fn render(frame: &mut Frame, app: &mut App) {
     let area = frame.area();
@@ -24,29 +38,115 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         return;
     }
 
+    if let Some(ref problem) = app.ssh_unavailable {
+        render_ssh_unavailable(frame, area, problem);
+        return;
+    }
+
     let [main_area, status_area] =
         Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(area);
+    let masker = app.masker();
 
-    let [host_area, tunnel_area] =
-        Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
-            .areas(main_area);
-
-    host_list::render(frame, host_area, app);
-    tunnel_list::render(
-        frame,
-        tunnel_area,
-        app.active_panel == Panel::Tunnels,
-        &app.tunnels,
-        &mut app.tunnel_list_state,
+    let areas = layout::panel_areas(
+        main_area,
+        app.config.ui.layout,
+        app.config.ui.hosts_percent,
+        app.active_panel,
+        app.zoomed,
     );
-    status_bar::render(frame, status_area, app);
+    if let Some(tabs_area) = areas.tabs {
+        render_tab_strip(frame, tabs_area, app.active_panel);
+    }
+    if let Some(host_area) = areas.hosts {
+        host_list::render(frame, host_area, app);
+    }
+    if let Some(tunnel_area) = areas.tunnels {
+        tunnel_list::render(frame, tunnel_area, app, &masker);
+    }
+    status_bar::render(frame, status_area, app, &masker);
 
     // Overlays
     if let Some(ref modal) = app.add_modal {
-        add_modal::render(frame, modal);
+        add_modal::render(frame, modal, &masker);
+    } else if let Some(ref prompt) = app.prompt {
+        prompt::render(frame, prompt, &masker);
+    } else if let Some(ref collision) = app.socket_collision {
+        if let Some(host) = app.hosts.get(collision.host_idx) {
+            socket_collision::render(frame, collision, &host.name, &masker);
+        }
+    } else if let Some(ref modal) = app.restore_modal {
+        if let ConnectionStatus::Connected(ref name) = app.connection_status {
+            restore_modal::render(frame, modal, name, &masker);
+        }
+    } else if let Some(ref audit) = app.forward_audit {
+        if let ConnectionStatus::Connected(ref name) = app.connection_status {
+            forward_audit::render(frame, audit, name, &masker);
+        }
+    } else if let Some(ref discovery) = app.port_discovery {
+        if let ConnectionStatus::Connected(ref name) = app.connection_status {
+            port_discovery::render(frame, discovery, name, &masker);
+        }
+    } else if let Some(ref state) = app.ssh_command {
+        if let ConnectionStatus::Connected(ref name) = app.connection_status {
+            ssh_command::render(frame, state, name, &masker);
+        }
+    } else if let Some(ref state) = app.settings {
+        settings::render(frame, state, &masker);
+    } else if let Some(ref state) = app.key_editor {
+        keymap::render(frame, state);
+    } else if let Some(ref menu) = app.host_menu {
+        if let Some(host) = app.hosts.get(menu.host_idx) {
+            host_menu::render(frame, menu, &host.name);
+        }
+    } else if let Some(ref details) = app.host_details {
+        if let Some(host) = app.hosts.get(details.host_idx) {
+            host_details::render(
+                frame,
+                details,
+                host,
+                app.history.hosts.get(&host.name),
+                app.reachability.get(&host.name),
+                app.mux_stats()
+                    .filter(|_| app.connected_host_name() == Some(&host.name)),
+                &masker,
+            );
+        }
     } else if app.show_help {
         render_help_overlay(frame);
     }
+    // Over the add-tunnel modal it came from
+    if let Some(ref conflict) = app.port_conflict {
+        port_conflict::render(frame, conflict, &masker);
+    }
+}
+
+fn render_tab_strip(frame: &mut Frame, area: Rect, active: Panel) {
+    use ratatui::{
+        style::{Modifier, Style},
+        text::{Line, Span},
+        widgets::Paragraph,
+    };
+
+    let tab = |label: &'static str, panel: Panel| {
+        if panel == active {
+            Span::styled(
+                label,
+                Style::default()
+                    .fg(theme::HIGHLIGHT_FG)
+                    .bg(theme::HIGHLIGHT_BG)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(label, Style::default().fg(theme::TEXT_DIM))
+        }
+    };
+
+    let line = Line::from(vec![
+        tab(" Hosts ", Panel::Hosts),
+        Span::raw(" "),
+        tab(" Tunnels ", Panel::Tunnels),
+    ]);
+    frame.render_widget(Paragraph::new(line), area);
 }
 
 fn render_help_overlay(frame: &mut Frame) {
@@ -85,26 +185,150 @@ fn render_help_overlay(frame: &mut Frame) {
             Span::styled("  Enter       ", bold),
             Span::styled("Connect to selected host", dim),
         ]),
+        Line::from(vec![
+            Span::styled("  m           ", bold),
+            Span::styled("Host actions menu", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  A / X       ", bold),
+            Span::styled("Toggle agent / cycle X11 forwarding", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  W           ", bold),
+            Span::styled("Wake-on-LAN host, then connect", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  1-9         ", bold),
+            Span::styled("Connect to numbered host", dim),
+        ]),
         Line::from(vec![
             Span::styled("  x           ", bold),
             Span::styled("Disconnect from host", dim),
         ]),
+        Line::from(vec![
+            Span::styled("  z           ", bold),
+            Span::styled("Zoom focused panel", dim),
+        ]),
         Line::from(vec![
             Span::styled("  /           ", bold),
-            Span::styled("Search hosts", dim),
+            Span::styled("Search hosts (name, address, saved tunnel ports)", dim),
         ]),
         Line::from(vec![
             Span::styled("  a           ", bold),
             Span::styled("Add tunnel", dim),
         ]),
+        Line::from(vec![
+            Span::styled("  p           ", bold),
+            Span::styled("Add tunnel from clipboard (host:port or URL)", dim),
+        ]),
         Line::from(vec![
             Span::styled("  Space       ", bold),
             Span::styled("Toggle tunnel on/off", dim),
         ]),
+        Line::from(vec![
+            Span::styled("  R           ", bold),
+            Span::styled("Retry failed tunnel", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  T           ", bold),
+            Span::styled("Enable all tunnels / disable all", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  v           ", bold),
+            Span::styled("Visual mode: Space mark, t toggle, d delete", dim),
+        ]),
         Line::from(vec![
             Span::styled("  d           ", bold),
             Span::styled("Delete tunnel", dim),
         ]),
+        Line::from(vec![
+            Span::styled("  e           ", bold),
+            Span::styled("Edit tunnel remote host:port", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  E           ", bold),
+            Span::styled("Temporary: keep the tunnel out of history", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  N           ", bold),
+            Span::styled("Note on the selected tunnel", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  I           ", bold),
+            Span::styled("Idle timeout: disable after N min unused", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  B           ", bold),
+            Span::styled("Bandwidth limit each way, e.g. 512K", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  W           ", bold),
+            Span::styled("Schedule: keep up 09:00-18:00 weekdays…", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  C           ", bold),
+            Span::styled("Test tunnel: connect + HTTP HEAD, show the result", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  O / H       ", bold),
+            Span::styled("Open tunnel in the browser / switch http↔https", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  G           ", bold),
+            Span::styled("Put the selected tunnel in a group", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  g / f       ", bold),
+            Span::styled("Toggle / fold the selected tunnel's group", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  y           ", bold),
+            Span::styled("Duplicate tunnel on the next free port", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  c           ", bold),
+            Span::styled("Collapse/expand disabled tunnels", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  s           ", bold),
+            Span::styled("Cycle tunnel sort / host order (recent, latency)", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  n           ", bold),
+            Span::styled("Note why this connection is up", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  ,           ", bold),
+            Span::styled("Settings", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  K           ", bold),
+            Span::styled("Rebind keys (shows the current bindings)", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  S           ", bold),
+            Span::styled("Copy the equivalent ssh command", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  r           ", bold),
+            Span::styled("Choose saved tunnels to restore", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  o           ", bold),
+            Span::styled("What's on a local port?", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  F           ", bold),
+            Span::styled("Audit the master's forwards against the list", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  D           ", bold),
+            Span::styled("Discover ports listening on the remote host", dim),
+        ]),
+        Line::from(vec![
+            Span::styled("  P           ", bold),
+            Span::styled("Safe mode: mask hostnames, addresses and users", dim),
+        ]),
         Line::from(vec![
             Span::styled("  ?           ", bold),
             Span::styled("Toggle this help", dim),
@@ -125,3 +349,45 @@ fn render_help_overlay(frame: &mut Frame) {
 
     frame.render_widget(help, modal_area);
 }
+
+fn render_ssh_unavailable(
+    frame: &mut Frame,
+    area: Rect,
+    problem: &crate::ssh::preflight::SshUnavailable,
+) {
+    use ratatui::{
+        style::{Modifier, Style},
+        text::Line,
+        widgets::{Block, Borders, Paragraph, Wrap},
+    };
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::styled(
+            format!("  {problem}"),
+            Style::default()
+                .fg(theme::ERROR_COLOR)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Line::from(""),
+    ];
+    lines.extend(
+        problem
+            .remediation()
+            .iter()
+            .map(|step| Line::from(format!("  {step}"))),
+    );
+    lines.push(Line::from(""));
+    lines.push(Line::styled(
+        "  Run `stm doctor` to re-check. Press q to quit.",
+        Style::default().fg(theme::TEXT_DIM),
+    ));
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(" OpenSSH client unavailable ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme::ERROR_COLOR)),
+    );
+    frame.render_widget(paragraph, area);
+}
@@ -0,0 +1,163 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::ssh::port_owner::PortOwner;
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// How to get past a local port the add-tunnel modal found taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    /// Stop the process listening on it.
+    Kill,
+    /// Add the tunnel on this free port instead.
+    OtherPort(u16),
+}
+
+/// Ways past `owner` holding a local port: another port first, so a stray
+/// Enter doesn't kill anything, then stopping the owner unless it is stm's.
+pub fn choices(
+    owner: Option<&PortOwner>,
+    socket_dir: &std::path::Path,
+    free_port: Option<u16>,
+) -> Vec<ConflictChoice> {
+    let mut choices: Vec<ConflictChoice> = free_port
+        .map(ConflictChoice::OtherPort)
+        .into_iter()
+        .collect();
+    if owner.is_some_and(|o| !o.is_stm(socket_dir)) {
+        choices.push(ConflictChoice::Kill);
+    }
+    choices
+}
+
+#[derive(Debug, Clone)]
+pub struct PortConflictState {
+    pub port: u16,
+    /// The listening process, if stm can see it.
+    pub owner: Option<PortOwner>,
+    pub choices: Vec<ConflictChoice>,
+    pub selected: usize,
+    /// Kill was chosen once; choosing it again stops the process.
+    pub confirming_kill: bool,
+}
+
+impl PortConflictState {
+    pub fn new(port: u16, owner: Option<PortOwner>, choices: Vec<ConflictChoice>) -> Self {
+        Self {
+            port,
+            owner,
+            choices,
+            selected: 0,
+            confirming_kill: false,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.choices.len().saturating_sub(1));
+        self.confirming_kill = false;
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.confirming_kill = false;
+    }
+
+    pub fn choice(&self) -> Option<ConflictChoice> {
+        self.choices.get(self.selected).copied()
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &PortConflictState, masker: &Masker) {
+    let area = frame.area();
+
+    let [modal_area] = Layout::horizontal([Constraint::Length(64)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(state.choices.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = state
+        .choices
+        .iter()
+        .map(|choice| {
+            let label = match (choice, &state.owner) {
+                (ConflictChoice::Kill, Some(_)) if state.confirming_kill => {
+                    " Stop it? Enter again to send SIGTERM".to_string()
+                }
+                (ConflictChoice::Kill, Some(owner)) => {
+                    // A foreign command line can name anything; hide it whole
+                    let command = masker.hide(&owner.short_command(36));
+                    format!(" Stop pid {} `{command}`", owner.pid)
+                }
+                (ConflictChoice::Kill, None) => " Stop the process".to_string(),
+                (ConflictChoice::OtherPort(port), _) => format!(" Use port {port} instead"),
+            };
+            ListItem::new(Line::from(label))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!(" Port {} is already in use ", state.port))
+                .title_bottom(
+                    Line::from(" Enter choose · Esc back to the form ")
+                        .style(Style::default().fg(theme::TEXT_DIM)),
+                )
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::WARNING)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_choices() {
+        let sockets = Path::new("/home/me/.config/stm/sockets");
+        let foreign = PortOwner {
+            pid: 4242,
+            command: "python3 -m http.server 8080".to_string(),
+        };
+        let master = PortOwner {
+            pid: 917,
+            command: "ssh -M -S /home/me/.config/stm/sockets/db-22 -N db".to_string(),
+        };
+        assert_eq!(
+            choices(Some(&foreign), sockets, Some(8081)),
+            [ConflictChoice::OtherPort(8081), ConflictChoice::Kill]
+        );
+        // stm's own masters are never offered for killing
+        assert_eq!(
+            choices(Some(&master), sockets, Some(8081)),
+            [ConflictChoice::OtherPort(8081)]
+        );
+        assert_eq!(
+            choices(None, sockets, Some(8081)),
+            [ConflictChoice::OtherPort(8081)]
+        );
+        assert_eq!(
+            choices(Some(&foreign), sockets, None),
+            [ConflictChoice::Kill]
+        );
+    }
+}
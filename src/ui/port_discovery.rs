@@ -0,0 +1,126 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::ssh::discovery::RemoteListener;
+use crate::ssh::tunnel::{Tunnel, TunnelDirection};
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// Ports listening on the remote host, to pick one to forward.
+#[derive(Debug, Clone)]
+pub struct PortDiscoveryState {
+    pub listeners: Vec<RemoteListener>,
+    /// Per listener: an -L tunnel already forwards to it.
+    pub forwarded: Vec<bool>,
+    pub selected: usize,
+}
+
+impl PortDiscoveryState {
+    pub fn new(listeners: Vec<RemoteListener>, tunnels: &[Tunnel]) -> Self {
+        let forwarded = listeners
+            .iter()
+            .map(|l| {
+                tunnels.iter().any(|t| {
+                    t.direction == TunnelDirection::Local
+                        && t.remote_port == l.port
+                        && t.remote_host == l.forward_host()
+                })
+            })
+            .collect();
+        Self {
+            listeners,
+            forwarded,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.listeners.len().saturating_sub(1));
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn selected(&self) -> Option<&RemoteListener> {
+        self.listeners.get(self.selected)
+    }
+
+    pub fn is_forwarded(&self) -> bool {
+        self.forwarded.get(self.selected).copied().unwrap_or(false)
+    }
+
+    pub fn mark_forwarded(&mut self) {
+        if let Some(forwarded) = self.forwarded.get_mut(self.selected) {
+            *forwarded = true;
+        }
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &PortDiscoveryState, host_name: &str, masker: &Masker) {
+    let area = frame.area();
+
+    let height = (state.listeners.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let [modal_area] = Layout::horizontal([Constraint::Length(60)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = state
+        .listeners
+        .iter()
+        .zip(&state.forwarded)
+        .map(|(listener, &forwarded)| {
+            let mut spans = vec![
+                Span::styled(
+                    format!(" {:>5}  ", listener.port),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(
+                    "{:<16}",
+                    listener.process.as_deref().unwrap_or("?")
+                )),
+                Span::styled(
+                    masker.mask(&listener.address),
+                    Style::default().fg(theme::TEXT_DIM),
+                ),
+            ];
+            if forwarded {
+                spans.push(Span::styled(
+                    "  forwarded",
+                    Style::default().fg(theme::CONNECTED),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!(" Listening on {host_name} "))
+                .title_bottom(
+                    Line::from(" Enter forward · a edit first · Esc close ")
+                        .style(Style::default().fg(theme::TEXT_DIM)),
+                )
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
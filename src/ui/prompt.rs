@@ -0,0 +1,130 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::ui::input::{self, EditKey, TextInput};
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// What a submitted prompt value is used for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptKind {
+    /// Concrete hostname for the pattern host at this index in `App::hosts`.
+    InstantiatePattern(usize),
+    /// New local port for the tunnel with this id, whose port is taken.
+    RemapLocalPort(uuid::Uuid),
+    /// New `host:port` destination for the tunnel with this id.
+    EditTunnelRemote(uuid::Uuid),
+    /// `user:port` overrides for connecting to the host at this index.
+    ConnectOverride(usize),
+    /// Note attached to the current connection.
+    SessionNote,
+    /// Note attached to the tunnel with this id.
+    TunnelNote(uuid::Uuid),
+    /// Idle timeout in minutes for the tunnel with this id.
+    IdleTimeout(uuid::Uuid),
+    /// Bandwidth limit, e.g. `512K`, for the tunnel with this id.
+    BandwidthLimit(uuid::Uuid),
+    /// Active window, e.g. `09:00-18:00 weekdays`, for the tunnel with this id.
+    Schedule(uuid::Uuid),
+    /// Group name for the tunnel with this id.
+    TunnelGroup(uuid::Uuid),
+    /// Typed confirmation before enabling an exposed -R tunnel with this id.
+    ConfirmExposure(uuid::Uuid),
+    /// Local port to find the owner of; stays open to show the answer.
+    PortLookup,
+    /// Destination of the orphaned forward on this port, to adopt it.
+    AdoptForward(u16),
+    /// Destination of the orphaned forward on this port, to cancel it.
+    CancelForward(u16),
+}
+
+/// A single-line text prompt shown as a small modal.
+#[derive(Debug, Clone)]
+pub struct PromptState {
+    pub kind: PromptKind,
+    pub title: String,
+    pub label: String,
+    pub value: TextInput,
+    /// Explanation shown under the input while there is no error.
+    pub hint: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl PromptState {
+    pub fn new(kind: PromptKind, title: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            label: label.into(),
+            value: TextInput::default(),
+            hint: None,
+            error_message: None,
+        }
+    }
+
+    pub fn input(&mut self, c: char) {
+        self.value.insert(c);
+        self.error_message = None;
+    }
+
+    pub fn backspace(&mut self) {
+        self.value.backspace();
+        self.error_message = None;
+    }
+
+    pub fn edit(&mut self, key: EditKey) {
+        self.value.edit(key);
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &PromptState, masker: &Masker) {
+    let area = frame.area();
+
+    let [modal_area] = Layout::horizontal([Constraint::Percentage(50)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(6)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(format!(" {} ", masker.mask(&state.title)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::BORDER_FOCUSED));
+
+    let inner = block.inner(modal_area);
+    frame.render_widget(block, modal_area);
+
+    let [_, field_area, _, error_area] = Layout::vertical([
+        Constraint::Length(1), // padding
+        Constraint::Length(1), // input
+        Constraint::Length(1), // spacing
+        Constraint::Length(1), // error message
+    ])
+    .areas(inner);
+
+    input::render_field(frame, field_area, &state.label, state.value.spans(true, ""));
+
+    if let Some(ref error) = state.error_message {
+        let err_line = Line::from(Span::styled(
+            masker.mask(error),
+            Style::default().fg(theme::ERROR_COLOR),
+        ))
+        .centered();
+        frame.render_widget(Paragraph::new(err_line), error_area);
+    } else if let Some(ref hint) = state.hint {
+        let hint_line = Line::from(Span::styled(
+            masker.mask(hint),
+            Style::default().fg(theme::WARNING),
+        ))
+        .centered();
+        frame.render_widget(Paragraph::new(hint_line), error_area);
+    }
+}
@@ -0,0 +1,155 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::state::history::SavedTunnel;
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// Checklist of a host's saved tunnels to restore.
+#[derive(Debug, Clone)]
+pub struct RestoreModalState {
+    pub entries: Vec<SavedTunnel>,
+    pub checked: Vec<bool>,
+    pub selected: usize,
+}
+
+impl RestoreModalState {
+    /// Tunnels saved as enabled start out checked.
+    pub fn new(entries: Vec<SavedTunnel>) -> Self {
+        let checked = entries.iter().map(|t| t.enabled).collect();
+        Self {
+            entries,
+            checked,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn toggle(&mut self) {
+        if let Some(checked) = self.checked.get_mut(self.selected) {
+            *checked = !*checked;
+        }
+    }
+
+    /// Check everything, or uncheck everything if all are already checked.
+    pub fn toggle_all(&mut self) {
+        let all = self.checked.iter().all(|&c| c);
+        self.checked.iter_mut().for_each(|c| *c = !all);
+    }
+
+    /// The checked entries, in list order.
+    pub fn chosen(&self) -> Vec<SavedTunnel> {
+        self.entries
+            .iter()
+            .zip(&self.checked)
+            .filter(|(_, &checked)| checked)
+            .map(|(t, _)| t.clone())
+            .collect()
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &RestoreModalState, host_name: &str, masker: &Masker) {
+    let area = frame.area();
+
+    let height = (state.entries.len() as u16 + 3).min(area.height.saturating_sub(2));
+    let [modal_area] = Layout::horizontal([Constraint::Length(52)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = state
+        .entries
+        .iter()
+        .zip(&state.checked)
+        .map(|(t, &checked)| {
+            let mark = if checked { "[x] " } else { "[ ] " };
+            let mut spans = vec![Span::raw(format!(" {mark}"))];
+            if let Some(ref label) = t.label {
+                spans.push(Span::styled(
+                    format!("{} ", masker.mask(label)),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ));
+            }
+            spans.push(Span::raw(masker.mask(&t.spec())));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!(" Restore tunnels on {host_name} "))
+                .title_bottom(
+                    Line::from(" Space toggle · a all · Enter restore ")
+                        .style(Style::default().fg(theme::TEXT_DIM)),
+                )
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::history::TunnelStats;
+
+    fn saved(port: u16, enabled: bool) -> SavedTunnel {
+        SavedTunnel {
+            local_port: port,
+            remote_host: "localhost".to_string(),
+            remote_port: port,
+            direction: Default::default(),
+            local_socket: None,
+            bind_address: None,
+            label: None,
+            note: None,
+            idle_timeout_mins: None,
+            bandwidth_limit: None,
+            schedule: None,
+            group: None,
+            https: false,
+            enabled,
+            stats: TunnelStats::default(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_checklist_selection() {
+        let mut state = RestoreModalState::new(vec![saved(5432, true), saved(6379, false)]);
+        assert_eq!(state.chosen().len(), 1);
+
+        state.next();
+        state.toggle();
+        assert_eq!(state.chosen().len(), 2);
+
+        state.toggle_all();
+        assert!(state.chosen().is_empty());
+        state.toggle_all();
+        assert_eq!(state.chosen().len(), 2);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::ssh::config::SshHost;
+use crate::ssh::tunnel::Tunnel;
+
+/// Shown in place of a hostname, address or username.
+pub const MASK: &str = "•••";
+
+/// Hides hostnames, IP addresses and usernames in what the UI shows while
+/// safe mode is on, so stm can be screen-shared or recorded. Host aliases
+/// and loopback addresses stay readable.
+#[derive(Debug, Default)]
+pub struct Masker {
+    enabled: bool,
+    /// Known hostnames and addresses, and `user@` prefixes; longest first so
+    /// `db.prod.internal` goes before `prod.internal`.
+    secrets: Vec<String>,
+}
+
+impl Masker {
+    /// A masker for everything `hosts` and `tunnels` would reveal.
+    pub fn new(hosts: &[SshHost], tunnels: &[Tunnel]) -> Self {
+        let mut secrets = Vec::new();
+        for host in hosts {
+            secrets.extend(host.hostname.iter().filter(|h| **h != host.name).cloned());
+            secrets.extend(host.canonical_hostname.iter().cloned());
+            secrets.extend(host.user.iter().map(|u| format!("{u}@")));
+            for hop in &host.proxy_jump {
+                secrets.push(hop.host.clone());
+                secrets.extend(hop.user.iter().map(|u| format!("{u}@")));
+            }
+        }
+        for tunnel in tunnels {
+            if !tunnel.targets_socket() {
+                secrets.push(tunnel.remote_host.clone());
+            }
+            secrets.extend(tunnel.bind_address.iter().cloned());
+        }
+        // Aliases stay readable even where a hostname reuses one
+        secrets.retain(|s| hosts.iter().all(|h| h.name != *s));
+        let mut masker = Self {
+            enabled: true,
+            secrets: Vec::new(),
+        };
+        masker.also_mask(secrets);
+        masker
+    }
+
+    /// Mask these names too, e.g. hosts of saved tunnels not yet restored.
+    pub fn also_mask(&mut self, names: impl IntoIterator<Item = String>) {
+        self.secrets.extend(
+            names
+                .into_iter()
+                .filter(|s| !s.is_empty() && !is_loopback(s)),
+        );
+        self.secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        self.secrets.dedup();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// `text` with known hostnames, users and any IP address masked.
+    pub fn mask(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            text = replace_words(&text, secret);
+        }
+        mask_ipv4(&mask_ipv6(&text))
+    }
+
+    /// `text`, or only the mask in safe mode: for values like paths and
+    /// proxy commands that are sensitive as a whole.
+    pub fn hide(&self, text: &str) -> String {
+        if self.enabled {
+            MASK.to_string()
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn is_loopback(host: &str) -> bool {
+    matches!(
+        host,
+        "localhost" | "127.0.0.1" | "::1" | "0.0.0.0" | "*" | "::"
+    )
+}
+
+/// Part of a hostname or address, for telling where a match ends.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '.' || c == '-' || c == '_'
+}
+
+/// Replace `secret` where it stands as a whole word; `user@` prefixes only
+/// need to start one.
+fn replace_words(text: &str, secret: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(secret) {
+        let end = at + secret.len();
+        let starts = !rest[..at].chars().next_back().is_some_and(is_word);
+        let ends = secret.ends_with('@') || !rest[end..].chars().next().is_some_and(is_word);
+        out.push_str(&rest[..at]);
+        if starts && ends {
+            out.push_str(MASK);
+            // Keep the `@` so a masked target still reads as user@host
+            out.push_str(if secret.ends_with('@') { "@" } else { "" });
+        } else {
+            out.push_str(secret);
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Mask dotted-quad IPv4 addresses other than loopback and the wildcard.
+fn mask_ipv4(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        let run_len = rest[start..]
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len() - start);
+        let run = rest[start..start + run_len].trim_end_matches('.');
+        let after = &rest[start + run.len()..];
+        let word = rest[..start].chars().next_back().is_some_and(is_word)
+            || after.chars().next().is_some_and(char::is_alphanumeric);
+        out.push_str(&rest[..start]);
+        out.push_str(if !word && is_ipv4(run) && !is_loopback(run) {
+            MASK
+        } else {
+            run
+        });
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Mask IPv6 literals other than loopback and the wildcard, bracketed or
+/// not; a zone like `%eth0` is left after the mask.
+fn mask_ipv6(text: &str) -> String {
+    let part = |c: char| c.is_ascii_hexdigit() || c == ':' || c == '.';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_hexdigit() || c == ':') {
+        let run_len = rest[start..]
+            .find(|c: char| !part(c))
+            .unwrap_or(rest.len() - start);
+        let mut run = rest[start..start + run_len].trim_end_matches('.');
+        // A colon closing the sentence, as in "refused by fe80::1:"
+        if !is_ipv6(run) {
+            run = run.strip_suffix(':').filter(|r| is_ipv6(r)).unwrap_or(run);
+        }
+        let after = &rest[start + run.len()..];
+        let word = rest[..start].chars().next_back().is_some_and(is_word)
+            || after.chars().next().is_some_and(char::is_alphanumeric);
+        out.push_str(&rest[..start]);
+        out.push_str(if !word && is_ipv6(run) { MASK } else { run });
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_ipv6(text: &str) -> bool {
+    text.matches(':').count() >= 2
+        && text
+            .parse::<std::net::Ipv6Addr>()
+            .is_ok_and(|ip| !ip.is_loopback() && !ip.is_unspecified())
+}
+
+fn is_ipv4(text: &str) -> bool {
+    let octets: Vec<&str> = text.split('.').collect();
+    octets.len() == 4 && octets.iter().all(|o| o.parse::<u8>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_known_hosts_and_users() {
+        let host = SshHost {
+            name: "prod".to_string(),
+            hostname: Some("bastion.corp.example".to_string()),
+            user: Some("deploy".to_string()),
+            ..Default::default()
+        };
+        let tunnel = Tunnel::new(5432, "db.internal".to_string(), 5432);
+        let masker = Masker::new(&[host], &[tunnel]);
+        assert_eq!(
+            masker.mask("Connecting to deploy@bastion.corp.example:22 (prod)"),
+            "Connecting to •••@•••:22 (prod)"
+        );
+        assert_eq!(masker.mask("5432 → db.internal:5432"), "5432 → •••:5432");
+        // Only whole names: a longer hostname containing one is left as is
+        assert_eq!(masker.mask("mydb.internal.io"), "mydb.internal.io");
+        assert_eq!(Masker::default().mask("db.internal"), "db.internal");
+    }
+
+    #[test]
+    fn test_mask_ipv4() {
+        let masker = Masker::new(&[], &[]);
+        assert_eq!(
+            masker.mask("10.0.0.5:6379 and 127.0.0.1:8080, v1.2.3.4 at 192.168.1.20."),
+            "•••:6379 and 127.0.0.1:8080, v1.2.3.4 at •••."
+        );
+        assert_eq!(masker.hide("/home/alice/.ssh/id_ed25519"), MASK);
+    }
+
+    #[test]
+    fn test_mask_ipv6() {
+        let masker = Masker::new(&[], &[]);
+        assert_eq!(
+            masker.mask("8080 → [2001:db8::5]:80, fe80::1%eth0 and [::1]:8080"),
+            "8080 → [•••]:80, •••%eth0 and [::1]:8080"
+        );
+        assert_eq!(
+            masker.mask("refused by 2001:db8:0:0:0:0:0:7: no route"),
+            "refused by •••: no route"
+        );
+        // Times and forward specs aren't addresses
+        assert_eq!(
+            masker.mask("at 10:30:00, 5432:db:5432 and Foo::bar"),
+            "at 10:30:00, 5432:db:5432 and Foo::bar"
+        );
+        assert_eq!(masker.mask("::ffff:10.0.0.5"), MASK);
+    }
+}
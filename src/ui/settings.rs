@@ -0,0 +1,408 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+use std::path::PathBuf;
+
+use crate::state::persistence::{AppConfig, HostSort, PanelLayout};
+use crate::ui::input::{EditKey, TextInput};
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// A `config.toml` option editable from the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    SshConfigPath,
+    SocketDir,
+    AutoRestore,
+    AutoRemap,
+    MaxRecentHosts,
+    KeepMastersOnExit,
+    ReachabilitySweep,
+    ReachabilityTtl,
+    ProbeTunnels,
+    AutoLabel,
+    TrafficStats,
+    ShowAllHosts,
+    Layout,
+    HostsPercent,
+    FocusTunnelsOnConnect,
+    CollapseDisabledTunnels,
+    HostSort,
+    SafeMode,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 18] = [
+        Setting::SshConfigPath,
+        Setting::SocketDir,
+        Setting::AutoRestore,
+        Setting::AutoRemap,
+        Setting::MaxRecentHosts,
+        Setting::KeepMastersOnExit,
+        Setting::ReachabilitySweep,
+        Setting::ReachabilityTtl,
+        Setting::ProbeTunnels,
+        Setting::AutoLabel,
+        Setting::TrafficStats,
+        Setting::ShowAllHosts,
+        Setting::Layout,
+        Setting::HostsPercent,
+        Setting::FocusTunnelsOnConnect,
+        Setting::CollapseDisabledTunnels,
+        Setting::HostSort,
+        Setting::SafeMode,
+    ];
+
+    /// The option's key in `config.toml`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Setting::SshConfigPath => "general.ssh_config_path",
+            Setting::SocketDir => "general.socket_dir",
+            Setting::AutoRestore => "general.auto_restore",
+            Setting::AutoRemap => "general.auto_remap",
+            Setting::MaxRecentHosts => "general.max_recent_hosts",
+            Setting::KeepMastersOnExit => "general.keep_masters_on_exit",
+            Setting::ReachabilitySweep => "general.reachability_sweep",
+            Setting::ReachabilityTtl => "general.reachability_ttl_secs",
+            Setting::ProbeTunnels => "general.probe_tunnels",
+            Setting::AutoLabel => "general.auto_label",
+            Setting::TrafficStats => "general.traffic_stats",
+            Setting::ShowAllHosts => "ui.show_all_hosts",
+            Setting::Layout => "ui.layout",
+            Setting::HostsPercent => "ui.hosts_percent",
+            Setting::FocusTunnelsOnConnect => "ui.focus_tunnels_on_connect",
+            Setting::CollapseDisabledTunnels => "ui.collapse_disabled_tunnels",
+            Setting::HostSort => "ui.host_sort",
+            Setting::SafeMode => "ui.safe_mode",
+        }
+    }
+
+    /// Text options are typed in; the others toggle or cycle on Enter.
+    /// The setting's value as written to config.toml.
+    pub fn toml_value(self, config: &AppConfig) -> toml_edit::Value {
+        let text = self.value(config);
+        match self {
+            Setting::MaxRecentHosts | Setting::ReachabilityTtl | Setting::HostsPercent => text
+                .parse::<i64>()
+                .map(toml_edit::Value::from)
+                .unwrap_or_else(|_| text.into()),
+            Setting::SshConfigPath | Setting::SocketDir | Setting::Layout | Setting::HostSort => {
+                text.into()
+            }
+            _ => (text == "true").into(),
+        }
+    }
+
+    /// Settings that differ between `before` and `after`, keyed for config.toml.
+    pub fn changed(before: &AppConfig, after: &AppConfig) -> Vec<(&'static str, toml_edit::Value)> {
+        Setting::ALL
+            .into_iter()
+            .filter(|s| s.value(before) != s.value(after))
+            .map(|s| (s.key(), s.toml_value(after)))
+            .collect()
+    }
+
+    pub fn is_text(self) -> bool {
+        matches!(
+            self,
+            Setting::SshConfigPath
+                | Setting::SocketDir
+                | Setting::MaxRecentHosts
+                | Setting::ReachabilityTtl
+                | Setting::HostsPercent
+        )
+    }
+
+    pub fn value(self, config: &AppConfig) -> String {
+        let general = &config.general;
+        let ui = &config.ui;
+        match self {
+            Setting::SshConfigPath => general.ssh_config_path.display().to_string(),
+            Setting::SocketDir => general.socket_dir.display().to_string(),
+            Setting::AutoRestore => general.auto_restore.to_string(),
+            Setting::AutoRemap => general.auto_remap.to_string(),
+            Setting::MaxRecentHosts => general.max_recent_hosts.to_string(),
+            Setting::KeepMastersOnExit => general.keep_masters_on_exit.to_string(),
+            Setting::ReachabilitySweep => general.reachability_sweep.to_string(),
+            Setting::ReachabilityTtl => general.reachability_ttl_secs.to_string(),
+            Setting::ProbeTunnels => general.probe_tunnels.to_string(),
+            Setting::AutoLabel => general.auto_label.to_string(),
+            Setting::TrafficStats => general.traffic_stats.to_string(),
+            Setting::ShowAllHosts => ui.show_all_hosts.to_string(),
+            Setting::Layout => match ui.layout {
+                PanelLayout::HostsLeft => "hosts-left",
+                PanelLayout::TunnelsTop => "tunnels-top",
+                PanelLayout::Tabbed => "tabbed",
+            }
+            .to_string(),
+            Setting::HostsPercent => ui.hosts_percent.to_string(),
+            Setting::FocusTunnelsOnConnect => ui.focus_tunnels_on_connect.to_string(),
+            Setting::CollapseDisabledTunnels => ui.collapse_disabled_tunnels.to_string(),
+            Setting::HostSort => match ui.host_sort {
+                HostSort::Recent => "recent",
+                HostSort::Latency => "latency",
+            }
+            .to_string(),
+            Setting::SafeMode => ui.safe_mode.to_string(),
+        }
+    }
+
+    /// Flip a boolean option or move to the next layout or host order.
+    pub fn toggle(self, config: &mut AppConfig) {
+        let general = &mut config.general;
+        let ui = &mut config.ui;
+        match self {
+            Setting::AutoRestore => general.auto_restore = !general.auto_restore,
+            Setting::AutoRemap => general.auto_remap = !general.auto_remap,
+            Setting::KeepMastersOnExit => {
+                general.keep_masters_on_exit = !general.keep_masters_on_exit
+            }
+            Setting::ReachabilitySweep => general.reachability_sweep = !general.reachability_sweep,
+            Setting::ProbeTunnels => general.probe_tunnels = !general.probe_tunnels,
+            Setting::AutoLabel => general.auto_label = !general.auto_label,
+            Setting::TrafficStats => general.traffic_stats = !general.traffic_stats,
+            Setting::ShowAllHosts => ui.show_all_hosts = !ui.show_all_hosts,
+            Setting::Layout => {
+                ui.layout = match ui.layout {
+                    PanelLayout::HostsLeft => PanelLayout::TunnelsTop,
+                    PanelLayout::TunnelsTop => PanelLayout::Tabbed,
+                    PanelLayout::Tabbed => PanelLayout::HostsLeft,
+                }
+            }
+            Setting::FocusTunnelsOnConnect => {
+                ui.focus_tunnels_on_connect = !ui.focus_tunnels_on_connect
+            }
+            Setting::CollapseDisabledTunnels => {
+                ui.collapse_disabled_tunnels = !ui.collapse_disabled_tunnels
+            }
+            Setting::HostSort => {
+                ui.host_sort = match ui.host_sort {
+                    HostSort::Recent => HostSort::Latency,
+                    HostSort::Latency => HostSort::Recent,
+                }
+            }
+            Setting::SafeMode => ui.safe_mode = !ui.safe_mode,
+            _ => {}
+        }
+    }
+
+    /// Set a text option from what was typed.
+    pub fn set(self, config: &mut AppConfig, text: &str) -> Result<(), String> {
+        let text = text.trim();
+        let number = || {
+            text.parse::<u64>()
+                .map_err(|_| format!("{} must be a number", self.key()))
+        };
+        match self {
+            Setting::SshConfigPath | Setting::SocketDir => {
+                if text.is_empty() {
+                    return Err(format!("{} cannot be empty", self.key()));
+                }
+                let path = match text.strip_prefix("~/") {
+                    Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+                    None => PathBuf::from(text),
+                };
+                if self == Setting::SshConfigPath {
+                    config.general.ssh_config_path = path;
+                } else {
+                    config.general.socket_dir = path;
+                }
+            }
+            Setting::MaxRecentHosts => config.general.max_recent_hosts = number()? as usize,
+            Setting::ReachabilityTtl => config.general.reachability_ttl_secs = number()?,
+            Setting::HostsPercent => match number()? {
+                p @ 10..=90 => config.ui.hosts_percent = p as u16,
+                _ => return Err("ui.hosts_percent must be between 10 and 90".to_string()),
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Settings screen: edits a copy of the config until it is written.
+#[derive(Debug, Clone)]
+pub struct SettingsState {
+    pub draft: AppConfig,
+    pub selected: usize,
+    /// Text being typed for the selected option, while editing it.
+    pub editing: Option<TextInput>,
+    pub error_message: Option<String>,
+    /// The draft differs from the config stm is running with.
+    pub dirty: bool,
+}
+
+impl SettingsState {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            draft: config.clone(),
+            selected: 0,
+            editing: None,
+            error_message: None,
+            dirty: false,
+        }
+    }
+
+    pub fn setting(&self) -> Setting {
+        Setting::ALL[self.selected]
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(Setting::ALL.len() - 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Enter on an option: toggle it, start typing, or commit what was typed.
+    pub fn activate(&mut self) {
+        let setting = self.setting();
+        if let Some(text) = self.editing.take() {
+            match setting.set(&mut self.draft, text.text()) {
+                Ok(()) => {
+                    self.dirty = true;
+                    self.error_message = None;
+                }
+                Err(message) => {
+                    self.error_message = Some(message);
+                    self.editing = Some(text);
+                }
+            }
+        } else if setting.is_text() {
+            self.editing = Some(TextInput::new(setting.value(&self.draft)));
+        } else {
+            setting.toggle(&mut self.draft);
+            self.dirty = true;
+        }
+    }
+
+    pub fn input(&mut self, c: char) {
+        if let Some(ref mut text) = self.editing {
+            text.insert(c);
+            self.error_message = None;
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(ref mut text) = self.editing {
+            text.backspace();
+            self.error_message = None;
+        }
+    }
+
+    pub fn edit(&mut self, key: EditKey) {
+        if let Some(ref mut text) = self.editing {
+            text.edit(key);
+        }
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &SettingsState, masker: &Masker) {
+    let area = frame.area();
+
+    let height = (Setting::ALL.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let [modal_area] = Layout::horizontal([Constraint::Length(72)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = Setting::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, setting)| {
+            let mut spans = vec![Span::styled(
+                format!(" {:<34}", setting.key()),
+                Style::default().fg(theme::TEXT_DIM),
+            )];
+            match state.editing {
+                Some(ref text) if i == state.selected => spans.extend(text.spans(true, "")),
+                _ => spans.push(Span::styled(
+                    masker.mask(&setting.value(&state.draft)),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let footer = match state.error_message {
+        Some(ref error) => Line::from(format!(" {} ", masker.mask(error)))
+            .style(Style::default().fg(theme::ERROR_COLOR)),
+        None => Line::from(" Enter edit/toggle · w write config.toml · Esc close ")
+            .style(Style::default().fg(theme::TEXT_DIM)),
+    };
+    let title = if state.dirty {
+        " Settings (unsaved) "
+    } else {
+        " Settings "
+    };
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .title_bottom(footer)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_and_toggle() {
+        let mut state = SettingsState::new(&AppConfig::default());
+
+        state.selected = Setting::ALL
+            .iter()
+            .position(|&s| s == Setting::HostsPercent)
+            .unwrap();
+        state.activate();
+        state.editing = Some(TextInput::new("95"));
+        state.activate();
+        assert!(state.error_message.is_some());
+        assert_eq!(state.draft.ui.hosts_percent, 35);
+
+        state.editing = Some(TextInput::new("40"));
+        state.activate();
+        assert!(state.editing.is_none());
+        assert_eq!(state.draft.ui.hosts_percent, 40);
+
+        state.selected = Setting::ALL
+            .iter()
+            .position(|&s| s == Setting::AutoRestore)
+            .unwrap();
+        state.activate();
+        assert!(state.draft.general.auto_restore);
+        assert!(state.dirty);
+
+        let changed = Setting::changed(&AppConfig::default(), &state.draft);
+        let changed: Vec<_> = changed
+            .iter()
+            .map(|(key, value)| format!("{key} = {value}"))
+            .collect();
+        assert_eq!(
+            changed,
+            ["general.auto_restore = true", "ui.hosts_percent = 40"]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// How to resolve a control socket already used by another alias's master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionChoice {
+    /// Attach to the running master.
+    Reuse,
+    /// Start a master on a socket named after this alias.
+    SeparateSocket,
+}
+
+impl CollisionChoice {
+    pub const ALL: [CollisionChoice; 2] = [CollisionChoice::Reuse, CollisionChoice::SeparateSocket];
+}
+
+#[derive(Debug, Clone)]
+pub struct SocketCollisionState {
+    /// Index into `App::hosts` of the host being connected.
+    pub host_idx: usize,
+    /// Alias that started the running master.
+    pub owner: String,
+    pub selected: usize,
+}
+
+impl SocketCollisionState {
+    pub fn new(host_idx: usize, owner: String) -> Self {
+        Self {
+            host_idx,
+            owner,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1).min(CollisionChoice::ALL.len() - 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn choice(&self) -> CollisionChoice {
+        CollisionChoice::ALL[self.selected]
+    }
+}
+
+pub fn render(frame: &mut Frame, state: &SocketCollisionState, host_name: &str, masker: &Masker) {
+    let area = frame.area();
+
+    let [modal_area] = Layout::horizontal([Constraint::Length(56)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] =
+        Layout::vertical([Constraint::Length(CollisionChoice::ALL.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let items: Vec<ListItem> = CollisionChoice::ALL
+        .iter()
+        .map(|choice| {
+            let label = match choice {
+                CollisionChoice::Reuse => format!(" Reuse the master started by {}", state.owner),
+                CollisionChoice::SeparateSocket => {
+                    format!(" Start a separate master for {host_name}")
+                }
+            };
+            ListItem::new(Line::from(masker.mask(&label)))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(" Control socket already in use ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme::WARNING)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(theme::HIGHLIGHT_BG)
+                .fg(theme::HIGHLIGHT_FG),
+        );
+
+    let mut list_state = ListState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(list, modal_area, &mut list_state);
+}
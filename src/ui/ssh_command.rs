@@ -0,0 +1,52 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::ui::safe_mode::Masker;
+use crate::ui::theme;
+
+/// The plain `ssh` command equivalent to the connection and its enabled
+/// tunnels, as copied to the clipboard.
+#[derive(Debug, Clone)]
+pub struct SshCommandState {
+    pub command: String,
+    /// Why copying failed, if it did.
+    pub copy_error: Option<String>,
+}
+
+pub fn render(frame: &mut Frame, state: &SshCommandState, host_name: &str, masker: &Masker) {
+    let area = frame.area();
+
+    let width = area.width.saturating_sub(4).min(100);
+    let command = masker.mask(&state.command);
+    let text_width = usize::from(width.saturating_sub(2)).max(1);
+    let lines = command.chars().count().div_ceil(text_width) as u16;
+    let height = (lines + 4).min(area.height.saturating_sub(2));
+    let [modal_area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [modal_area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(modal_area);
+
+    frame.render_widget(Clear, modal_area);
+
+    let status = match state.copy_error {
+        Some(ref e) => Line::from(e.as_str()).style(Style::default().fg(theme::ERROR_COLOR)),
+        None => Line::from("Copied to clipboard").style(Style::default().fg(theme::SUCCESS)),
+    };
+    let text = vec![Line::from(command), Line::from(""), status];
+
+    let paragraph = Paragraph::new(text).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(format!(" ssh command for {host_name} "))
+            .title_bottom(Line::from(" Esc close ").style(Style::default().fg(theme::TEXT_DIM)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme::BORDER_FOCUSED)),
+    );
+    frame.render_widget(paragraph, modal_area);
+}
@@ -7,21 +7,22 @@ use ratatui::{
 };
 
 use crate::app::{App, ConnectionStatus, NotificationLevel};
+use crate::ui::safe_mode::Masker;
 use crate::ui::theme;
 
-pub fn render(frame: &mut Frame, area: Rect, app: &App) {
+pub fn render(frame: &mut Frame, area: Rect, app: &App, masker: &Masker) {
     let [status_area, hints_area] =
         Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(area);
 
     // Left: notification or connection status
-    let status_line = if let Some(ref notif) = app.notification {
+    let mut status_line = if let Some(ref notif) = app.notification {
         let color = match notif.level {
             NotificationLevel::Success => theme::SUCCESS,
             NotificationLevel::Error => theme::ERROR_COLOR,
             NotificationLevel::Info => theme::INFO,
         };
         Line::from(Span::styled(
-            format!(" {}", notif.message),
+            format!(" {}", masker.mask(&notif.message)),
             Style::default().fg(color),
         ))
     } else {
@@ -34,18 +35,61 @@ pub fn render(frame: &mut Frame, area: Rect, app: &App) {
                 " Connecting...",
                 Style::default().fg(theme::HIGHLIGHT_FG),
             )),
-            ConnectionStatus::Connected(name) => Line::from(vec![
-                Span::styled(" Connected to ", Style::default().fg(theme::CONNECTED)),
-                Span::styled(
-                    name,
-                    Style::default()
-                        .fg(theme::CONNECTED)
-                        .add_modifier(Modifier::BOLD),
-                ),
-            ]),
+            ConnectionStatus::Connected(name) => {
+                let up = app.tunnels.iter().filter(|t| t.enabled).count();
+                let agent = app
+                    .connection
+                    .as_ref()
+                    .is_some_and(|c| c.host().forward_agent);
+                let mut line = Line::from(vec![
+                    Span::styled(" Connected to ", Style::default().fg(theme::CONNECTED)),
+                    Span::styled(
+                        name,
+                        Style::default()
+                            .fg(theme::CONNECTED)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("  {up}/{} tunnels up", app.tunnels.len()),
+                        Style::default().fg(theme::TEXT_DIM),
+                    ),
+                ]);
+                if agent {
+                    line.push_span(Span::styled(
+                        "  agent forwarded",
+                        Style::default().fg(theme::WARNING),
+                    ));
+                }
+                match app.mux_stats() {
+                    Some(stats) if stats.near_limit() => line.push_span(Span::styled(
+                        format!(
+                            "  shared by {} sessions (limit {})",
+                            stats.sessions, stats.max_sessions
+                        ),
+                        Style::default().fg(theme::ERROR_COLOR),
+                    )),
+                    _ if app.shared_sessions > 0 => line.push_span(Span::styled(
+                        format!("  shared by {} sessions", app.shared_sessions),
+                        Style::default().fg(theme::WARNING),
+                    )),
+                    _ => {}
+                }
+                if let Some(ref note) = app.session_note {
+                    line.push_span(Span::styled(
+                        format!("  ✎ {}", masker.mask(note)),
+                        Style::default().fg(theme::INFO),
+                    ));
+                }
+                line
+            }
+            ConnectionStatus::Disconnecting(name) => Line::from(Span::styled(
+                format!(" Disconnecting from {name}..."),
+                Style::default().fg(theme::WARNING),
+            )),
             ConnectionStatus::Error(msg) => {
-                let display_msg = if msg.len() > 45 {
-                    format!(" Error: {}...", &msg[..42])
+                let msg = masker.mask(msg);
+                let display_msg = if msg.chars().count() > 45 {
+                    format!(" Error: {}...", msg.chars().take(42).collect::<String>())
                 } else {
                     format!(" Error: {msg}")
                 };
@@ -57,6 +101,17 @@ pub fn render(frame: &mut Frame, area: Rect, app: &App) {
         }
     };
 
+    if masker.is_enabled() {
+        status_line.spans.insert(
+            0,
+            Span::styled(
+                " SAFE",
+                Style::default()
+                    .fg(theme::WARNING)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        );
+    }
     frame.render_widget(Paragraph::new(status_line), status_area);
 
     // Right: keyboard hints
@@ -72,6 +127,17 @@ pub fn render(frame: &mut Frame, area: Rect, app: &App) {
             Span::styled("Enter", bold),
             Span::styled(" Confirm", dim),
         ]
+    } else if app.tunnel_marks.is_some() {
+        vec![
+            Span::styled("Space", bold),
+            Span::styled(" Mark  ", dim),
+            Span::styled("t", bold),
+            Span::styled(" Toggle  ", dim),
+            Span::styled("d", bold),
+            Span::styled(" Delete  ", dim),
+            Span::styled("Esc", bold),
+            Span::styled(" Leave", dim),
+        ]
     } else {
         vec![
             Span::styled("j/k", bold),
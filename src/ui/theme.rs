@@ -6,6 +6,7 @@ pub const HIGHLIGHT_FG: Color = Color::White;
 pub const CONNECTED: Color = Color::Green;
 pub const DISCONNECTED: Color = Color::DarkGray;
 pub const ERROR_COLOR: Color = Color::Red;
+pub const WARNING: Color = Color::Yellow;
 pub const BORDER_FOCUSED: Color = Color::Cyan;
 pub const BORDER_UNFOCUSED: Color = Color::DarkGray;
 pub const TEXT_PRIMARY: Color = Color::White;
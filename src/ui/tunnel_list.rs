@@ -2,32 +2,56 @@ use ratatui::{
     layout::Rect,
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
     Frame,
 };
 
-use crate::ssh::tunnel::Tunnel;
+use crate::app::{App, Panel};
+use crate::ssh::health::TunnelTest;
+use crate::ssh::tunnel::{Tunnel, TunnelSort, TunnelStatus};
+use crate::ui::safe_mode::Masker;
 use crate::ui::theme;
 
-pub fn render(
-    frame: &mut Frame,
-    area: Rect,
-    focused: bool,
-    tunnels: &[Tunnel],
-    list_state: &mut ListState,
-) {
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+pub fn render(frame: &mut Frame, area: Rect, app: &mut App, masker: &Masker) {
+    let focused = app.active_panel == Panel::Tunnels;
+    let shown = app.visible_tunnel_indices();
+    let (sort, collapse_disabled) = (app.tunnel_sort, app.collapse_disabled);
+    let tick = app.tick_count;
+    let tunnels = &app.tunnels;
+    let marks = app.tunnel_marks.as_ref();
+    let list_state = &mut app.tunnel_list_state;
+    let rows_state = &mut app.tunnel_rows_state;
     let border_color = if focused {
         theme::BORDER_FOCUSED
     } else {
         theme::BORDER_UNFOCUSED
     };
 
-    let title = format!(" Tunnels ({}) ", tunnels.len());
+    let mut title = match sort {
+        TunnelSort::Usage => format!(" Tunnels ({}) ", tunnels.len()),
+        _ => format!(" Tunnels ({}) · by {} ", tunnels.len(), sort.label()),
+    };
+    if let Some(marks) = marks {
+        title.push_str(&format!("· VISUAL {} marked ", marks.len()));
+    }
 
-    let block = Block::default()
+    let mut block = Block::default()
         .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(border_color));
+    // The selected tunnel's note, on the bottom border
+    if let Some(note) = list_state
+        .selected()
+        .and_then(|s| tunnels.get(s))
+        .and_then(|t| t.note.as_deref())
+    {
+        block = block.title_bottom(
+            Line::from(format!(" ✎ {} ", masker.mask(note)))
+                .style(Style::default().fg(theme::INFO)),
+        );
+    }
 
     if tunnels.is_empty() {
         let text =
@@ -37,31 +61,210 @@ pub fn render(
         return;
     }
 
-    let items: Vec<ListItem> = tunnels
+    // Off tunnels hidden by `c`; folded groups say what they hold themselves
+    let hidden = tunnels
         .iter()
-        .map(|tunnel| {
-            let status = if tunnel.enabled {
+        .filter(|t| {
+            collapse_disabled
+                && !t.enabled
+                && !t
+                    .group
+                    .as_ref()
+                    .is_some_and(|g| app.collapsed_groups.contains(g))
+        })
+        .count();
+
+    let tunnel_item = |tunnel: &Tunnel| {
+        let status = match tunnel.status() {
+            TunnelStatus::Off if app.forward_queue.contains(&tunnel.id) => {
+                Span::styled("[QUE]", Style::default().fg(theme::WARNING))
+            }
+            TunnelStatus::Active => Span::styled(
+                "[ON]",
+                Style::default()
+                    .fg(theme::CONNECTED)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            // Spins until the request resolves; the tunnel is locked meanwhile
+            TunnelStatus::Starting | TunnelStatus::Stopping => {
+                let frame = SPINNER[tick as usize % SPINNER.len()];
+                Span::styled(format!("[ {frame} ]"), Style::default().fg(theme::WARNING))
+            }
+            TunnelStatus::Failed(_) => {
+                Span::styled("[ERR]", Style::default().fg(theme::ERROR_COLOR))
+            }
+            TunnelStatus::Off => Span::styled("[OFF]", Style::default().fg(theme::TEXT_DIM)),
+        };
+
+        let mut spans = Vec::new();
+        if let Some(marks) = marks {
+            spans.push(if marks.contains(&tunnel.id) {
                 Span::styled(
-                    "[ON] ",
+                    "● ",
                     Style::default()
-                        .fg(theme::CONNECTED)
+                        .fg(theme::WARNING)
                         .add_modifier(Modifier::BOLD),
                 )
             } else {
-                Span::styled("[OFF]", Style::default().fg(theme::TEXT_DIM))
+                Span::raw("  ")
+            });
+        }
+        spans.push(status);
+        // Liveness dot, filling the fifth column of `[ON]`
+        if tunnel.status() == TunnelStatus::Active {
+            spans.push(match tunnel.alive {
+                Some(true) => Span::styled("●", Style::default().fg(theme::CONNECTED)),
+                Some(false) => Span::styled("●", Style::default().fg(theme::ERROR_COLOR)),
+                None => Span::styled("○", Style::default().fg(theme::TEXT_DIM)),
+            });
+        }
+        spans.push(Span::styled(
+            format!(" {}  ", tunnel.direction.tag()),
+            Style::default().fg(theme::TEXT_PRIMARY),
+        ));
+        if let Some(ref label) = tunnel.label {
+            spans.push(Span::styled(
+                format!("{} ", masker.mask(label)),
+                Style::default()
+                    .fg(theme::TEXT_PRIMARY)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        spans.push(Span::styled(
+            masker.mask(&tunnel.describe()),
+            Style::default().fg(theme::TEXT_PRIMARY),
+        ));
+        if tunnel.ephemeral {
+            spans.push(Span::styled(" [tmp]", Style::default().fg(theme::INFO)));
+        }
+        if let (false, Some(since)) = (tunnel.pending, tunnel.up_since) {
+            let secs = (chrono::Utc::now() - since).num_seconds().max(0) as u64;
+            spans.push(Span::styled(
+                format!("  up {}", format_uptime(secs)),
+                Style::default().fg(theme::TEXT_DIM),
+            ));
+        }
+        if let (true, Some(ms)) = (tunnel.enabled, tunnel.open_ms) {
+            spans.push(Span::styled(
+                format!("  open {ms}ms"),
+                Style::default().fg(theme::TEXT_DIM),
+            ));
+        }
+        if let (true, Some(traffic)) = (tunnel.enabled, tunnel.traffic) {
+            let (up, down) = tunnel.throughput;
+            spans.push(Span::styled(
+                format!("  ↑{}/s ↓{}/s", format_bytes(up), format_bytes(down)),
+                Style::default().fg(theme::INFO),
+            ));
+            spans.push(Span::styled(
+                format!(" · {}", format_bytes(traffic.sent + traffic.received)),
+                Style::default().fg(theme::TEXT_DIM),
+            ));
+        }
+        if let (true, Some(clients @ 1..)) = (tunnel.enabled, tunnel.clients) {
+            let noun = if clients == 1 { "client" } else { "clients" };
+            spans.push(Span::styled(
+                format!("  {clients} {noun}"),
+                Style::default().fg(theme::WARNING),
+            ));
+        }
+        if tunnel.note.is_some() {
+            spans.push(Span::styled(" ✎", Style::default().fg(theme::TEXT_DIM)));
+        }
+        if let Some(schedule) = tunnel.schedule {
+            spans.push(Span::styled(
+                format!(" ◷{schedule}"),
+                Style::default().fg(theme::TEXT_DIM),
+            ));
+        }
+        if let Some(rate) = tunnel.bandwidth_limit {
+            spans.push(Span::styled(
+                format!(" ⇅{}/s", format_bytes(rate)),
+                Style::default().fg(theme::TEXT_DIM),
+            ));
+        }
+        if let Some(mins) = tunnel.idle_timeout_mins {
+            spans.push(Span::styled(
+                format!(" ⏱{mins}m"),
+                Style::default().fg(theme::TEXT_DIM),
+            ));
+        }
+        if let (true, Some(test)) = (tunnel.enabled, &tunnel.test) {
+            let color = match test {
+                TunnelTest::Passed { .. } => theme::CONNECTED,
+                TunnelTest::Failed(_) => theme::ERROR_COLOR,
             };
+            spans.push(Span::styled(
+                format!("  {}", masker.mask(&test.badge())),
+                Style::default().fg(color),
+            ));
+        }
+        if let TunnelStatus::Failed(error) = tunnel.status() {
+            spans.push(Span::styled(
+                format!("  {} (R to retry)", masker.mask(&error)),
+                Style::default().fg(theme::ERROR_COLOR),
+            ));
+        }
+        if let (true, Some(warning)) = (tunnel.enabled, &tunnel.warning) {
+            spans.push(Span::styled(
+                format!("  ⚠ {}", masker.mask(warning)),
+                Style::default().fg(theme::WARNING),
+            ));
+        }
+        ListItem::new(Line::from(spans))
+    };
 
-            let spec = format!(
-                " L  {} → {}:{}",
-                tunnel.local_port, tunnel.remote_host, tunnel.remote_port
-            );
-
-            ListItem::new(Line::from(vec![
-                status,
-                Span::styled(spec, Style::default().fg(theme::TEXT_PRIMARY)),
-            ]))
-        })
-        .collect();
+    // Each row and the tunnel it selects; group headers select nothing,
+    // a folded group's header stands for its first tunnel
+    let mut items: Vec<ListItem> = Vec::new();
+    let mut rows: Vec<Option<usize>> = Vec::new();
+    let mut current_group = None;
+    for &i in &shown {
+        let tunnel = &tunnels[i];
+        if tunnel.group.is_some() && tunnel.group != current_group {
+            current_group = tunnel.group.clone();
+            let group = tunnel.group.as_deref().unwrap_or_default();
+            let members = tunnels.iter().filter(|t| t.group.as_deref() == Some(group));
+            let (total, on) = members.fold((0, 0), |(total, on), t| {
+                (total + 1, on + usize::from(t.enabled))
+            });
+            let folded = app.collapsed_groups.contains(group);
+            let (arrow, tail) = if folded {
+                ("▸", format!("  {total} tunnels, {on} on · f to expand"))
+            } else {
+                ("▾", format!("  {on}/{total} on"))
+            };
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{arrow} {group}"),
+                    Style::default()
+                        .fg(theme::INFO)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(tail, Style::default().fg(theme::TEXT_DIM)),
+            ])));
+            if folded {
+                rows.push(Some(i));
+                continue;
+            }
+            rows.push(None);
+        } else if tunnel.group.is_none() {
+            current_group = None;
+        }
+        items.push(tunnel_item(tunnel));
+        rows.push(Some(i));
+    }
+    if hidden > 0 {
+        let summary = if shown.is_empty() {
+            format!("{hidden} tunnel(s) (off) · c to expand")
+        } else {
+            format!("… and {hidden} more (off) · c to expand")
+        };
+        items.push(ListItem::new(Line::from(Span::styled(
+            summary,
+            Style::default().fg(theme::TEXT_DIM),
+        ))));
+    }
 
     let list = List::new(items)
         .block(block)
@@ -72,5 +275,59 @@ pub fn render(
         )
         .highlight_symbol("▶ ");
 
-    frame.render_stateful_widget(list, area, list_state);
+    if rows.iter().enumerate().any(|(row, &i)| i != Some(row)) {
+        // Selection indexes `tunnels`; map it to the row it is shown on
+        let row = list_state
+            .selected()
+            .and_then(|s| rows.iter().position(|&i| i == Some(s)));
+        rows_state.select(row);
+        frame.render_stateful_widget(list, area, rows_state);
+    } else {
+        frame.render_stateful_widget(list, area, list_state);
+    }
+}
+
+/// Elapsed time in its two largest units, e.g. `42s`, `13m`, `2h13m`, `3d4h`.
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{mins}m"),
+        3600..86400 => format!("{hours}h{mins:02}m"),
+        _ => format!("{days}d{hours}h"),
+    }
+}
+
+/// Byte count with a binary-prefix unit, e.g. `1.5K`, `320M`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || value >= 10.0 {
+        format!("{value:.0}{}", UNITS[unit])
+    } else {
+        format!("{value:.1}{}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(0), "0s");
+        assert_eq!(format_uptime(59), "59s");
+        assert_eq!(format_uptime(60), "1m");
+        assert_eq!(format_uptime(3599), "59m");
+        assert_eq!(format_uptime(3600), "1h00m");
+        assert_eq!(format_uptime(3600 + 5 * 60 + 30), "1h05m");
+        assert_eq!(format_uptime(86399), "23h59m");
+        assert_eq!(format_uptime(86400), "1d0h");
+        assert_eq!(format_uptime(3 * 86400 + 7 * 3600 + 59), "3d7h");
+    }
 }